@@ -0,0 +1,180 @@
+//! Generates the table of characters searched by the unicode provider, from the
+//! Unicode character names & CLDR emoji annotations in `data/`, which are under
+//! the license in `data/LICENSE-UNICODE`. `data/update.sh` downloads them.
+//!
+//! The table is written to `$OUT_DIR/unicode.txt`, one character per line:
+//! `text \t name \t keywords \t flags`, where the flags are `e` for emoji and
+//! `t` for emoji which take a skin tone.
+
+use std::collections::{BTreeMap, HashSet};
+use std::fmt::Write;
+use std::path::Path;
+
+const UNICODE_DATA: &str = "data/UnicodeData.txt";
+const EMOJI_DATA: &str = "data/emoji-data.txt";
+/// Holds CLDR annotation files, like `annotations-en.xml` & the
+/// `annotationsDerived-en.xml` of emoji sequences, copied from CLDR's
+/// `common/annotations/en.xml` & `common/annotationsDerived/en.xml`.
+const ANNOTATIONS: &str = "data/cldr";
+
+#[derive(Default)]
+struct Entry {
+    name: String,
+    keywords: Vec<String>,
+    emoji: bool,
+}
+
+fn main() {
+    for path in [UNICODE_DATA, EMOJI_DATA, ANNOTATIONS] {
+        println!("cargo:rerun-if-changed={}", path);
+    }
+
+    // Keyed by text, ordered by code point.
+    let mut entries: BTreeMap<Vec<char>, Entry> = BTreeMap::new();
+
+    for line in read(UNICODE_DATA).lines() {
+        let mut fields = line.split(';');
+        let (Some(cp), Some(name)) = (fields.next(), fields.next()) else {
+            continue;
+        };
+        // Controls & ranges have no name of their own.
+        if name.starts_with('<') {
+            continue;
+        }
+        if let Some(c) = u32::from_str_radix(cp, 16).ok().and_then(char::from_u32) {
+            let entry = entries.entry(vec![c]).or_default();
+            entry.name = name.to_lowercase();
+        }
+    }
+
+    let mut files: Vec<_> = std::fs::read_dir(ANNOTATIONS)
+        .unwrap_or_else(|e| panic!("failed to read {}: {}", ANNOTATIONS, e))
+        .map(|entry| entry.unwrap().path())
+        .filter(|path| path.extension().map_or(false, |e| e == "xml"))
+        .collect();
+    files.sort();
+    let xml: String = files
+        .iter()
+        .map(|path| read(path.to_str().unwrap()))
+        .collect();
+    for (cp, tts, text) in annotations(&xml) {
+        // Annotations leave out the variation selector that asks for emoji style.
+        let chars: Vec<char> = cp.chars().filter(|c| *c != '\u{FE0F}').collect();
+        // Skin tones are added to the emoji which take one when searching.
+        if chars.len() > 1
+            && chars
+                .iter()
+                .any(|c| ('\u{1F3FB}'..='\u{1F3FF}').contains(c))
+        {
+            continue;
+        }
+        let entry = entries.entry(chars).or_default();
+        entry.emoji = true;
+        if tts {
+            // Prefer the short CLDR name, keeping the formal one searchable.
+            if !entry.name.is_empty() {
+                let formal = std::mem::take(&mut entry.name);
+                entry.keywords.push(formal);
+            }
+            entry.name = text;
+        } else {
+            entry
+                .keywords
+                .extend(text.split('|').map(|k| k.trim().to_lowercase()));
+        }
+    }
+
+    let tones = modifier_bases(&read(EMOJI_DATA));
+
+    let mut table = String::new();
+    for (chars, entry) in entries.iter() {
+        if entry.name.is_empty() {
+            continue;
+        }
+        let text: String = chars.iter().collect();
+        let mut keywords = entry.keywords.clone();
+        keywords.retain(|k| !k.is_empty() && *k != entry.name);
+        keywords.dedup();
+
+        let mut flags = String::new();
+        if entry.emoji {
+            flags.push('e');
+        }
+        if chars.len() == 1 && tones.contains(&chars[0]) {
+            flags.push('t');
+        }
+        writeln!(
+            table,
+            "{}\t{}\t{}\t{}",
+            text,
+            clean(&entry.name),
+            clean(&keywords.join("|")),
+            flags
+        )
+        .unwrap();
+    }
+
+    let out = std::env::var("OUT_DIR").unwrap();
+    std::fs::write(Path::new(&out).join("unicode.txt"), table).unwrap();
+}
+
+fn read(path: &str) -> String {
+    std::fs::read_to_string(path).unwrap_or_else(|e| panic!("failed to read {}: {}", path, e))
+}
+
+/// Keeps tabs & newlines out of a table field.
+fn clean(s: &str) -> String {
+    s.replace(['\t', '\n'], " ")
+}
+
+/// Returns the `(cp, is_tts, text)` of each `<annotation>` in a CLDR
+/// annotations file.
+fn annotations(xml: &str) -> Vec<(String, bool, String)> {
+    let mut found = vec![];
+    for line in xml.lines().map(str::trim) {
+        let Some(rest) = line.strip_prefix("<annotation cp=\"") else {
+            continue;
+        };
+        let Some((cp, rest)) = rest.split_once('"') else {
+            continue;
+        };
+        let Some((attrs, rest)) = rest.split_once('>') else {
+            continue;
+        };
+        let Some(text) = rest.strip_suffix("</annotation>") else {
+            continue;
+        };
+        found.push((unescape(cp), attrs.contains("type=\"tts\""), unescape(text)));
+    }
+    found
+}
+
+fn unescape(s: &str) -> String {
+    s.replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&apos;", "'")
+        .replace("&amp;", "&")
+}
+
+/// Returns the characters with the Emoji_Modifier_Base property.
+fn modifier_bases(data: &str) -> HashSet<char> {
+    let mut bases = HashSet::new();
+    for line in data.lines() {
+        let line = line.split('#').next().unwrap_or_default();
+        let Some((range, property)) = line.split_once(';') else {
+            continue;
+        };
+        if property.trim() != "Emoji_Modifier_Base" {
+            continue;
+        }
+        let range = range.trim();
+        let (start, end) = range.split_once("..").unwrap_or((range, range));
+        let (Ok(start), Ok(end)) = (u32::from_str_radix(start, 16), u32::from_str_radix(end, 16))
+        else {
+            continue;
+        };
+        bases.extend((start..=end).filter_map(char::from_u32));
+    }
+    bases
+}
@@ -0,0 +1,13 @@
+#!/usr/bin/env bash
+# Downloads the full Unicode 14.0 character data & the annotations of CLDR 40,
+# its matching release, which build.rs generates the unicode provider's table from.
+set -eux
+cd "$(dirname "$0")"
+
+UCD=https://www.unicode.org/Public/14.0.0/ucd
+CLDR=https://raw.githubusercontent.com/unicode-org/cldr/release-40
+
+curl -fsSL -o UnicodeData.txt "$UCD/UnicodeData.txt"
+curl -fsSL -o emoji-data.txt "$UCD/emoji/emoji-data.txt"
+curl -fsSL -o cldr/annotations-en.xml "$CLDR/common/annotations/en.xml"
+curl -fsSL -o cldr/annotationsDerived-en.xml "$CLDR/common/annotationsDerived/en.xml"
@@ -1,14 +1,84 @@
 use egui::TextEdit;
 use std::collections::HashMap;
 use std::path::PathBuf;
+use std::sync::mpsc::Receiver;
 use std::sync::{Arc, Mutex};
+use std::time::Duration;
 
 use fuzzy_matcher::skim::SkimMatcherV2;
 use fuzzy_matcher::FuzzyMatcher;
 
+use crate::cli::Args;
+use crate::config::Config;
+use crate::daemon::Command;
+use crate::dmenu::{DmenuOptions, DmenuProvider};
+use crate::executor::Executor;
 use crate::ext::{EquationExtra, ImmediateExtra, NumFormatExtra};
+use crate::favourites::{calculation_query, Favourites};
+use crate::headless::{ExtraRow, QueryOutput, QueryResult};
+use crate::history::History;
+use crate::keymap::{Cursor, KeyAction, Keymap, Mode};
+use crate::provider::{Action, Provider, ResultItem};
+use crate::theme::Theme;
+use crate::window::WindowCommand;
 
-const ICON_SIZE: [usize; 2] = [32, 32];
+/// Identifies the search box, whose text cursor is moved when recalling history.
+const INPUT_ID: &str = "input";
+
+/// How a key moves the selection through the results.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Movement {
+    Next,
+    Previous,
+    PageDown,
+    PageUp,
+    First,
+    Last,
+}
+
+/// Returns the scroll offset which brings the row into view, or None if it's
+/// already in view.
+fn scroll_to_row(row: usize, row_stride: f32, offset: f32, height: f32) -> Option<f32> {
+    let top = row as f32 * row_stride;
+    if top < offset {
+        Some(top)
+    } else if top + row_stride > offset + height {
+        Some(top + row_stride - height)
+    } else {
+        None
+    }
+}
+
+/// Starts the applications & actions chosen in the launcher. Tests substitute
+/// their own to see what would be started.
+pub trait Backend {
+    fn launch(&self, app: &crate::sys_apps::App, quit: bool);
+    fn run(&self, action: &Action, quit: bool);
+}
+
+/// Starts applications & actions for real.
+#[derive(Debug, Default)]
+struct SystemBackend;
+
+impl Backend for SystemBackend {
+    fn launch(&self, app: &crate::sys_apps::App, quit: bool) {
+        app.run(quit)
+    }
+
+    fn run(&self, action: &Action, quit: bool) {
+        action.run(quit)
+    }
+}
+
+/// An action waiting to run, which can be cancelled until it does.
+struct Countdown {
+    /// Describes what's about to happen, like "Rebooting".
+    text: String,
+    seconds: u64,
+    action: Action,
+    /// Input time the action runs at, set on the first frame it's shown.
+    deadline: Option<f64>,
+}
 
 /// We derive Deserialize/Serialize so we can persist app state on shutdown.
 #[derive(serde::Deserialize, serde::Serialize)]
@@ -22,16 +92,83 @@ pub struct Launcher {
     #[serde(skip)]
     applications: Arc<Mutex<Option<crate::sys_apps::AppList>>>,
     #[serde(skip)]
+    show_apps: bool,
+    #[serde(skip)]
     matching_app_idx: Option<Vec<usize>>,
     #[serde(skip)]
     selected_idx: usize,
     #[serde(skip)]
     icons: Arc<Mutex<HashMap<String, PathBuf>>>,
     #[serde(skip)]
-    matcher: SkimMatcherV2,
+    matcher: Arc<SkimMatcherV2>,
 
     #[serde(skip)]
     extras: Vec<Box<dyn ImmediateExtra>>,
+    /// Queries the providers of results other than the applications.
+    #[serde(skip)]
+    executor: Executor,
+    /// Results from providers, with the name of the provider.
+    #[serde(skip)]
+    provider_results: Vec<(&'static str, ResultItem)>,
+    /// The chosen action, if it's counting down to running.
+    #[serde(skip)]
+    countdown: Option<Countdown>,
+    /// Enter pressed while providers were still answering the input, so the
+    /// launch waits for them to answer or run out of time.
+    #[serde(skip)]
+    pending_launch: Option<(KeyAction, String)>,
+
+    /// Set when choosing between lines from stdin rather than launching apps.
+    #[serde(skip)]
+    dmenu: Option<DmenuOptions>,
+
+    #[serde(skip)]
+    config: Config,
+    /// Problems with the config file or the files it refers to.
+    #[serde(skip)]
+    config_errors: Vec<String>,
+    /// The OS theme preference the style was last configured for.
+    #[serde(skip)]
+    system_theme: Option<eframe::Theme>,
+
+    /// Commands from clients when running as a daemon.
+    #[serde(skip)]
+    commands: Option<Receiver<Command>>,
+    #[serde(skip)]
+    hidden: bool,
+
+    /// Changes to make to the window once the frame is drawn.
+    #[serde(skip)]
+    window_commands: Vec<WindowCommand>,
+    /// Whether the window had focus last frame.
+    #[serde(skip)]
+    was_focused: bool,
+    /// Whether the window has been moved to where the config puts it.
+    #[serde(skip)]
+    positioned: bool,
+
+    /// Pinned apps, launch counts & the calculations made, shown with the empty
+    /// query & under `=`.
+    favourites: Favourites,
+    /// Row of the pinned app being dragged to a new position.
+    #[serde(skip)]
+    dragged_pin: Option<usize>,
+    /// Submitted queries, recalled with Up & Down or searched with Ctrl+R.
+    history: History,
+
+    /// Whether the results should scroll to the selected row.
+    #[serde(skip)]
+    scroll_to_selected: bool,
+    /// Scroll offset & height of the results last frame.
+    #[serde(skip)]
+    results_scroll: (f32, f32),
+    /// Number of rows moved by PageUp & PageDown.
+    #[serde(skip)]
+    page_rows: usize,
+    #[serde(skip)]
+    keymap: Keymap,
+    #[serde(skip)]
+    backend: Box<dyn Backend>,
 }
 
 impl Default for Launcher {
@@ -42,21 +179,43 @@ impl Default for Launcher {
             focus_input: true,
             applications: Arc::new(Mutex::new(None)),
             icons: Arc::new(Mutex::new(HashMap::new())),
-            matcher: SkimMatcherV2::default(),
+            matcher: Arc::default(),
+            show_apps: true,
             matching_app_idx: None,
             selected_idx: 0,
-            extras: vec![
-                Box::new(NumFormatExtra::default()),
-                Box::new(EquationExtra::default()),
-            ],
+            extras: vec![],
+            executor: Executor::default(),
+            provider_results: vec![],
+            countdown: None,
+            pending_launch: None,
+            dmenu: None,
+            config: Config::default(),
+            config_errors: vec![],
+            system_theme: None,
+            commands: None,
+            hidden: false,
+            window_commands: vec![],
+            was_focused: false,
+            positioned: false,
+            favourites: Favourites::default(),
+            dragged_pin: None,
+            history: History::default(),
+            scroll_to_selected: false,
+            results_scroll: (0., 0.),
+            page_rows: 1,
+            keymap: Keymap::default(),
+            backend: Box::new(SystemBackend),
         }
     }
 }
 
 impl Launcher {
     /// Called once before the first frame.
-    pub fn new(cc: &eframe::CreationContext<'_>) -> Self {
-        configure_text_styles(&cc.egui_ctx);
+    pub fn new(cc: &eframe::CreationContext<'_>, config: Config) -> Self {
+        let system_theme = cc.integration_info.system_theme;
+        let config_errors = configure_style(&cc.egui_ctx, &config, system_theme)
+            .into_iter()
+            .collect();
         egui_extras::install_image_loaders(&cc.egui_ctx);
 
         // Load previous app state (if any).
@@ -72,33 +231,37 @@ impl Launcher {
         let icons = Arc::new(Mutex::new(HashMap::with_capacity(32)));
         let icons2 = icons.clone();
         let frame = cc.egui_ctx.clone();
+        let icon_size = config.icon_size;
+        let extra_dirs = config.apps.extra_dirs.clone();
 
         // When compiling natively, implement the applications list.
         #[cfg(not(target_arch = "wasm32"))]
         {
-            std::thread::spawn(move || match crate::sys_apps::AppList::new() {
-                Err(e) => println!("failed to load system applications: {:?}", e),
-                Ok(apps_list) => {
-                    {
-                        let mut data = applications2.lock().unwrap();
-                        *data = Some(apps_list.clone());
-                    }
-                    frame.request_repaint();
+            std::thread::spawn(move || {
+                match crate::sys_apps::AppList::with_extra_dirs(&extra_dirs) {
+                    Err(e) => eprintln!("failed to load system applications: {:?}", e),
+                    Ok(apps_list) => {
+                        {
+                            let mut data = applications2.lock().unwrap();
+                            *data = Some(apps_list.clone());
+                        }
+                        frame.request_repaint();
 
-                    // iterate through each app and attempt to load the icon.
-                    for (i, app) in apps_list.apps.iter().enumerate() {
-                        if let Some(path) = app.find_icon(ICON_SIZE[0] as u16) {
-                            {
-                                let mut data = icons2.lock().unwrap();
-                                (*data).insert(app.name.clone(), path);
+                        // iterate through each app and attempt to load the icon.
+                        for (i, app) in apps_list.apps.iter().enumerate() {
+                            if let Some(path) = app.find_icon(icon_size) {
+                                {
+                                    let mut data = icons2.lock().unwrap();
+                                    (*data).insert(app.name.clone(), path);
+                                }
+                            }
+                            // request redraw after every 12 entries
+                            if i % 12 == 0 {
+                                frame.request_repaint();
                             }
                         }
-                        // request redraw after every 12 entries
-                        if i % 12 == 0 {
-                            frame.request_repaint();
-                        }
+                        frame.request_repaint();
                     }
-                    frame.request_repaint();
                 }
             });
         }
@@ -106,10 +269,202 @@ impl Launcher {
         Self {
             icons,
             applications,
+            extras: extras_from_config(&config),
+            executor: Executor::new(
+                default_providers(&config),
+                Arc::default(),
+                Some(cc.egui_ctx.clone()),
+            ),
+            keymap: Keymap::new(&config.keys, config.on_escape).unwrap_or_default(),
+            config,
+            config_errors,
+            system_theme,
             ..prev_state
         }
     }
 
+    /// Adds a problem with the config file, shown at the top of the results.
+    pub fn with_config_error(mut self, error: Option<String>) -> Self {
+        self.config_errors.extend(error);
+        self
+    }
+
+    /// Keeps running in the background, acting on the commands received after
+    /// the one which first showed the window. Launching a result hides the
+    /// window rather than exiting.
+    pub fn with_commands(mut self, shown_by: Command, commands: Receiver<Command>) -> Self {
+        self.commands = Some(commands);
+        self.hide();
+        self.handle_command(shown_by);
+        self
+    }
+
+    fn show(&mut self) {
+        self.hidden = false;
+        self.window_commands
+            .extend([WindowCommand::SetVisible(true), WindowCommand::Focus]);
+        self.focus_input = true;
+        // The window may be on a different monitor to last time.
+        self.positioned = false;
+    }
+
+    /// Hides the window, resetting it for the next time it is shown. winit
+    /// can't hide windows under Wayland, so there it's only reset.
+    fn hide(&mut self) {
+        self.hidden = true;
+        self.was_focused = false;
+        self.window_commands.push(WindowCommand::SetVisible(false));
+        self.history.stop_browsing();
+        self.history.stop_search();
+        self.keymap.reset();
+        self.countdown = None;
+        self.input.clear();
+        self.selected_idx = 0;
+        self.handle_input_changed();
+    }
+
+    fn handle_command(&mut self, command: Command) {
+        match command {
+            Command::Show => self.show(),
+            Command::Hide => self.hide(),
+            Command::Toggle if self.hidden => self.show(),
+            Command::Toggle => self.hide(),
+            Command::Query(text) => {
+                self.input = text;
+                self.handle_input_changed();
+                self.show();
+            }
+        }
+    }
+
+    /// Creates a launcher without a UI, loading the apps up front.
+    pub fn new_headless(args: &Args) -> Self {
+        let (config, config_error) = Config::load(args.config.as_deref());
+        if let Some(e) = config_error {
+            eprintln!("{}", e);
+        }
+
+        let apps_list = match crate::sys_apps::AppList::with_extra_dirs(&config.apps.extra_dirs) {
+            Ok(apps_list) => apps_list,
+            Err(e) => {
+                eprintln!("failed to load system applications: {:?}", e);
+                crate::sys_apps::AppList::default()
+            }
+        };
+
+        let mut launcher = Self {
+            applications: Arc::new(Mutex::new(Some(apps_list))),
+            extras: extras_from_config(&config),
+            executor: Executor::new(default_providers(&config), Arc::default(), None),
+            keymap: Keymap::new(&config.keys, config.on_escape).unwrap_or_default(),
+            config,
+            ..Self::default()
+        }
+        .with_args(args);
+
+        // Loading every provider could mean walking the whole home directory, so
+        // only those asked for by name are waited on. The rest answer within
+        // their budgets with what they've loaded so far.
+        if !args.providers.is_empty() {
+            launcher.executor.wait_ready();
+        }
+        launcher
+    }
+
+    /// Applies the query and provider selection from the command line.
+    pub fn with_args(mut self, args: &Args) -> Self {
+        if let Some(query) = &args.query {
+            self.input = query.clone();
+        }
+
+        if !args.providers.is_empty() {
+            for name in args.providers.iter() {
+                if name != "apps" && !self.executor.names().contains(&name.as_str()) {
+                    eprintln!("unknown provider {:?}", name);
+                }
+            }
+
+            self.show_apps = args.providers.iter().any(|n| n == "apps");
+            self.executor
+                .retain(|name| args.providers.iter().any(|n| n == name));
+        }
+        self
+    }
+
+    /// Returns everything that would be shown for the input, without drawing it.
+    pub fn query(&mut self, input: &str) -> QueryOutput {
+        self.input = input.to_string();
+        self.handle_input_changed();
+        self.executor.wait();
+        self.update_results();
+
+        let extras = self
+            .extras
+            .iter_mut()
+            .flat_map(|e| e.text(&extra_input(e.as_ref(), &self.favourites, &self.input)))
+            .map(|(label, value)| ExtraRow { label, value })
+            .collect();
+
+        let mut results = vec![];
+        if let (Some(apps_list), Some(idxs)) =
+            (&*self.applications.lock().unwrap(), &self.matching_app_idx)
+        {
+            results.extend(idxs.iter().map(|i| QueryResult {
+                kind: "apps".into(),
+                title: apps_list.apps[*i].name.clone(),
+                subtitle: None,
+                score: None,
+            }));
+        }
+        results.extend(self.provider_results.iter().map(|(name, r)| QueryResult {
+            kind: name.to_string(),
+            title: r.title.clone(),
+            subtitle: r.subtitle.clone(),
+            score: Some(r.score),
+        }));
+
+        QueryOutput {
+            query: self.input.clone(),
+            extras,
+            results,
+        }
+    }
+
+    /// Creates a launcher which chooses between the given items, printing the
+    /// choice to stdout.
+    pub fn new_dmenu(
+        cc: &eframe::CreationContext<'_>,
+        config: Config,
+        options: DmenuOptions,
+        items: Vec<String>,
+    ) -> Self {
+        let system_theme = cc.integration_info.system_theme;
+        let config_errors = configure_style(&cc.egui_ctx, &config, system_theme)
+            .into_iter()
+            .collect();
+        egui_extras::install_image_loaders(&cc.egui_ctx);
+
+        let matcher = Arc::new(options.matcher());
+        let mut launcher = Self {
+            applications: Arc::new(Mutex::new(Some(crate::sys_apps::AppList::default()))),
+            matcher: matcher.clone(),
+            extras: vec![],
+            executor: Executor::new(
+                vec![Box::new(DmenuProvider::new(items))],
+                matcher,
+                Some(cc.egui_ctx.clone()),
+            ),
+            dmenu: Some(options),
+            keymap: Keymap::new(&config.keys, config.on_escape).unwrap_or_default(),
+            config,
+            config_errors,
+            system_theme,
+            ..Self::default()
+        };
+        launcher.handle_input_changed();
+        launcher
+    }
+
     fn compute_app_indices(
         matcher: &SkimMatcherV2,
         apps_list: &crate::sys_apps::AppList,
@@ -135,15 +490,65 @@ impl Launcher {
                 .map(|e| e.unwrap())
                 .collect();
 
-            idx_scores.sort_by(|a, b| a.1.partial_cmp(&b.1).unwrap());
+            // Best match first.
+            idx_scores.sort_by(|a, b| b.1.partial_cmp(&a.1).unwrap());
 
             idx_scores.into_iter().map(|e| e.0).collect()
         }
     }
 
     fn handle_input_changed(&mut self) {
+        // Searching the history lists only the queries matching the input.
+        if self.history.is_searching() {
+            if self.applications.lock().unwrap().is_some() {
+                self.matching_app_idx = Some(vec![]);
+            }
+            self.provider_results = self
+                .history
+                .search(&self.input)
+                .into_iter()
+                .map(|r| ("history", r))
+                .collect();
+            if self.result_count() <= self.selected_idx {
+                self.selected_idx = 0;
+            }
+            return;
+        }
+
+        self.executor.query(&self.input);
+        self.update_results();
+    }
+
+    /// Lists the apps matching the input, and the providers' results so far.
+    fn update_results(&mut self) {
+        if self.history.is_searching() {
+            return;
+        }
+
+        let exclusive_provider = self.executor.exclusive();
         self.matching_app_idx = match &*self.applications.lock().unwrap() {
             None => None,
+            Some(_) if !self.show_apps => Some(vec![]),
+            Some(_) if self.extras.iter().any(|e| e.exclusive(&self.input)) => Some(vec![]),
+            Some(_) if exclusive_provider => Some(vec![]),
+            Some(_) if self.dmenu.is_none() && calculation_query(&self.input).is_some() => {
+                Some(vec![])
+            }
+            Some(apps_list) if self.input.is_empty() && self.dmenu.is_none() => {
+                let idxs = self
+                    .favourites
+                    .app_indices(apps_list, crate::favourites::now());
+                // Until something is pinned or launched, there's nothing to curate.
+                if idxs.is_empty() && self.favourites.calculations.is_empty() {
+                    Some(Launcher::compute_app_indices(
+                        &self.matcher,
+                        apps_list,
+                        &self.input,
+                    ))
+                } else {
+                    Some(idxs)
+                }
+            }
             Some(apps_list) => Some(Launcher::compute_app_indices(
                 &self.matcher,
                 apps_list,
@@ -151,11 +556,136 @@ impl Launcher {
             )),
         };
 
-        if self.matching_app_idx.as_ref().map(|v| v.len()).unwrap_or(0) <= self.selected_idx {
+        self.provider_results = if self.extras.iter().any(|e| e.exclusive(&self.input)) {
+            vec![]
+        } else if let (Some(query), None) = (calculation_query(&self.input), &self.dmenu) {
+            self.favourites
+                .search_calculations(&self.matcher, query)
+                .into_iter()
+                .map(|r| ("calculations", r))
+                .collect()
+        } else {
+            let mut results = self.executor.results();
+            if self.input.is_empty() && self.dmenu.is_none() {
+                results.extend(
+                    self.favourites
+                        .calculation_results()
+                        .into_iter()
+                        .map(|r| ("calculations", r)),
+                );
+            }
+            results
+        };
+
+        // Apps must have loaded before we can say nothing matched.
+        if self.matching_app_idx.is_some() && self.result_count() == 0 {
+            self.provider_results = self.executor.fallback();
+        }
+
+        if self.result_count() <= self.selected_idx {
             self.selected_idx = 0;
         }
     }
 
+    /// Number of rows in the results list: matching apps, then provider results.
+    fn result_count(&self) -> usize {
+        self.matching_app_idx.as_ref().map(|v| v.len()).unwrap_or(0) + self.provider_results.len()
+    }
+
+    /// Returns the app in the selected row, if it's an app.
+    fn selected_app(&self) -> Option<crate::sys_apps::App> {
+        let idxs = self.matching_app_idx.as_ref()?;
+        let apps_list = self.applications.lock().unwrap();
+        let i = *idxs.get(self.selected_idx)?;
+        apps_list.as_ref().map(|list| list.apps[i].clone())
+    }
+
+    /// Launches the selected result, using its alternate action if alt is set.
+    /// Closes the window afterwards, or hides it when running as a daemon.
+    fn launch_selected(&mut self, alt: bool) {
+        let apps_len = self.matching_app_idx.as_ref().map(|v| v.len()).unwrap_or(0);
+        if let Some(app) = self.selected_app() {
+            self.favourites
+                .record_launch(&app, crate::favourites::now());
+            self.backend.launch(&app, false);
+        } else if let Some((_, item)) = self.provider_results.get(self.selected_idx - apps_len) {
+            let action = match (&item.alt_action, alt) {
+                (Some(alt_action), true) => alt_action,
+                _ => &item.action,
+            };
+            match action {
+                Action::SetInput(text) => {
+                    self.history.stop_search();
+                    self.input = text.clone();
+                    self.focus_input = true;
+                    self.handle_input_changed();
+                    return;
+                }
+                Action::Countdown(text, seconds, action) => {
+                    self.countdown = Some(Countdown {
+                        text: text.clone(),
+                        seconds: *seconds,
+                        action: (**action).clone(),
+                        deadline: None,
+                    });
+                    return;
+                }
+                _ => self.backend.run(action, false),
+            }
+        }
+        self.close();
+    }
+
+    /// Closes the window, or hides it when running as a daemon.
+    fn close(&mut self) {
+        // Closing rather than exiting lets eframe save the favourites.
+        if self.commands.is_some() {
+            self.hide();
+        } else {
+            self.window_commands.push(WindowCommand::Close);
+        }
+    }
+
+    /// Moves the selection, stopping at the first & last results, and scrolls to it.
+    fn select(&mut self, movement: Movement) {
+        let last = self.result_count().saturating_sub(1);
+        let page = self.page_rows.max(1);
+        self.selected_idx = match movement {
+            Movement::Next => (self.selected_idx + 1).min(last),
+            Movement::Previous => self.selected_idx.saturating_sub(1),
+            Movement::PageDown => (self.selected_idx + page).min(last),
+            Movement::PageUp => self.selected_idx.saturating_sub(page),
+            Movement::First => 0,
+            Movement::Last => last,
+        };
+        self.scroll_to_selected = true;
+    }
+
+    /// Returns the text of the selected result: the app's name, or what the
+    /// result would copy, or its title.
+    fn selected_text(&self) -> Option<String> {
+        if let Some(app) = self.selected_app() {
+            return Some(app.name);
+        }
+        let apps_len = self.matching_app_idx.as_ref().map_or(0, |v| v.len());
+        let (_, item) = self
+            .provider_results
+            .get(self.selected_idx.checked_sub(apps_len)?)?;
+        match &item.action {
+            Action::Copy(text) => Some(text.clone()),
+            _ => Some(item.title.clone()),
+        }
+    }
+
+    /// Pins the selected app, or unpins it if it was pinned.
+    fn toggle_selected_pin(&mut self) {
+        if let Some(app) = self.selected_app() {
+            self.favourites.toggle_pin(&app);
+            self.handle_input_changed();
+        }
+    }
+
+    /// Draws a row for the application, returning the response of its label.
     fn ui_for_app_entry(
         &self,
         app: &crate::sys_apps::App,
@@ -163,188 +693,1372 @@ impl Launcher {
         ctx: &egui::Context,
         ui: &mut egui::Ui,
         icons: &HashMap<String, PathBuf>,
-    ) {
+    ) -> egui::Response {
         ui.allocate_space(egui::Vec2::new(0., 2.));
 
         if let Some(icon_path) = icons.get(&app.name) {
             let uri = "file://".to_owned() + icon_path.to_str().unwrap();
             ui.add(
                 egui::Image::from_uri(uri)
-                    .fit_to_exact_size(egui::Vec2::new(ICON_SIZE[0] as f32, ICON_SIZE[1] as f32)),
+                    .fit_to_exact_size(egui::Vec2::splat(self.config.icon_size as f32)),
             );
         } else {
             //ui.label("🔆");
             ui.add(
                 egui::Image::new(egui::include_image!("../unknown-app.png"))
-                    .fit_to_exact_size(egui::Vec2::new(ICON_SIZE[0] as f32, ICON_SIZE[1] as f32)),
+                    .fit_to_exact_size(egui::Vec2::splat(self.config.icon_size as f32)),
             );
         }
 
-        let label = ui.selectable_label(selected, app.name.clone());
-        if label.clicked() {
-            app.run(true);
+        ui.horizontal(|ui| {
+            let response = ui.selectable_label(selected, app.name.clone());
+            if self.favourites.is_pinned(app) {
+                let hover = match self.keymap.chord(KeyAction::TogglePin) {
+                    Some(chord) => format!("Pinned, {} to unpin", chord),
+                    None => "Pinned".to_string(),
+                };
+                ui.weak("📌").on_hover_text(hover);
+            }
+            response
+        })
+        .inner
+    }
+
+    /// Lets the row of a pinned app be dragged onto another, marking where it
+    /// would go. Returns the rows to move from & to once it's dropped.
+    fn ui_for_pin_drag(
+        &self,
+        row: usize,
+        response: &egui::Response,
+        ui: &mut egui::Ui,
+    ) -> Option<(usize, usize)> {
+        let response = response.interact(egui::Sense::drag());
+        if response.drag_started() {
+            return Some((row, row));
         }
+
+        let from = self.dragged_pin?;
+        let pointer = ui.ctx().pointer_interact_pos()?;
+        let rect = response.rect;
+        let over = (rect.top()..rect.bottom()).contains(&pointer.y);
+        if over && from != row {
+            let y = if row < from {
+                rect.top()
+            } else {
+                rect.bottom()
+            };
+            let stroke = ui.visuals().selection.stroke;
+            ui.painter().hline(ui.max_rect().x_range(), y, stroke);
+        }
+        if over && !ui.ctx().input(|i| i.pointer.any_down()) {
+            return Some((from, row));
+        }
+        None
     }
-}
 
-impl eframe::App for Launcher {
-    /// Called by the frame work to save state before shutdown.
-    fn save(&mut self, storage: &mut dyn eframe::Storage) {
-        eframe::set_value(storage, eframe::APP_KEY, self);
+    /// Starts dragging a pinned app when from & to are the same row, otherwise
+    /// moves the dragged app to the row it was dropped on.
+    fn drop_pin(&mut self, from: usize, to: usize) {
+        if from == to {
+            self.dragged_pin = Some(from);
+            return;
+        }
+        self.dragged_pin = None;
+
+        let app = |row: usize| {
+            let idxs = self.matching_app_idx.as_ref()?;
+            let apps_list = self.applications.lock().unwrap();
+            Some(apps_list.as_ref()?.apps[*idxs.get(row)?].clone())
+        };
+        if let (Some(app), Some(target)) = (app(from), app(to)) {
+            self.favourites.move_pin(&app, &target);
+            self.selected_idx = to;
+            self.handle_input_changed();
+        }
     }
 
-    /// Called each time the UI needs repainting, which may be many times per second.
-    fn update(&mut self, ctx: &egui::Context, _frame: &mut eframe::Frame) {
-        egui::TopBottomPanel::top("top_panel").show(ctx, |ui| {
-            ui.allocate_space(egui::Vec2::new(0., 4.));
-            ui.horizontal(|ui| {
-                let fbc = ui.visuals().faint_bg_color;
-                ui.visuals_mut().extreme_bg_color = fbc;
-                ui.label("🔎");
-                let input = ui.add_sized(
-                    ui.available_size(),
-                    TextEdit::multiline(&mut self.input).desired_rows(1), // .hint_text("Start typing ...")
-                                                                          //.horizontal_align(egui::Align::Center)
-                );
-                if self.focus_input {
-                    self.focus_input = false;
-                    input.request_focus();
+    /// Puts the input's text cursor at the character index given, or at the end.
+    fn set_input_cursor(ctx: &egui::Context, index: Option<usize>, input: &str) {
+        let id = egui::Id::new(INPUT_ID);
+        let mut state = TextEdit::load_state(ctx, id).unwrap_or_default();
+        let cursor = egui::text::CCursor::new(index.unwrap_or(input.chars().count()));
+        state.set_ccursor_range(Some(egui::text::CCursorRange::one(cursor)));
+        state.store(ctx, id);
+    }
+
+    /// Returns the character index of the input's text cursor.
+    fn input_cursor(&self, ctx: &egui::Context) -> usize {
+        TextEdit::load_state(ctx, egui::Id::new(INPUT_ID))
+            .and_then(|state| state.ccursor_range())
+            .map_or(self.input.chars().count(), |range| range.primary.index)
+    }
+
+    /// Submits the input to the extras, or launches the selected result.
+    fn launch(&mut self, ctx: &egui::Context, action: KeyAction) {
+        if let Some(dmenu) = &self.dmenu {
+            // Without a match, what was typed is the choice.
+            if self.result_count() == 0 {
+                crate::dmenu::print_and_exit(&self.input);
+            }
+            if dmenu.print_query {
+                crate::dmenu::print(&self.input);
+            }
+        }
+        self.history.push(&self.input);
+
+        for e in self.extras.iter_mut() {
+            // With the answers filled in, so it means the same when recalled.
+            let input = extra_input(e.as_ref(), &self.favourites, &self.input);
+            if let Some(result) = e.result(&input) {
+                self.favourites.record_calculation(&input, &result);
+            }
+        }
+        let submitted = self.extras.iter_mut().any(|e| {
+            let input = extra_input(e.as_ref(), &self.favourites, &self.input);
+            e.submit(&input, ctx)
+        });
+        if !submitted && self.result_count() > 0 {
+            self.launch_selected(action == KeyAction::LaunchAlt);
+        }
+    }
+
+    /// Acts on a key bound in the keymap.
+    fn handle_key_action(&mut self, ctx: &egui::Context, action: KeyAction) {
+        let searching = self.history.is_searching();
+        match action {
+            KeyAction::Hide | KeyAction::Clear if self.countdown.is_some() => {
+                self.countdown = None;
+            }
+            // Like a shell: Up at the start of the input steps back through the
+            // history, & Ctrl+R searches it, again for the next older match.
+            KeyAction::SelectPrevious if !searching && self.selected_idx == 0 => {
+                if self.input_cursor(ctx) > 0 {
+                    Self::set_input_cursor(ctx, Some(0), &self.input);
+                } else if let Some(query) = self.history.older(&self.input) {
+                    let query = query.to_string();
+                    self.recall(ctx, query, Some(0));
+                }
+            }
+            KeyAction::SelectNext if !searching && self.history.is_browsing() => {
+                if let Some(query) = self.history.newer() {
+                    // Back to what was typed, the cursor goes back to the end.
+                    let cursor = self.history.is_browsing().then_some(0);
+                    self.recall(ctx, query, cursor);
                 }
+            }
+            KeyAction::SearchHistory if searching => self.select(Movement::Next),
+            KeyAction::SearchHistory if self.dmenu.is_none() => {
+                self.history.start_search(&self.input);
+                self.input.clear();
+                self.handle_input_changed();
+            }
+            KeyAction::SearchHistory => {}
 
-                let (down, up, enter) = if input.has_focus() {
-                    ui.input(|i| {
-                        (
-                            i.key_pressed(egui::Key::ArrowDown),
-                            i.key_pressed(egui::Key::ArrowUp),
-                            i.key_pressed(egui::Key::Enter),
-                        )
-                    })
+            KeyAction::SelectNext => self.select(Movement::Next),
+            KeyAction::SelectPrevious => self.select(Movement::Previous),
+            KeyAction::SelectPageDown => self.select(Movement::PageDown),
+            KeyAction::SelectPageUp => self.select(Movement::PageUp),
+            KeyAction::SelectFirst => self.select(Movement::First),
+            KeyAction::SelectLast => self.select(Movement::Last),
+
+            KeyAction::Launch | KeyAction::LaunchAlt if searching => {
+                if self.result_count() > 0 {
+                    self.launch_selected(false);
                 } else {
-                    (false, false, false)
-                };
+                    self.history.stop_search();
+                    self.handle_input_changed();
+                }
+                Self::set_input_cursor(ctx, None, &self.input);
+            }
+            KeyAction::Launch | KeyAction::LaunchAlt => {
+                // Choose from every result, not just those which have arrived.
+                match self.executor.time_left() {
+                    Some(_) => self.pending_launch = Some((action, self.input.clone())),
+                    None => self.launch(ctx, action),
+                }
+            }
+            KeyAction::CopyResult => {
+                if let Some(text) = self.selected_text() {
+                    ctx.output_mut(|o| o.copied_text = text);
+                }
+            }
+
+            KeyAction::DeleteWord => {
+                let (input, cursor) =
+                    crate::keymap::delete_word(&self.input, self.input_cursor(ctx));
+                Self::set_input_cursor(ctx, Some(cursor), &input);
+                self.input = input;
+                self.history.stop_browsing();
+                self.handle_input_changed();
+            }
+            KeyAction::Clear => {
+                self.input.clear();
+                self.history.stop_browsing();
+                self.handle_input_changed();
+            }
+            KeyAction::Hide if searching => {
+                self.input = self.history.stop_search();
+                Self::set_input_cursor(ctx, None, &self.input);
+                self.handle_input_changed();
+            }
+            KeyAction::Hide => match (&self.dmenu, &self.commands) {
+                // Scripts need to be able to tell a cancelled choice apart.
+                (Some(_), _) => std::process::exit(1),
+                (None, Some(_)) => self.hide(),
+                // Closing rather than exiting lets eframe save the history.
+                (None, None) => self.window_commands.push(WindowCommand::Close),
+            },
+
+            KeyAction::TogglePin => self.toggle_selected_pin(),
+            // The keymap switches mode itself.
+            KeyAction::InsertMode | KeyAction::NormalMode => {}
+        }
+    }
+
+    /// Replaces the input with a query recalled from the history.
+    fn recall(&mut self, ctx: &egui::Context, query: String, cursor: Option<usize>) {
+        Self::set_input_cursor(ctx, cursor, &query);
+        self.input = query;
+        self.selected_idx = 0;
+        self.handle_input_changed();
+    }
 
-                if ui.input(|i| i.key_pressed(egui::Key::Escape)) {
-                    std::process::exit(0);
+    /// Draws a row for the provider result, returning the response of its title.
+    fn ui_for_result_item(
+        &self,
+        item: &ResultItem,
+        selected: bool,
+        ui: &mut egui::Ui,
+    ) -> egui::Response {
+        ui.allocate_space(egui::Vec2::new(0., 2.));
+
+        ui.add_sized(
+            egui::Vec2::splat(self.config.icon_size as f32),
+            egui::Label::new(
+                egui::RichText::new(&item.glyph).size(self.config.icon_size as f32 * 0.7),
+            ),
+        );
+
+        ui.horizontal(|ui| {
+            let response = ui.selectable_label(selected, item.title.clone());
+            if let Some(subtitle) = &item.subtitle {
+                ui.weak(subtitle);
+            }
+            response
+        })
+        .inner
+    }
+
+    /// Draws the frame, leaving the changes to make to the window in
+    /// `window_commands`. This is all of `update` but what needs a real window.
+    fn update_ui(&mut self, ctx: &egui::Context, info: &eframe::IntegrationInfo) {
+        // eframe resets the visuals when the OS theme changes.
+        if info.system_theme != self.system_theme {
+            self.system_theme = info.system_theme;
+            Theme::from_config(&self.config.theme, info.system_theme)
+                .apply(ctx, &self.config.fonts);
+        }
+        self.ui(ctx, &info.window_info);
+    }
+
+    /// Draws the launcher, queueing any changes to the window.
+    fn ui(&mut self, ctx: &egui::Context, window: &eframe::WindowInfo) {
+        while let Some(command) = self.commands.as_ref().and_then(|c| c.try_recv().ok()) {
+            self.handle_command(command);
+        }
+
+        // Results can't be computed until the applications have loaded.
+        if self.matching_app_idx.is_none() && self.applications.lock().unwrap().is_some() {
+            self.handle_input_changed();
+        }
+        // Providers answer in their own time, so their results stream in.
+        if self.executor.poll() {
+            self.update_results();
+        }
+        // A launch waits for the results of the input it was pressed for.
+        if self.executor.time_left().is_none() {
+            if let Some((action, input)) = self.pending_launch.take() {
+                if input == self.input {
+                    self.launch(ctx, action);
                 }
-                if enter && self.matching_app_idx.as_ref().map(|v| v.len()).unwrap_or(0) > 0 {
-                    let apps_mutex = &*self.applications.lock().unwrap();
-                    match (apps_mutex, &self.matching_app_idx) {
-                        (Some(apps_list), Some(idxs)) => {
-                            if self.selected_idx < idxs.len() {
-                                apps_list.apps[idxs[self.selected_idx]].run(true);
-                            }
-                        }
-                        _ => {}
+            }
+        }
+        if let Some(left) = self.executor.time_left() {
+            ctx.request_repaint_after(left);
+        }
+        // Escape cancels, as the confirmation says, whatever it's bound to, as the
+        // vim preset takes it for normal mode.
+        if self.countdown.is_some()
+            && ctx.input_mut(|i| i.consume_key(egui::Modifiers::NONE, egui::Key::Escape))
+        {
+            self.countdown = None;
+        }
+        if let Some(countdown) = &mut self.countdown {
+            let now = ctx.input(|i| i.time);
+            let deadline = *countdown
+                .deadline
+                .get_or_insert(now + countdown.seconds as f64);
+            if now >= deadline {
+                let countdown = self.countdown.take().unwrap();
+                self.backend.run(&countdown.action, false);
+                self.close();
+            } else {
+                // Redraw when the seconds left tick down.
+                let left = deadline - now;
+                let tick = left - (left.ceil() - 1.);
+                ctx.request_repaint_after(Duration::from_secs_f64(tick));
+            }
+        }
+
+        // When transparent, round the top corners of the top panel and the bottom
+        // corners of the central panel, which together fill the window.
+        let style = ctx.style();
+        let (mut top_frame, mut central_frame) = (
+            egui::Frame::side_top_panel(&style),
+            egui::Frame::central_panel(&style),
+        );
+        if self.config.window.transparent {
+            let r = style.visuals.window_rounding;
+            top_frame.rounding = egui::Rounding {
+                nw: r.nw,
+                ne: r.ne,
+                ..Default::default()
+            };
+            central_frame.rounding = egui::Rounding {
+                sw: r.sw,
+                se: r.se,
+                ..Default::default()
+            };
+        }
+
+        egui::TopBottomPanel::top("top_panel")
+            .frame(top_frame)
+            .show(ctx, |ui| {
+                ui.allocate_space(egui::Vec2::new(0., 4.));
+                ui.horizontal(|ui| {
+                    let fbc = ui.visuals().faint_bg_color;
+                    ui.visuals_mut().extreme_bg_color = fbc;
+                    // Bound keys are taken before the input can act on them too. Escape
+                    // takes the focus away from the input, so it's taken back.
+                    let focus = ctx.memory(|m| m.focus());
+                    if focus.is_none() {
+                        self.focus_input = true;
                     }
-                    if self.input.ends_with("\n") {
-                        self.input.pop();
+                    let cursor = Cursor::new(&self.input, self.input_cursor(ctx));
+                    let actions = match focus.filter(|id| *id != egui::Id::new(INPUT_ID)) {
+                        None => ctx.input_mut(|i| self.keymap.take_actions(i, cursor)),
+                        Some(_) => vec![],
+                    };
+
+                    match self.dmenu.as_ref().and_then(|d| d.prompt.as_ref()) {
+                        _ if self.history.is_searching() => ui.label("⟲"),
+                        Some(prompt) => ui.label(prompt),
+                        None => ui.label("🔎"),
+                    };
+                    if self.keymap.mode() == Mode::Normal {
+                        ui.weak("NORMAL");
                     }
-                } else if input.changed() || down || up {
-                    self.handle_input_changed();
+                    let input = ui.add_sized(
+                        ui.available_size(),
+                        TextEdit::multiline(&mut self.input)
+                            .id(egui::Id::new(INPUT_ID))
+                            .desired_rows(1), // .hint_text("Start typing ...")
+                                              //.horizontal_align(egui::Align::Center)
+                    );
+                    if self.focus_input {
+                        self.focus_input = false;
+                        input.request_focus();
+                    }
+
+                    if input.changed() {
+                        self.history.stop_browsing();
+                        self.handle_input_changed();
+                    }
+                    for action in actions {
+                        self.handle_key_action(ctx, action);
+                    }
+                });
+                ui.allocate_space(egui::Vec2::new(0., 4.));
+            });
+
+        let fitted_height = egui::CentralPanel::default()
+            .frame(central_frame)
+            .show(ctx, |ui| {
+                for e in self.config_errors.iter() {
+                    ui.colored_label(ui.visuals().warn_fg_color, format!("⚠ {}", e));
                 }
-                if down || up {
-                    let length = self.matching_app_idx.as_ref().map(|v| v.len()).unwrap_or(0);
-                    if length > 0 {
-                        if down && self.selected_idx < length - 1 {
-                            self.selected_idx += 1;
-                        } else if up && self.selected_idx > 0 {
-                            self.selected_idx -= 1;
-                        }
-                    } else {
-                        self.selected_idx = 0;
+                if !self.config_errors.is_empty() {
+                    ui.separator();
+                }
+
+                if let Some(countdown) = &self.countdown {
+                    let left = countdown
+                        .deadline
+                        .map_or(countdown.seconds as f64, |d| d - ctx.input(|i| i.time));
+                    let mut cancel = false;
+                    ui.horizontal(|ui| {
+                        ui.strong(format!("⏳ {} in {} s", countdown.text, left.ceil()));
+                        cancel = ui.button("Cancel").clicked();
+                    });
+                    if cancel {
+                        self.countdown = None;
                     }
+                    ui.separator();
                 }
-            });
-            ui.allocate_space(egui::Vec2::new(0., 4.));
-        });
 
-        egui::CentralPanel::default().show(ctx, |ui| {
-            let mut had_extra = false;
-            for ext in self.extras.iter_mut() {
-                let had_content = ext.ui(&self.input, ctx, ui);
-                if had_content {
-                    ui.allocate_space(egui::Vec2::new(0., 2.));
+                let mut had_extra = false;
+                for ext in self.extras.iter_mut() {
+                    let input = extra_input(ext.as_ref(), &self.favourites, &self.input);
+                    let had_content = ext.ui(&input, ctx, ui);
+                    if had_content {
+                        ui.allocate_space(egui::Vec2::new(0., 2.));
+                    }
+                    had_extra |= had_content;
                 }
-                had_extra |= had_content;
-            }
 
-            if had_extra {
-                ui.separator();
-            }
+                if had_extra {
+                    ui.separator();
+                }
 
-            {
+                let mut clicked = None;
+                // Pinned apps lead the empty query, & can be dragged to reorder them.
+                let pinned_rows = match self.input.is_empty() {
+                    true => self.favourites.pinned.len(),
+                    false => 0,
+                };
+                let mut pin_drop = None;
+                let mut hovered = None;
+                let mut scrolled = None;
                 let row_height = ui
                     .text_style_height(&egui::TextStyle::Body)
-                    .max(ICON_SIZE[1] as f32);
-
-                let apps_mutex = &*self.applications.lock().unwrap();
-                let icons_mutex = &*self.icons.lock().unwrap();
-                match (apps_mutex, &self.matching_app_idx) {
-                    (Some(apps_list), Some(idx)) => {
-                        egui::ScrollArea::vertical()
-                            .auto_shrink([false; 2])
-                            .show_rows(ui, row_height, idx.len(), |ui, row_range| {
-                                egui::Grid::new("apps_grid").num_columns(3).show(ui, |ui| {
-                                    for row in row_range {
-                                        self.ui_for_app_entry(
-                                            &apps_list.apps[idx[row]],
-                                            self.selected_idx == row,
-                                            ctx,
-                                            ui,
-                                            icons_mutex,
-                                        );
-                                        ui.end_row();
-                                    }
-                                });
-                            });
-                    }
-                    (Some(apps_list), None) => {
-                        egui::ScrollArea::vertical()
-                            .auto_shrink([false; 2])
-                            .show_rows(ui, row_height, apps_list.apps.len(), |ui, row_range| {
-                                egui::Grid::new("apps_grid").num_columns(3).show(ui, |ui| {
-                                    for row in row_range {
-                                        self.ui_for_app_entry(
-                                            &apps_list.apps[row],
-                                            self.selected_idx == row,
-                                            ctx,
+                    .max(self.config.icon_size as f32);
+                let row_stride = row_height + ui.spacing().item_spacing.y;
+                // Selection follows the mouse, but not rows scrolled under a still one.
+                let pointer_moved = ctx.input(|i| i.pointer.delta() != egui::Vec2::ZERO);
+                let w = &self.config.window;
+                let fitted_height = crate::window::fitted_height(
+                    ui.cursor().top() + central_frame.inner_margin.bottom,
+                    self.result_count(),
+                    row_stride,
+                    w.min_height,
+                    w.max_height,
+                );
+                {
+                    let apps_mutex = &*self.applications.lock().unwrap();
+                    let icons_mutex = &*self.icons.lock().unwrap();
+                    match (apps_mutex, &self.matching_app_idx) {
+                        (Some(apps_list), Some(idx)) => {
+                            let mut scroll_area =
+                                egui::ScrollArea::vertical().auto_shrink([false; 2]);
+                            let (offset, height) = self.results_scroll;
+                            if let Some(offset) =
+                                scroll_to_row(self.selected_idx, row_stride, offset, height)
+                                    .filter(|_| self.scroll_to_selected)
+                            {
+                                scroll_area = scroll_area.vertical_scroll_offset(offset);
+                            }
+                            let output = scroll_area.show_rows(
+                                ui,
+                                row_height,
+                                self.result_count(),
+                                |ui, row_range| {
+                                    egui::Grid::new("apps_grid").num_columns(3).show(ui, |ui| {
+                                        for row in row_range {
+                                            let response = if row < idx.len() {
+                                                let app = &apps_list.apps[idx[row]];
+                                                let response = self.ui_for_app_entry(
+                                                    app,
+                                                    self.selected_idx == row,
+                                                    ctx,
+                                                    ui,
+                                                    icons_mutex,
+                                                );
+                                                if row < pinned_rows
+                                                    && self.favourites.is_pinned(app)
+                                                {
+                                                    pin_drop = pin_drop
+                                                        .or(self
+                                                            .ui_for_pin_drag(row, &response, ui));
+                                                }
+                                                response
+                                            } else {
+                                                self.ui_for_result_item(
+                                                    &self.provider_results[row - idx.len()].1,
+                                                    self.selected_idx == row,
+                                                    ui,
+                                                )
+                                            };
+                                            if response.clicked() {
+                                                clicked = Some(row);
+                                            }
+                                            if response.hovered() && pointer_moved {
+                                                hovered = Some(row);
+                                            }
+                                            ui.end_row();
+                                        }
+                                    });
+                                },
+                            );
+                            scrolled = Some((output.state.offset.y, output.inner_rect.height()));
+                        }
+                        (Some(apps_list), None) => {
+                            egui::ScrollArea::vertical()
+                                .auto_shrink([false; 2])
+                                .show_rows(
+                                    ui,
+                                    row_height,
+                                    apps_list.apps.len(),
+                                    |ui, row_range| {
+                                        egui::Grid::new("apps_grid").num_columns(3).show(
                                             ui,
-                                            icons_mutex,
+                                            |ui| {
+                                                for row in row_range {
+                                                    if self
+                                                        .ui_for_app_entry(
+                                                            &apps_list.apps[row],
+                                                            self.selected_idx == row,
+                                                            ctx,
+                                                            ui,
+                                                            icons_mutex,
+                                                        )
+                                                        .clicked()
+                                                    {
+                                                        self.backend.launch(
+                                                            &apps_list.apps[row],
+                                                            self.commands.is_none(),
+                                                        );
+                                                    }
+                                                    ui.end_row();
+                                                }
+                                            },
                                         );
-                                        ui.end_row();
-                                    }
-                                });
-                            });
-                    }
+                                    },
+                                );
+                        }
 
-                    _ => {}
+                        _ => {}
+                    }
                 }
+                if let Some((offset, height)) = scrolled {
+                    self.results_scroll = (offset, height);
+                    self.page_rows = (height / row_stride) as usize;
+                    self.scroll_to_selected = false;
+                }
+                if let Some(row) = hovered {
+                    self.selected_idx = row;
+                }
+                if let Some((from, to)) = pin_drop {
+                    self.drop_pin(from, to);
+                } else if !ctx.input(|i| i.pointer.any_down()) {
+                    // Dropped somewhere other than a pinned app.
+                    self.dragged_pin = None;
+                }
+                if let Some(row) = clicked {
+                    self.selected_idx = row;
+                    self.launch_selected(false);
+                }
+
+                egui::warn_if_debug_build(
+                    &mut ui.child_ui(
+                        ui.max_rect()
+                            .split_left_right_at_x(ui.max_rect().max.x - 155.)
+                            .1,
+                        egui::Layout::default(),
+                    ),
+                );
+                fitted_height
+            })
+            .inner;
+
+        self.update_window(window, fitted_height);
+    }
+
+    /// Positions & sizes the window, and closes it once it loses focus.
+    fn update_window(&mut self, window: &eframe::WindowInfo, fitted_height: f32) {
+        let w = &self.config.window;
+        if self.hidden {
+            return;
+        }
+
+        if w.close_on_focus_loss && self.was_focused && !window.focused {
+            match (&self.dmenu, &self.commands) {
+                (Some(_), _) => std::process::exit(1),
+                (None, Some(_)) => self.hide(),
+                (None, None) => self.window_commands.push(WindowCommand::Close),
             }
+            return;
+        }
+        self.was_focused = window.focused;
 
-            egui::warn_if_debug_build(
-                &mut ui.child_ui(
-                    ui.max_rect()
-                        .split_left_right_at_x(ui.max_rect().max.x - 155.)
-                        .1,
-                    egui::Layout::default(),
-                ),
-            );
-        });
+        let height = if w.auto_height {
+            fitted_height
+        } else {
+            window.size.y
+        };
+        if !self.positioned {
+            if let Some(monitor_size) = window.monitor_size {
+                let size = egui::vec2(window.size.x, height);
+                let pos = crate::window::centred_pos(monitor_size, size, w.offset.into());
+                self.window_commands.push(WindowCommand::SetPos(pos));
+                self.positioned = true;
+            }
+        }
+        if (height - window.size.y).abs() > 0.5 {
+            self.window_commands
+                .push(WindowCommand::SetSize(egui::vec2(window.size.x, height)));
+        }
+    }
+}
+
+impl eframe::App for Launcher {
+    /// Called by the frame work to save state before shutdown.
+    fn save(&mut self, storage: &mut dyn eframe::Storage) {
+        // dmenu mode starts from the defaults, so would forget the favourites.
+        if self.dmenu.is_some() {
+            return;
+        }
+        eframe::set_value(storage, eframe::APP_KEY, self);
+    }
+
+    /// Called each time the UI needs repainting, which may be many times per second.
+    fn update(&mut self, ctx: &egui::Context, frame: &mut eframe::Frame) {
+        self.update_ui(ctx, frame.info());
+        for command in self.window_commands.drain(..) {
+            command.apply(frame);
+        }
+    }
+
+    fn clear_color(&self, visuals: &egui::Visuals) -> [f32; 4] {
+        if self.config.window.transparent {
+            [0.; 4]
+        } else {
+            visuals.panel_fill.to_normalized_gamma_f32()
+        }
+    }
+
+    /// Closing the window only hides it when running as a daemon.
+    fn on_close_event(&mut self) -> bool {
+        if self.commands.is_some() {
+            self.hide();
+            return false;
+        }
+        true
+    }
+}
+
+fn extras_from_config(config: &Config) -> Vec<Box<dyn ImmediateExtra>> {
+    config
+        .extras
+        .iter()
+        .filter_map(|name| -> Option<Box<dyn ImmediateExtra>> {
+            match name.as_str() {
+                "numbers" => Some(Box::new(NumFormatExtra::default())),
+                "equation" => Some(Box::new(EquationExtra::default())),
+                // Running commands needs processes & threads, so its native only.
+                #[cfg(not(target_arch = "wasm32"))]
+                "shell" => Some(Box::new(crate::shell::ShellExtra::default())),
+                #[cfg(not(target_arch = "wasm32"))]
+                "scripts" => Some(Box::new(crate::scripts::ScriptExtra::new(
+                    config.dir()?.join("scripts"),
+                ))),
+                _ => None,
+            }
+        })
+        .collect()
+}
+
+/// Returns the input as the extra sees it, with previous answers filled in if
+/// it takes them.
+fn extra_input(extra: &dyn ImmediateExtra, favourites: &Favourites, input: &str) -> String {
+    match extra.takes_answers() {
+        true => favourites.expand_answers(input),
+        false => input.to_string(),
     }
 }
 
-fn configure_text_styles(ctx: &egui::Context) {
-    use egui::FontFamily::{Monospace, Proportional};
-    use egui::{FontId, TextStyle};
-
-    let mut style = (*ctx.style()).clone();
-    style.text_styles = [
-        (TextStyle::Heading, FontId::new(28.0, Proportional)),
-        (TextStyle::Body, FontId::new(20.0, Proportional)),
-        (TextStyle::Monospace, FontId::new(16.0, Monospace)),
-        (TextStyle::Button, FontId::new(16.0, Proportional)),
-        (TextStyle::Small, FontId::new(15.0, Proportional)),
-    ]
-    .into();
-    ctx.set_style(style);
+fn default_providers(config: &Config) -> Vec<Box<dyn Provider>> {
+    let mut providers: Vec<Box<dyn Provider>> = vec![];
+
+    #[cfg(not(target_arch = "wasm32"))]
+    if config.files.enabled {
+        providers.push(Box::new(crate::files::FileProvider::new(
+            config.files.index_config(),
+        )));
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    if let (true, Some(home)) = (config.bookmarks.enabled, std::env::var_os("HOME")) {
+        providers.push(Box::new(crate::bookmarks::BookmarkProvider::new(
+            home.into(),
+        )));
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    if let (true, Some(path)) = (config.recent.enabled, crate::recent::default_path()) {
+        providers.push(Box::new(crate::recent::RecentProvider::new(path)));
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    if let (true, Some(home)) = (config.ssh.enabled, std::env::var_os("HOME")) {
+        providers.push(Box::new(crate::ssh::SshProvider::new(
+            home.as_ref(),
+            crate::sys_apps::terminal_command(config.terminal.as_deref()),
+        )));
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    if let (true, Some(dir)) = (config.plugins.enabled, config.plugins.dir(config.dir())) {
+        let (plugins, errors) = crate::plugins::discover(&dir);
+        for e in errors {
+            eprintln!("failed to load plugin: {}", e);
+        }
+        for p in plugins {
+            providers.push(Box::new(p));
+        }
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    if config.processes.enabled {
+        providers.push(Box::new(crate::processes::ProcessProvider::new(
+            "/proc".into(),
+            "/etc/passwd".into(),
+        )));
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    if config.session.enabled {
+        providers.push(Box::new(crate::session::SessionProvider::new(
+            &config.session,
+        )));
+    }
+
+    if config.unicode.enabled {
+        providers.push(Box::new(crate::unicode::UnicodeProvider::new(
+            config.unicode.skin_tone,
+        )));
+    }
+
+    if config.web_search.enabled {
+        providers.push(Box::new(crate::websearch::WebSearchProvider::new(
+            config.web_search.engines.clone(),
+        )));
+    }
+
+    providers
+}
+
+/// Installs the fonts & theme from the config, returning any problem loading fonts.
+fn configure_style(
+    ctx: &egui::Context,
+    config: &Config,
+    system_theme: Option<eframe::Theme>,
+) -> Option<String> {
+    let error = match crate::theme::font_definitions(&config.fonts) {
+        Ok(fonts) => {
+            ctx.set_fonts(fonts);
+            None
+        }
+        Err(e) => Some(e),
+    };
+    Theme::from_config(&config.theme, system_theme).apply(ctx, &config.fonts);
+    error
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use egui::{Event, Key, Modifiers};
+
+    /// What the launcher would have started.
+    #[derive(Debug, Clone, PartialEq)]
+    enum Launched {
+        /// The command line of an application.
+        App(Vec<String>),
+        Action(Action),
+    }
+
+    /// Records what would be started rather than starting it.
+    #[derive(Debug, Default)]
+    struct MockBackend(Arc<Mutex<Vec<Launched>>>);
+
+    impl Backend for MockBackend {
+        fn launch(&self, app: &crate::sys_apps::App, _quit: bool) {
+            self.0
+                .lock()
+                .unwrap()
+                .push(Launched::App(app.command(&[]).unwrap()));
+        }
+
+        fn run(&self, action: &Action, _quit: bool) {
+            self.0
+                .lock()
+                .unwrap()
+                .push(Launched::Action(action.clone()));
+        }
+    }
+
+    /// Runs a launcher frame by frame in a bare context, without a window.
+    struct Harness {
+        launcher: Launcher,
+        ctx: egui::Context,
+        launched: Arc<Mutex<Vec<Launched>>>,
+        /// Changes the last frame made to the window.
+        window_commands: Vec<WindowCommand>,
+        /// Text drawn by the last frame.
+        texts: Vec<String>,
+        /// Time of the frames, which otherwise advances a 60th of a second each.
+        time: Option<f64>,
+        /// Whether the window has the focus.
+        focused: bool,
+    }
+
+    impl Harness {
+        /// Creates a launcher listing the applications, given by name & command.
+        fn new(apps: &[(&str, &str)]) -> Self {
+            let backend = MockBackend::default();
+            let launched = backend.0.clone();
+            let launcher = Launcher {
+                // Only the extras which don't read the user's files or run commands.
+                extras: vec![
+                    Box::new(NumFormatExtra::default()),
+                    Box::new(EquationExtra::default()),
+                ],
+                backend: Box::new(backend),
+                ..Launcher::default()
+            };
+            let apps = apps
+                .iter()
+                .map(|(name, cmd)| crate::sys_apps::App::new(name, cmd))
+                .collect();
+            *launcher.applications.lock().unwrap() = Some(crate::sys_apps::AppList { apps });
+
+            let mut harness = Harness {
+                launcher,
+                ctx: egui::Context::default(),
+                launched,
+                window_commands: vec![],
+                texts: vec![],
+                time: None,
+                focused: true,
+            };
+            // Lays out the results, so their height is known for paging.
+            harness.frame(vec![]);
+            harness.frame(vec![]);
+            harness
+        }
+
+        /// Creates a launcher listing "App 0", "App 1" & so on.
+        fn numbered(apps: usize) -> Self {
+            let apps: Vec<_> = (0..apps)
+                .map(|i| (format!("App {}", i), format!("app{}", i)))
+                .collect();
+            let apps: Vec<_> = apps.iter().map(|(n, c)| (n.as_str(), c.as_str())).collect();
+            Self::new(&apps)
+        }
+
+        /// Gives the applications the icons, by name.
+        fn with_icons(self, icons: &[(&str, &str)]) -> Self {
+            *self.launcher.icons.lock().unwrap() = icons
+                .iter()
+                .map(|(name, path)| (name.to_string(), PathBuf::from(path)))
+                .collect();
+            self
+        }
+
+        /// Queries the providers, waiting for each to answer.
+        fn with_providers(mut self, providers: Vec<Box<dyn Provider>>) -> Self {
+            self.launcher.executor =
+                Executor::new(providers, Arc::default(), None).without_budgets();
+            self
+        }
+
+        /// Draws a frame in a 600x300 window with the events given, holding the
+        /// modifiers of the first key pressed.
+        fn frame(&mut self, events: Vec<Event>) {
+            let info = eframe::IntegrationInfo {
+                system_theme: None,
+                cpu_usage: None,
+                native_pixels_per_point: Some(1.),
+                window_info: eframe::WindowInfo {
+                    position: None,
+                    fullscreen: false,
+                    minimized: false,
+                    maximized: false,
+                    focused: self.focused,
+                    size: egui::vec2(600., 300.),
+                    monitor_size: None,
+                },
+            };
+            let input = egui::RawInput {
+                screen_rect: Some(egui::Rect::from_min_size(
+                    egui::Pos2::ZERO,
+                    egui::vec2(600., 300.),
+                )),
+                modifiers: events
+                    .iter()
+                    .find_map(|e| match e {
+                        Event::Key { modifiers, .. } => Some(*modifiers),
+                        _ => None,
+                    })
+                    .unwrap_or_default(),
+                events,
+                time: self.time,
+                ..Default::default()
+            };
+            let output = self
+                .ctx
+                .run(input, |ctx| self.launcher.update_ui(ctx, &info));
+            self.window_commands = self.launcher.window_commands.drain(..).collect();
+            self.texts = vec![];
+            for clipped in output.shapes {
+                texts(&clipped.shape, &mut self.texts);
+            }
+        }
+
+        fn press(&mut self, key: Key, modifiers: Modifiers) {
+            self.frame(vec![Event::Key {
+                key,
+                pressed: true,
+                repeat: false,
+                modifiers,
+            }]);
+        }
+
+        fn type_text(&mut self, text: &str) {
+            self.frame(vec![Event::Text(text.into())]);
+        }
+
+        /// Draws frames until the condition holds, for work done on other threads.
+        fn frame_until(&mut self, mut done: impl FnMut(&Self) -> bool) {
+            let deadline = std::time::Instant::now() + Duration::from_secs(10);
+            while !done(self) {
+                assert!(
+                    std::time::Instant::now() < deadline,
+                    "timed out drawing {:?}",
+                    self.texts
+                );
+                std::thread::sleep(Duration::from_millis(1));
+                self.frame(vec![]);
+            }
+        }
+
+        /// Returns whether the last frame drew the text.
+        fn shows(&self, text: &str) -> bool {
+            self.texts.iter().any(|t| t == text)
+        }
+
+        fn selected(&self) -> Option<String> {
+            self.launcher.selected_text()
+        }
+
+        /// Returns what has been started since last called.
+        fn launched(&self) -> Vec<Launched> {
+            std::mem::take(&mut *self.launched.lock().unwrap())
+        }
+    }
+
+    /// Adds the text drawn by the shape.
+    fn texts(shape: &egui::Shape, out: &mut Vec<String>) {
+        match shape {
+            egui::Shape::Text(text) => out.push(text.galley.text().to_string()),
+            egui::Shape::Vec(shapes) => shapes.iter().for_each(|s| texts(s, out)),
+            _ => {}
+        }
+    }
+
+    #[test]
+    fn scroll_into_view() {
+        // Rows of 10 in a list 35 high, scrolled down 20.
+        assert_eq!(scroll_to_row(3, 10., 20., 35.), None);
+        assert_eq!(scroll_to_row(1, 10., 20., 35.), Some(10.));
+        assert_eq!(scroll_to_row(5, 10., 20., 35.), Some(25.));
+    }
+
+    #[test]
+    fn launch_app() {
+        let mut h = Harness::new(&[
+            ("Firefox", "firefox %u"),
+            ("Files", "nautilus --new-window"),
+            ("Terminal", "gnome-terminal"),
+        ])
+        .with_icons(&[("Firefox", "/usr/share/icons/firefox.png")]);
+        assert!(h.shows("Firefox") && h.shows("Terminal"));
+
+        h.type_text("fi");
+        assert!(h.shows("Files") && h.shows("Firefox") && !h.shows("Terminal"));
+        h.type_text("les");
+        assert_eq!(h.selected().as_deref(), Some("Files"));
+        assert!(!h.shows("Firefox"));
+
+        h.press(Key::Enter, Modifiers::NONE);
+        assert_eq!(
+            h.launched(),
+            vec![Launched::App(vec![
+                "nautilus".into(),
+                "--new-window".into()
+            ])]
+        );
+        assert!(h.window_commands.contains(&WindowCommand::Close));
+        assert_eq!(h.launcher.history.entries, vec!["files"]);
+    }
+
+    #[test]
+    fn launch_result() {
+        let mut h = Harness::new(&[("Firefox", "firefox")]);
+        h.launcher.history.push("firefox");
+        h.launcher.history.push("2 + 2");
+
+        // Choosing a query from the history puts it back in the input.
+        h.press(Key::R, Modifiers::CTRL);
+        h.type_text("fire");
+        assert_eq!(h.selected().as_deref(), Some("firefox"));
+        h.press(Key::Enter, Modifiers::NONE);
+        assert_eq!(h.launcher.input, "firefox");
+        assert!(h.launched().is_empty());
+        assert!(!h.window_commands.contains(&WindowCommand::Close));
+
+        h.launcher.provider_results = vec![(
+            "test",
+            ResultItem {
+                title: "Run".into(),
+                subtitle: None,
+                glyph: "⚙".into(),
+                score: 0,
+                action: Action::Spawn(vec!["run".into()]),
+                alt_action: Some(Action::Copy("run".into())),
+            },
+        )];
+        h.launcher.matching_app_idx = Some(vec![]);
+        h.frame(vec![]);
+        assert!(h.shows("Run"));
+        h.press(Key::Enter, Modifiers::ALT);
+        assert_eq!(
+            h.launched(),
+            vec![Launched::Action(Action::Copy("run".into()))]
+        );
+    }
+
+    #[test]
+    fn calculation_history() {
+        let mut h = Harness::new(&[("Firefox", "firefox")]);
+        let enter = |h: &mut Harness, input: &str| {
+            h.launcher.input = input.into();
+            h.launcher.handle_input_changed();
+            h.press(Key::Enter, Modifiers::NONE);
+        };
+        enter(&mut h, "2 + 2");
+        enter(&mut h, "ans * 3");
+        assert_eq!(
+            h.launcher.favourites.calculations[0],
+            crate::favourites::Calculation {
+                input: "(4) * 3".into(),
+                result: "12".into(),
+            }
+        );
+
+        // `=` lists the history, & choosing an entry puts it back in the input.
+        h.launcher.input = "=".into();
+        h.launcher.handle_input_changed();
+        h.frame(vec![]);
+        assert!(h.shows("(4) * 3 = 12") && h.shows("2 + 2 = 4"));
+        assert!(!h.shows("Firefox"));
+        h.press(Key::ArrowDown, Modifiers::NONE);
+        h.press(Key::Enter, Modifiers::NONE);
+        assert_eq!(h.launcher.input, "2 + 2");
+        assert!(h.launched().is_empty());
+    }
+
+    /// Returns a result which reboots after counting down 10 seconds.
+    fn reboot_countdown(reboot: &Action) -> (&'static str, ResultItem) {
+        let item = ResultItem {
+            title: "Reboot now?".into(),
+            subtitle: None,
+            glyph: "⚠".into(),
+            score: 0,
+            action: Action::Countdown("Rebooting".into(), 10, Box::new(reboot.clone())),
+            alt_action: None,
+        };
+        ("test", item)
+    }
+
+    #[test]
+    fn countdown() {
+        let mut h = Harness::new(&[]);
+        let reboot = Action::Spawn(vec!["reboot".into()]);
+        h.launcher.provider_results = vec![reboot_countdown(&reboot)];
+        h.launcher.matching_app_idx = Some(vec![]);
+
+        h.time = Some(100.);
+        h.press(Key::Enter, Modifiers::NONE);
+        h.frame(vec![]);
+        assert!(h.shows("⏳ Rebooting in 10 s"));
+        h.time = Some(103.5);
+        h.frame(vec![]);
+        assert!(h.shows("⏳ Rebooting in 7 s"));
+        assert!(h.launched().is_empty());
+
+        // Escape cancels rather than closing the window.
+        h.press(Key::Escape, Modifiers::NONE);
+        h.time = Some(200.);
+        h.frame(vec![]);
+        assert!(!h.shows("⏳ Rebooting in 10 s"));
+        assert!(h.launched().is_empty());
+        assert!(!h.window_commands.contains(&WindowCommand::Close));
+
+        h.press(Key::Enter, Modifiers::NONE);
+        h.frame(vec![]);
+        h.time = Some(210.);
+        h.frame(vec![]);
+        assert_eq!(h.launched(), vec![Launched::Action(reboot)]);
+        assert!(h.window_commands.contains(&WindowCommand::Close));
+    }
+
+    /// Answers with a command to run, once the delay is up.
+    struct Slow(Duration);
+
+    impl Provider for Slow {
+        fn name(&self) -> &'static str {
+            "slow"
+        }
+
+        fn query(
+            &mut self,
+            _matcher: &SkimMatcherV2,
+            input: &str,
+            _cancel: &crate::provider::Cancel,
+        ) -> Vec<ResultItem> {
+            std::thread::sleep(self.0);
+            vec![ResultItem {
+                title: input.to_string(),
+                subtitle: None,
+                glyph: "🐢".into(),
+                score: 0,
+                action: Action::Spawn(vec![input.to_string()]),
+                alt_action: None,
+            }]
+        }
+    }
+
+    #[test]
+    fn launch_waits_for_providers() {
+        let mut h = Harness::new(&[]);
+        h.launcher.executor = Executor::new(
+            vec![Box::new(Slow(Duration::from_millis(200)))],
+            Arc::default(),
+            None,
+        );
+
+        // Enter doesn't hold up the frame, but launches once the provider answers.
+        h.type_text("true");
+        let start = std::time::Instant::now();
+        h.press(Key::Enter, Modifiers::NONE);
+        assert!(start.elapsed() < Duration::from_millis(150));
+        assert!(h.launched().is_empty());
+        let mut launched = vec![];
+        h.frame_until(|h| {
+            launched.extend(h.launched());
+            !launched.is_empty()
+        });
+        assert_eq!(
+            launched,
+            vec![Launched::Action(Action::Spawn(vec!["true".into()]))]
+        );
+
+        // Changing the input drops the launch.
+        h.type_text("x");
+        h.press(Key::Enter, Modifiers::NONE);
+        h.type_text("y");
+        std::thread::sleep(Duration::from_millis(300));
+        h.frame_until(|h| h.launcher.executor.time_left().is_none());
+        h.frame(vec![]);
+        assert!(h.launched().is_empty());
+    }
+
+    #[test]
+    fn session_countdown() {
+        let session = crate::session::SessionProvider::new(&Default::default());
+        let mut h = Harness::new(&[]).with_providers(vec![Box::new(session)]);
+
+        h.type_text("shutdown");
+        h.press(Key::Enter, Modifiers::NONE);
+        h.frame_until(|h| h.launcher.input == "power off?");
+        h.time = Some(100.);
+        h.press(Key::Enter, Modifiers::NONE);
+        h.frame(vec![]);
+        assert!(h.shows("⏳ Powering off in 10 s"));
+        assert!(h.launched().is_empty());
+
+        h.time = Some(110.);
+        h.frame(vec![]);
+        assert_eq!(
+            h.launched(),
+            vec![Launched::Action(Action::Spawn(vec![
+                "systemctl".into(),
+                "poweroff".into()
+            ]))]
+        );
+    }
+
+    #[test]
+    fn countdown_vim() {
+        let mut h = Harness::new(&[]);
+        let config = crate::keymap::KeysConfig {
+            preset: crate::keymap::KeyPreset::Vim,
+            ..Default::default()
+        };
+        h.launcher.keymap = Keymap::new(&config, crate::config::EscapeAction::Exit).unwrap();
+        let reboot = Action::Spawn(vec!["reboot".into()]);
+        h.launcher.provider_results = vec![reboot_countdown(&reboot)];
+        h.launcher.matching_app_idx = Some(vec![]);
+
+        h.time = Some(100.);
+        h.press(Key::Enter, Modifiers::NONE);
+        h.frame(vec![]);
+        assert!(h.shows("⏳ Rebooting in 10 s"));
+
+        // Escape cancels rather than switching to normal mode.
+        h.press(Key::Escape, Modifiers::NONE);
+        assert_eq!(h.launcher.keymap.mode(), Mode::Insert);
+        h.time = Some(200.);
+        h.frame(vec![]);
+        assert!(!h.shows("⏳ Rebooting in 10 s"));
+        assert!(h.launched().is_empty());
+    }
+
+    #[test]
+    fn countdown_hidden() {
+        let mut h = Harness::new(&[]);
+        let (commands, receiver) = std::sync::mpsc::channel();
+        h.launcher.commands = Some(receiver);
+        let reboot = Action::Spawn(vec!["reboot".into()]);
+        h.launcher.provider_results = vec![reboot_countdown(&reboot)];
+        h.launcher.matching_app_idx = Some(vec![]);
+
+        h.time = Some(100.);
+        h.press(Key::Enter, Modifiers::NONE);
+        h.frame(vec![]);
+        assert!(h.shows("⏳ Rebooting in 10 s"));
+
+        // Losing the focus hides the window, cancelling the countdown.
+        h.focused = false;
+        h.frame(vec![]);
+        assert!(h
+            .window_commands
+            .contains(&WindowCommand::SetVisible(false)));
+
+        h.focused = true;
+        commands.send(Command::Show).unwrap();
+        h.time = Some(200.);
+        h.frame(vec![]);
+        h.frame(vec![]);
+        assert!(!h.shows("Rebooting"));
+        assert!(h.launched().is_empty());
+    }
+
+    #[test]
+    fn extras_output() {
+        let mut h = Harness::new(&[("Firefox", "firefox")]);
+        h.type_text("x = 4; y = x * 3");
+        h.frame_until(|h| h.shows("Equations"));
+        assert!(h.shows("y") && h.shows("12"));
+
+        let mut h = Harness::new(&[("Firefox", "firefox")]);
+        h.type_text("0x1f");
+        assert!(h.shows("Integer representations") && h.shows("31"));
+        h.press(Key::Enter, Modifiers::NONE);
+        assert!(h.launched().is_empty());
+    }
+
+    #[test]
+    fn keyboard_navigation() {
+        let mut h = Harness::numbered(50);
+        let none = Modifiers::NONE;
+        let page_rows = h.launcher.page_rows;
+        assert_eq!(h.launcher.result_count(), 50);
+        assert!(page_rows > 1 && page_rows < 50);
+
+        h.press(Key::ArrowDown, none);
+        assert_eq!(h.launcher.selected_idx, 1);
+        h.press(Key::N, Modifiers::CTRL);
+        assert_eq!(h.launcher.selected_idx, 2);
+        h.press(Key::P, Modifiers::CTRL);
+        assert_eq!(h.launcher.selected_idx, 1);
+        h.press(Key::PageDown, none);
+        assert_eq!(h.launcher.selected_idx, 1 + page_rows);
+        h.press(Key::PageUp, none);
+        assert_eq!(h.launcher.selected_idx, 1);
+        h.press(Key::PageUp, none);
+        assert_eq!(h.launcher.selected_idx, 0);
+
+        // The last row is scrolled into view, & the input's cursor doesn't move.
+        h.press(Key::End, none);
+        assert_eq!(h.selected().as_deref(), Some("App 49"));
+        h.frame(vec![]);
+        let (offset, height) = h.launcher.results_scroll;
+        assert!(offset > 0. && height > 0.);
+        assert!(h.shows("App 49") && !h.shows("App 0"));
+        h.press(Key::ArrowDown, none);
+        assert_eq!(h.launcher.selected_idx, 49);
+
+        h.press(Key::Home, none);
+        assert_eq!(h.launcher.selected_idx, 0);
+        h.frame(vec![]);
+        assert_eq!(h.launcher.results_scroll.0, 0.);
+    }
+
+    #[test]
+    fn home_end_move_cursor_first() {
+        let mut h = Harness::numbered(50);
+        let none = Modifiers::NONE;
+        h.type_text("app");
+        h.press(Key::ArrowDown, none);
+        assert_eq!(h.launcher.selected_idx, 1);
+
+        // Home & End move the input's cursor, until it's already at that end.
+        h.press(Key::Home, none);
+        assert_eq!(h.launcher.input_cursor(&h.ctx), 0);
+        assert_eq!(h.launcher.selected_idx, 1);
+        h.press(Key::Home, none);
+        assert_eq!(h.launcher.selected_idx, 0);
+        h.press(Key::End, none);
+        assert_eq!(h.launcher.input_cursor(&h.ctx), 3);
+        assert_eq!(h.launcher.selected_idx, 0);
+        h.press(Key::End, none);
+        assert_eq!(h.selected().as_deref(), Some("App 49"));
+        assert_eq!(h.launcher.input, "app");
+    }
+
+    #[test]
+    fn key_actions() {
+        let mut h = Harness::numbered(5);
+        let config = crate::keymap::KeysConfig {
+            preset: crate::keymap::KeyPreset::Emacs,
+            ..Default::default()
+        };
+        h.launcher.keymap = Keymap::new(&config, crate::config::EscapeAction::Clear).unwrap();
+
+        h.type_text("open app 3");
+        assert_eq!(h.launcher.input, "open app 3");
+        h.press(Key::W, Modifiers::CTRL);
+        assert_eq!(h.launcher.input, "open app ");
+        h.press(Key::Escape, Modifiers::NONE);
+        assert_eq!(h.launcher.input, "");
+        h.type_text("app");
+        assert_eq!(h.launcher.input, "app");
+
+        h.press(Key::G, Modifiers::CTRL);
+        assert!(h.window_commands.contains(&WindowCommand::Close));
+    }
+
+    #[test]
+    fn hover_selects() {
+        let mut h = Harness::numbered(50);
+        assert_eq!(h.launcher.selected_idx, 0);
+
+        // Over a row in the lower half of the window.
+        for y in [190., 200.] {
+            h.frame(vec![Event::PointerMoved(egui::pos2(120., y))]);
+        }
+        assert!(h.launcher.selected_idx > 0);
+    }
 }
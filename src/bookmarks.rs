@@ -0,0 +1,566 @@
+use fuzzy_matcher::skim::SkimMatcherV2;
+use fuzzy_matcher::FuzzyMatcher;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, SystemTime};
+
+use crate::provider::{Action, Cancel, Provider, ResultItem};
+
+/// How often bookmark files are checked for changes.
+const REFRESH_INTERVAL: Duration = Duration::from_secs(10);
+
+/// At most this many bookmarks are listed.
+const MAX_RESULTS: usize = 10;
+
+/// Queries shorter than this don't search bookmarks, as they'd match almost everything.
+const MIN_QUERY_LEN: usize = 2;
+
+/// Where Firefox keeps its profiles, relative to $HOME.
+const FIREFOX_DIRS: [&str; 3] = [
+    ".mozilla/firefox",
+    "snap/firefox/common/.mozilla/firefox",
+    ".var/app/org.mozilla.firefox/.mozilla/firefox",
+];
+
+/// Where Chromium-based browsers keep their profiles, relative to $HOME.
+const CHROMIUM_DIRS: [&str; 6] = [
+    ".config/chromium",
+    ".config/google-chrome",
+    ".config/google-chrome-beta",
+    ".config/BraveSoftware/Brave-Browser",
+    ".config/microsoft-edge",
+    ".config/vivaldi",
+];
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Bookmark {
+    pub title: String,
+    pub url: String,
+    /// Folders containing the bookmark, outermost first, joined with `/`.
+    pub folder: String,
+}
+
+/// A browser profile's bookmarks file.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Source {
+    /// A Firefox `places.sqlite` database.
+    Firefox(PathBuf),
+    /// A Chromium-family `Bookmarks` JSON file.
+    Chromium(PathBuf),
+}
+
+impl Source {
+    pub fn read(&self) -> Result<Vec<Bookmark>, String> {
+        match self {
+            Source::Firefox(path) => read_firefox(path),
+            Source::Chromium(path) => read_chromium(path),
+        }
+        .map_err(|e| format!("failed to read bookmarks from {:?}: {}", self.path(), e))
+    }
+
+    fn path(&self) -> &Path {
+        match self {
+            Source::Firefox(path) | Source::Chromium(path) => path,
+        }
+    }
+
+    /// Returns something which changes whenever the bookmarks might have.
+    fn stamp(&self) -> Stamp {
+        let stamp = |p: &Path| {
+            let meta = std::fs::metadata(p).ok()?;
+            Some((meta.modified().ok()?, meta.len()))
+        };
+        match self {
+            // Recent changes are only in the write-ahead log until it's checkpointed.
+            Source::Firefox(path) => vec![stamp(path), stamp(&wal_path(path))],
+            Source::Chromium(path) => vec![stamp(path)],
+        }
+    }
+}
+
+/// Modification time & length of each file a source is read from.
+type Stamp = Vec<Option<(SystemTime, u64)>>;
+
+/// Returns the bookmark files of the browser profiles found under home.
+pub fn discover(home: &Path) -> Vec<Source> {
+    let profiles = |dir: &str| -> Vec<PathBuf> {
+        match std::fs::read_dir(home.join(dir)) {
+            Ok(entries) => entries.flatten().map(|e| e.path()).collect(),
+            Err(_) => vec![],
+        }
+    };
+
+    let mut sources = vec![];
+    for dir in FIREFOX_DIRS {
+        for profile in profiles(dir) {
+            let places = profile.join("places.sqlite");
+            if places.is_file() {
+                sources.push(Source::Firefox(places));
+            }
+        }
+    }
+    for dir in CHROMIUM_DIRS {
+        for profile in profiles(dir) {
+            let bookmarks = profile.join("Bookmarks");
+            if bookmarks.is_file() {
+                sources.push(Source::Chromium(bookmarks));
+            }
+        }
+    }
+    sources.sort();
+    sources
+}
+
+fn wal_path(path: &Path) -> PathBuf {
+    let mut wal = path.as_os_str().to_owned();
+    wal.push("-wal");
+    wal.into()
+}
+
+/// Reads the bookmarks of a Firefox profile.
+///
+/// Firefox keeps the database locked while it's running, & it mustn't be read
+/// while Firefox writes to it, so a copy is read instead. The write-ahead log
+/// is copied along with it, as recent changes are only there until Firefox
+/// checkpoints them into the database.
+fn read_firefox(path: &Path) -> Result<Vec<Bookmark>, String> {
+    static COPIES: AtomicUsize = AtomicUsize::new(0);
+
+    let dir = std::env::temp_dir().join(format!(
+        "liquid-launcher-places-{}-{}",
+        std::process::id(),
+        COPIES.fetch_add(1, Ordering::SeqCst)
+    ));
+    let copy = dir.join("places.sqlite");
+    let result = std::fs::create_dir(&dir)
+        .and_then(|_| std::fs::copy(path, &copy))
+        .and_then(|_| match std::fs::copy(wal_path(path), wal_path(&copy)) {
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(0),
+            r => r,
+        })
+        .map_err(|e| e.to_string())
+        .and_then(|_| query_places(&copy).map_err(|e| e.to_string()));
+
+    let _ = std::fs::remove_dir_all(&dir);
+    result
+}
+
+/// A row of `moz_bookmarks`, with the URL of bookmarks.
+struct PlacesRow {
+    kind: i64,
+    parent: i64,
+    title: Option<String>,
+    url: Option<String>,
+}
+
+fn query_places(path: &Path) -> rusqlite::Result<Vec<Bookmark>> {
+    const BOOKMARK: i64 = 1;
+    const ROOT_NAMES: [(&str, &str); 4] = [
+        ("menu", "Bookmarks Menu"),
+        ("toolbar", "Bookmarks Toolbar"),
+        ("unfiled", "Other Bookmarks"),
+        ("mobile", "Mobile Bookmarks"),
+    ];
+
+    // Opened for writing, so the copied write-ahead log can be read.
+    let conn = rusqlite::Connection::open(path)?;
+    let mut stmt = conn.prepare(
+        "SELECT b.id, b.type, b.parent, b.title, p.url
+         FROM moz_bookmarks b LEFT JOIN moz_places p ON p.id = b.fk",
+    )?;
+    let rows = stmt
+        .query_map([], |row| {
+            Ok((
+                row.get::<_, i64>(0)?,
+                PlacesRow {
+                    kind: row.get(1)?,
+                    parent: row.get(2)?,
+                    title: row.get(3)?,
+                    url: row.get(4)?,
+                },
+            ))
+        })?
+        .collect::<rusqlite::Result<HashMap<_, _>>>()?;
+
+    // Returns the folder path of the row, or None if it's a tag rather than a
+    // bookmark.
+    let folder = |mut id: i64| -> Option<String> {
+        let mut names = vec![];
+        while let Some(row) = rows.get(&id) {
+            let title = row.title.as_deref().unwrap_or("");
+            match rows.get(&row.parent) {
+                // The root, whose children have special names.
+                Some(parent) if parent.parent == 0 => {
+                    if title == "tags" {
+                        return None;
+                    }
+                    let name = ROOT_NAMES.iter().find(|(t, _)| *t == title);
+                    names.push(name.map(|(_, n)| *n).unwrap_or(title));
+                    break;
+                }
+                Some(_) => names.push(title),
+                None => break,
+            }
+            id = row.parent;
+        }
+        names.reverse();
+        Some(names.join("/"))
+    };
+
+    let mut out: Vec<Bookmark> = rows
+        .values()
+        .filter(|row| row.kind == BOOKMARK)
+        .filter_map(|row| {
+            // place: URLs are saved searches of the history, like "Most Visited".
+            let url = row.url.clone().filter(|u| !u.starts_with("place:"))?;
+            Some(Bookmark {
+                title: row.title.clone().unwrap_or_default(),
+                url,
+                folder: folder(row.parent)?,
+            })
+        })
+        .collect();
+    out.sort_by(|a, b| (&a.folder, &a.title).cmp(&(&b.folder, &b.title)));
+    Ok(out)
+}
+
+#[derive(serde::Deserialize)]
+struct ChromiumFile {
+    roots: BTreeMap<String, ChromiumNode>,
+}
+
+#[derive(serde::Deserialize)]
+struct ChromiumNode {
+    #[serde(default)]
+    name: String,
+    #[serde(rename = "type")]
+    kind: String,
+    url: Option<String>,
+    #[serde(default)]
+    children: Vec<ChromiumNode>,
+}
+
+fn read_chromium(path: &Path) -> Result<Vec<Bookmark>, String> {
+    fn walk(node: &ChromiumNode, folder: &str, out: &mut Vec<Bookmark>) {
+        match (node.kind.as_str(), &node.url) {
+            ("url", Some(url)) => out.push(Bookmark {
+                title: node.name.clone(),
+                url: url.clone(),
+                folder: folder.to_string(),
+            }),
+            ("folder", _) => {
+                let folder = match folder {
+                    "" => node.name.clone(),
+                    f => format!("{}/{}", f, node.name),
+                };
+                for child in node.children.iter() {
+                    walk(child, &folder, out);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    let data = std::fs::read_to_string(path).map_err(|e| e.to_string())?;
+    let file: ChromiumFile = serde_json::from_str(&data).map_err(|e| e.to_string())?;
+
+    let mut out = vec![];
+    for root in file.roots.values() {
+        walk(root, "", &mut out);
+    }
+    Ok(out)
+}
+
+/// The bookmarks of each source, reread when their files change.
+struct Index {
+    /// Each source with the stamp it was last read at, if it has been.
+    sources: Vec<(Source, Option<Stamp>, Vec<Bookmark>)>,
+}
+
+impl Index {
+    fn new(sources: Vec<Source>) -> Self {
+        Self {
+            sources: sources.into_iter().map(|s| (s, None, vec![])).collect(),
+        }
+    }
+
+    /// Rereads the sources which have changed, returning whether any had.
+    fn refresh(&mut self) -> bool {
+        let mut changed = false;
+        for (source, stamp, bookmarks) in self.sources.iter_mut() {
+            let new_stamp = Some(source.stamp());
+            if *stamp == new_stamp {
+                continue;
+            }
+
+            *stamp = new_stamp;
+            changed = true;
+            *bookmarks = match source.read() {
+                Ok(b) => b,
+                Err(e) => {
+                    eprintln!("{}", e);
+                    vec![]
+                }
+            };
+        }
+        changed
+    }
+
+    fn bookmarks(&self) -> Vec<Bookmark> {
+        // The same page is often bookmarked in several browsers.
+        let mut seen = HashSet::new();
+        self.sources
+            .iter()
+            .flat_map(|(_, _, bookmarks)| bookmarks)
+            .filter(|b| seen.insert(b.url.as_str()))
+            .cloned()
+            .collect()
+    }
+}
+
+/// Searches the bookmarks of Firefox & Chromium-based browsers.
+pub struct BookmarkProvider {
+    bookmarks: Arc<Mutex<Arc<Vec<Bookmark>>>>,
+    loaded: Arc<AtomicBool>,
+}
+
+impl BookmarkProvider {
+    /// Reads the bookmarks of the profiles under home in the background.
+    pub fn new(home: PathBuf) -> Self {
+        let bookmarks = Arc::new(Mutex::new(Arc::new(vec![])));
+        let bookmarks2 = bookmarks.clone();
+        let loaded = Arc::new(AtomicBool::new(false));
+        let loaded2 = loaded.clone();
+
+        std::thread::spawn(move || {
+            let mut index = Index::new(discover(&home));
+            loop {
+                if index.refresh() {
+                    *bookmarks2.lock().unwrap() = Arc::new(index.bookmarks());
+                }
+                loaded2.store(true, Ordering::SeqCst);
+                std::thread::sleep(REFRESH_INTERVAL);
+            }
+        });
+
+        Self { bookmarks, loaded }
+    }
+}
+
+/// Scores a bookmark against the query, preferring matches on the title.
+fn score(matcher: &SkimMatcherV2, b: &Bookmark, query: &str) -> Option<i64> {
+    [
+        matcher.fuzzy_match(&b.title, query).map(|s| s * 2),
+        matcher.fuzzy_match(&format!("{}/{}", b.folder, b.title), query),
+        matcher.fuzzy_match(&b.url, query),
+    ]
+    .into_iter()
+    .flatten()
+    .max()
+}
+
+impl Provider for BookmarkProvider {
+    fn name(&self) -> &'static str {
+        "bookmarks"
+    }
+
+    fn wait_ready(&self) {
+        while !self.loaded.load(Ordering::SeqCst) {
+            std::thread::sleep(Duration::from_millis(10));
+        }
+    }
+
+    fn query(&mut self, matcher: &SkimMatcherV2, input: &str, _cancel: &Cancel) -> Vec<ResultItem> {
+        let query = input.trim();
+        if query.len() < MIN_QUERY_LEN {
+            return vec![];
+        }
+
+        let bookmarks = self.bookmarks.lock().unwrap().clone();
+        let mut scored: Vec<(i64, &Bookmark)> = bookmarks
+            .iter()
+            .filter_map(|b| score(matcher, b, query).map(|s| (s, b)))
+            .collect();
+        scored.sort_by_key(|s| std::cmp::Reverse(s.0));
+        scored.truncate(MAX_RESULTS);
+
+        scored
+            .into_iter()
+            .map(|(score, b)| ResultItem {
+                title: if b.title.is_empty() {
+                    b.url.clone()
+                } else {
+                    b.title.clone()
+                },
+                subtitle: Some(match b.folder.as_str() {
+                    "" => b.url.clone(),
+                    folder => format!("{} · {}", folder, b.url),
+                }),
+                glyph: "🔖".into(),
+                score,
+                action: Action::Open(b.url.clone()),
+                alt_action: None,
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn home() -> PathBuf {
+        Path::new(env!("CARGO_MANIFEST_DIR")).join("testdata/bookmarks/home")
+    }
+
+    fn bookmark(title: &str, url: &str, folder: &str) -> Bookmark {
+        Bookmark {
+            title: title.into(),
+            url: url.into(),
+            folder: folder.into(),
+        }
+    }
+
+    #[test]
+    fn discovery() {
+        let home = home();
+        assert_eq!(
+            discover(&home),
+            vec![
+                Source::Firefox(
+                    home.join(".mozilla/firefox/abcd1234.default-release/places.sqlite")
+                ),
+                Source::Chromium(
+                    home.join(".config/BraveSoftware/Brave-Browser/Profile 1/Bookmarks")
+                ),
+                Source::Chromium(home.join(".config/chromium/Default/Bookmarks")),
+            ]
+        );
+    }
+
+    #[test]
+    fn firefox() {
+        let places = home().join(".mozilla/firefox/abcd1234.default-release/places.sqlite");
+        assert_eq!(
+            Source::Firefox(places).read().unwrap(),
+            vec![
+                bookmark("HN", "https://news.ycombinator.com/", "Bookmarks Menu"),
+                bookmark("Docs.rs", "https://docs.rs/", "Bookmarks Toolbar/Rust"),
+                bookmark(
+                    "Rust",
+                    "https://www.rust-lang.org/",
+                    "Bookmarks Toolbar/Rust"
+                ),
+                bookmark("", "https://www.mozilla.org/", "Other Bookmarks"),
+            ]
+        );
+    }
+
+    #[test]
+    fn firefox_wal() {
+        let dir =
+            std::env::temp_dir().join(format!("liquid-launcher-places-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        let places = dir.join("places.sqlite");
+        std::fs::copy(
+            home().join(".mozilla/firefox/abcd1234.default-release/places.sqlite"),
+            &places,
+        )
+        .unwrap();
+        let source = Source::Firefox(places.clone());
+        let stamp = source.stamp();
+
+        // Like Firefox, keep a bookmark in the write-ahead log until it's closed.
+        let firefox = rusqlite::Connection::open(&places).unwrap();
+        firefox
+            .execute_batch(
+                "PRAGMA journal_mode = WAL;
+                 PRAGMA wal_autocheckpoint = 0;
+                 INSERT INTO moz_places (id, url) VALUES (100, 'https://example.com/');
+                 INSERT INTO moz_bookmarks (id, type, fk, parent, title)
+                 SELECT 100, 1, 100, id, 'Example' FROM moz_bookmarks WHERE title = 'unfiled';",
+            )
+            .unwrap();
+        assert!(wal_path(&places).exists());
+        assert_ne!(source.stamp(), stamp);
+        let bookmarks = source.read().unwrap();
+        assert_eq!(bookmarks.len(), 5);
+        assert!(bookmarks.contains(&bookmark(
+            "Example",
+            "https://example.com/",
+            "Other Bookmarks"
+        )));
+        drop(firefox);
+
+        assert!(Source::Firefox(dir.join("missing.sqlite")).read().is_err());
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn chromium() {
+        let file = home().join(".config/chromium/Default/Bookmarks");
+        assert_eq!(
+            Source::Chromium(file).read().unwrap(),
+            vec![
+                bookmark("Crates.io", "https://crates.io/", "Bookmarks bar"),
+                bookmark("egui", "https://github.com/emilk/egui", "Bookmarks bar/GUI"),
+                bookmark("Wikipedia", "https://en.wikipedia.org/", "Other bookmarks"),
+            ]
+        );
+        assert!(Source::Chromium(home().join("missing")).read().is_err());
+    }
+
+    #[test]
+    fn refresh() {
+        let dir =
+            std::env::temp_dir().join(format!("liquid-launcher-bookmarks-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        let file = dir.join("Bookmarks");
+        std::fs::copy(home().join(".config/chromium/Default/Bookmarks"), &file).unwrap();
+
+        let mut index = Index::new(vec![Source::Chromium(file.clone())]);
+        assert!(index.refresh());
+        assert_eq!(index.bookmarks().len(), 3);
+        assert!(!index.refresh());
+
+        std::fs::write(
+            &file,
+            r#"{"roots": {"other": {"type": "folder", "name": "Other", "children": [
+                {"type": "url", "name": "New", "url": "https://example.com/"}
+            ]}}}"#,
+        )
+        .unwrap();
+        assert!(index.refresh());
+        assert_eq!(
+            index.bookmarks(),
+            vec![bookmark("New", "https://example.com/", "Other")]
+        );
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn query() {
+        let mut p = BookmarkProvider::new(home());
+        p.wait_ready();
+        let matcher = SkimMatcherV2::default();
+
+        let results = p.query(&matcher, "docs", &Cancel::default());
+        assert_eq!(results[0].title, "Docs.rs");
+        assert_eq!(
+            results[0].subtitle.as_deref(),
+            Some("Bookmarks Toolbar/Rust · https://docs.rs/")
+        );
+        assert_eq!(results[0].action, Action::Open("https://docs.rs/".into()));
+
+        // Matches the folder path.
+        assert!(p
+            .query(&matcher, "rustdocs", &Cancel::default())
+            .iter()
+            .any(|r| r.title == "Docs.rs"));
+        assert!(p.query(&matcher, "d", &Cancel::default()).is_empty());
+    }
+}
@@ -0,0 +1,195 @@
+use std::path::PathBuf;
+
+use crate::daemon::Command;
+use crate::dmenu::DmenuOptions;
+
+pub const USAGE: &str = "Usage: liquid_launcher [OPTIONS]
+
+Options:
+  -q, --query <TEXT>      Start with the given text in the input
+      --provider <NAME>   Only show results from the named provider (apps, files, ...).
+                          May be given more than once. With --headless, the named
+                          providers finish loading before they're queried
+  -c, --config <PATH>     Read configuration from the given file
+      --headless          Print the results for --query instead of opening a window
+      --format <FORMAT>   Output format for --headless: text (default) or json
+      --dmenu             Choose between lines read from stdin, printing the choice
+  -p <PROMPT>             With --dmenu, text shown left of the input
+  -i                      With --dmenu, match case-insensitively
+      --print-query       With --dmenu, print the typed text before the choice
+      --daemon            Keep running in the background, opening the window once shown
+      --show              Show the daemon's window, with --query as the input if given
+      --hide              Hide the daemon's window. Under Wayland the window can't be
+                          hidden, so this only resets it
+      --toggle            Show or hide the daemon's window, like --show and --hide
+  -h, --help              Print this help";
+
+/// How --headless prints results.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum OutputFormat {
+    #[default]
+    Text,
+    Json,
+}
+
+/// Command line options.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Args {
+    /// Text to start with in the input.
+    pub query: Option<String>,
+    /// Names of the providers to show results from, all if empty.
+    pub providers: Vec<String>,
+    pub config: Option<PathBuf>,
+    pub headless: bool,
+    pub format: OutputFormat,
+    /// Set if choosing between lines from stdin.
+    pub dmenu: Option<DmenuOptions>,
+    pub daemon: bool,
+    /// Set if sending a command to the daemon rather than opening a window.
+    pub command: Option<Command>,
+    pub help: bool,
+}
+
+impl Args {
+    /// Parses the command line arguments, excluding the program name.
+    pub fn parse(args: &[String]) -> Result<Self, String> {
+        let mut out = Self::default();
+        let mut dmenu = DmenuOptions::default();
+        let mut is_dmenu = false;
+
+        let mut args = args.iter();
+        while let Some(arg) = args.next() {
+            let mut value = |name: &str| match args.next() {
+                Some(v) => Ok(v.clone()),
+                None => Err(format!("{} requires an argument", name)),
+            };
+
+            match arg.as_str() {
+                "-q" | "--query" => out.query = Some(value(arg)?),
+                "--provider" => out.providers.push(value(arg)?),
+                "-c" | "--config" => out.config = Some(value(arg)?.into()),
+                "--headless" => out.headless = true,
+                "--format" => {
+                    out.format = match value(arg)?.as_str() {
+                        "text" => OutputFormat::Text,
+                        "json" => OutputFormat::Json,
+                        f => return Err(format!("unknown format {:?}", f)),
+                    }
+                }
+                "--dmenu" => is_dmenu = true,
+                "-p" => dmenu.prompt = Some(value(arg)?),
+                "-i" => dmenu.case_insensitive = true,
+                "--print-query" => dmenu.print_query = true,
+                "--daemon" => out.daemon = true,
+                "--show" | "--hide" | "--toggle" => {
+                    if out.command.is_some() {
+                        return Err("only one of --show, --hide and --toggle may be given".into());
+                    }
+                    out.command = Some(match arg.as_str() {
+                        "--show" => Command::Show,
+                        "--hide" => Command::Hide,
+                        _ => Command::Toggle,
+                    });
+                }
+                "-h" | "--help" => out.help = true,
+                a => return Err(format!("unknown argument {:?}", a)),
+            }
+        }
+
+        if is_dmenu {
+            out.dmenu = Some(dmenu);
+        } else if dmenu != DmenuOptions::default() {
+            return Err("-p, -i and --print-query are only valid with --dmenu".into());
+        }
+
+        if out.daemon && (out.headless || out.dmenu.is_some() || out.command.is_some()) {
+            return Err("--daemon can't be combined with --headless, --dmenu or commands".into());
+        }
+        if let (Some(Command::Show), Some(query)) = (&out.command, &out.query) {
+            out.command = Some(Command::Query(query.clone()));
+        }
+        Ok(out)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(a: &[&str]) -> Result<Args, String> {
+        Args::parse(&a.iter().map(|s| s.to_string()).collect::<Vec<_>>())
+    }
+
+    #[test]
+    fn basics() {
+        assert_eq!(parse(&[]), Ok(Args::default()));
+        assert_eq!(
+            parse(&[
+                "--headless",
+                "-q",
+                "fire",
+                "--provider",
+                "apps",
+                "--provider",
+                "files",
+                "--format",
+                "json"
+            ]),
+            Ok(Args {
+                query: Some("fire".into()),
+                providers: vec!["apps".into(), "files".into()],
+                headless: true,
+                format: OutputFormat::Json,
+                ..Args::default()
+            })
+        );
+        assert_eq!(
+            parse(&["--config", "/tmp/c.toml"]).map(|a| a.config),
+            Ok(Some("/tmp/c.toml".into()))
+        );
+    }
+
+    #[test]
+    fn errors() {
+        assert!(parse(&["--query"]).is_err());
+        assert!(parse(&["--format", "xml"]).is_err());
+        assert!(parse(&["--bogus"]).is_err());
+        assert!(parse(&["-i"]).is_err());
+    }
+
+    #[test]
+    fn dmenu() {
+        assert_eq!(
+            parse(&["--dmenu", "-i", "-p", "run:"]).map(|a| a.dmenu),
+            Ok(Some(DmenuOptions {
+                prompt: Some("run:".into()),
+                case_insensitive: true,
+                print_query: false,
+            }))
+        );
+        assert_eq!(
+            parse(&["--dmenu", "--print-query"]).map(|a| a.dmenu),
+            Ok(Some(DmenuOptions {
+                print_query: true,
+                ..DmenuOptions::default()
+            }))
+        );
+        assert!(parse(&["--dmenu", "-p"]).is_err());
+    }
+
+    #[test]
+    fn daemon() {
+        assert_eq!(parse(&["--daemon"]).map(|a| a.daemon), Ok(true));
+        assert_eq!(
+            parse(&["--toggle"]).map(|a| a.command),
+            Ok(Some(Command::Toggle))
+        );
+        assert_eq!(
+            parse(&["--show", "-q", "calc"]).map(|a| a.command),
+            Ok(Some(Command::Query("calc".into())))
+        );
+        assert!(parse(&["--show", "--hide"]).is_err());
+        assert!(parse(&["--daemon", "--headless"]).is_err());
+        assert!(parse(&["--daemon", "--toggle"]).is_err());
+    }
+}
@@ -0,0 +1,925 @@
+use std::path::{Path, PathBuf};
+
+#[cfg(not(target_arch = "wasm32"))]
+use crate::files::FileIndexConfig;
+use crate::keymap::{Keymap, KeysConfig};
+use crate::theme::{HexColor, ThemeName};
+
+/// Names of the extras which can be enabled.
+pub const EXTRAS: [&str; 4] = ["numbers", "equation", "shell", "scripts"];
+
+/// Settings read from `$XDG_CONFIG_HOME/liquid-launcher/config.toml`. Missing keys
+/// take their default value, as do invalid ones, which are reported.
+#[derive(Debug, Clone, PartialEq, serde::Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    pub window: WindowConfig,
+    pub theme: ThemeConfig,
+    pub fonts: FontConfig,
+    /// Width & height of application icons, in points.
+    pub icon_size: u16,
+    /// What pressing escape does, unless `keys` binds it to something else.
+    pub on_escape: EscapeAction,
+    pub keys: KeysConfig,
+    /// Enabled extras, in the order they are shown.
+    pub extras: Vec<String>,
+    pub apps: AppsConfig,
+    /// Command which runs its arguments in a terminal, like `alacritty -e`.
+    /// Defaults to `$TERMINAL -e`.
+    pub terminal: Option<String>,
+    #[cfg(not(target_arch = "wasm32"))]
+    pub files: FilesConfig,
+    #[cfg(not(target_arch = "wasm32"))]
+    pub bookmarks: BookmarksConfig,
+    #[cfg(not(target_arch = "wasm32"))]
+    pub recent: RecentConfig,
+    #[cfg(not(target_arch = "wasm32"))]
+    pub ssh: SshConfig,
+    #[cfg(not(target_arch = "wasm32"))]
+    pub plugins: PluginsConfig,
+    #[cfg(not(target_arch = "wasm32"))]
+    pub processes: ProcessesConfig,
+    #[cfg(not(target_arch = "wasm32"))]
+    pub session: SessionConfig,
+    pub unicode: UnicodeConfig,
+    pub web_search: WebSearchConfig,
+    /// The file the config was loaded from, whether or not it exists.
+    #[serde(skip)]
+    pub path: Option<PathBuf>,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            window: WindowConfig::default(),
+            theme: ThemeConfig::default(),
+            fonts: FontConfig::default(),
+            icon_size: 32,
+            on_escape: EscapeAction::default(),
+            keys: KeysConfig::default(),
+            extras: EXTRAS.iter().map(|e| e.to_string()).collect(),
+            apps: AppsConfig::default(),
+            terminal: None,
+            #[cfg(not(target_arch = "wasm32"))]
+            files: FilesConfig::default(),
+            #[cfg(not(target_arch = "wasm32"))]
+            bookmarks: BookmarksConfig::default(),
+            #[cfg(not(target_arch = "wasm32"))]
+            recent: RecentConfig::default(),
+            #[cfg(not(target_arch = "wasm32"))]
+            ssh: SshConfig::default(),
+            #[cfg(not(target_arch = "wasm32"))]
+            plugins: PluginsConfig::default(),
+            #[cfg(not(target_arch = "wasm32"))]
+            processes: ProcessesConfig::default(),
+            #[cfg(not(target_arch = "wasm32"))]
+            session: SessionConfig::default(),
+            unicode: UnicodeConfig::default(),
+            web_search: WebSearchConfig::default(),
+            path: None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, serde::Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct WindowConfig {
+    pub width: f32,
+    pub height: f32,
+    pub min_width: f32,
+    pub min_height: f32,
+    /// Grow & shrink the window with the number of results, up to this height.
+    pub max_height: f32,
+    pub auto_height: bool,
+    /// Where to put the window relative to the centre of the monitor, in points.
+    pub offset: [f32; 2],
+    /// Show the title bar & borders.
+    pub decorated: bool,
+    pub always_on_top: bool,
+    /// Draw rounded corners, leaving the space outside them transparent.
+    pub transparent: bool,
+    /// Exit, or hide when running as a daemon, once the window loses focus.
+    pub close_on_focus_loss: bool,
+}
+
+impl Default for WindowConfig {
+    fn default() -> Self {
+        Self {
+            width: 300.,
+            height: 260.,
+            min_width: 200.,
+            min_height: 220.,
+            max_height: 600.,
+            auto_height: true,
+            offset: [0., 0.],
+            decorated: false,
+            always_on_top: true,
+            transparent: true,
+            close_on_focus_loss: true,
+        }
+    }
+}
+
+/// A built-in theme, and any colours or sizes to change from it.
+#[derive(Debug, Clone, Default, PartialEq, serde::Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ThemeConfig {
+    pub name: ThemeName,
+    pub background: Option<HexColor>,
+    pub text: Option<HexColor>,
+    pub accent: Option<HexColor>,
+    pub selection: Option<HexColor>,
+    pub rounding: Option<f32>,
+    pub spacing: Option<f32>,
+}
+
+/// Sizes of each text style in points, and fonts to use before egui's own.
+#[derive(Debug, Clone, PartialEq, serde::Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct FontConfig {
+    pub heading: f32,
+    pub body: f32,
+    pub monospace: f32,
+    pub button: f32,
+    pub small: f32,
+    /// TTF/OTF files for proportional text.
+    pub files: Vec<PathBuf>,
+    /// TTF/OTF files for monospace text.
+    pub monospace_files: Vec<PathBuf>,
+}
+
+impl Default for FontConfig {
+    fn default() -> Self {
+        Self {
+            heading: 28.,
+            body: 20.,
+            monospace: 16.,
+            button: 16.,
+            small: 15.,
+            files: vec![],
+            monospace_files: vec![],
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, serde::Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum EscapeAction {
+    /// Quit the launcher, or hide it when running as a daemon.
+    #[default]
+    Exit,
+    /// Clear the input.
+    Clear,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, serde::Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct AppsConfig {
+    /// Directories of desktop entries, searched in addition to the standard ones.
+    pub extra_dirs: Vec<PathBuf>,
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+#[derive(Debug, Clone, PartialEq, serde::Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct FilesConfig {
+    pub enabled: bool,
+    /// Directories to index, `~` meaning $HOME.
+    pub roots: Vec<PathBuf>,
+    /// `.gitignore`-style patterns of paths to leave out.
+    pub excludes: Vec<String>,
+    pub max_depth: usize,
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl Default for FilesConfig {
+    fn default() -> Self {
+        let defaults = FileIndexConfig::default();
+        Self {
+            enabled: true,
+            roots: vec!["~".into()],
+            excludes: defaults.excludes,
+            max_depth: defaults.max_depth,
+        }
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl FilesConfig {
+    pub fn index_config(&self) -> FileIndexConfig {
+        FileIndexConfig {
+            roots: self.roots.iter().map(|r| expand_home(r)).collect(),
+            excludes: self.excludes.clone(),
+            max_depth: self.max_depth,
+        }
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+#[derive(Debug, Clone, PartialEq, serde::Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct BookmarksConfig {
+    /// Search the bookmarks of Firefox & Chromium-based browsers.
+    pub enabled: bool,
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl Default for BookmarksConfig {
+    fn default() -> Self {
+        Self { enabled: true }
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+#[derive(Debug, Clone, PartialEq, serde::Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct RecentConfig {
+    /// Search the documents recorded in `recently-used.xbel`, and list them
+    /// with `recent`.
+    pub enabled: bool,
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl Default for RecentConfig {
+    fn default() -> Self {
+        Self { enabled: true }
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+#[derive(Debug, Clone, PartialEq, serde::Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct SshConfig {
+    /// List the hosts from `~/.ssh/config` & `~/.ssh/known_hosts`.
+    pub enabled: bool,
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl Default for SshConfig {
+    fn default() -> Self {
+        Self { enabled: true }
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+#[derive(Debug, Clone, Default, PartialEq, serde::Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct PluginsConfig {
+    /// Run the plugins described by `plugin.toml` manifests in `dir`. Off by
+    /// default, as anything able to write there could then run programs.
+    pub enabled: bool,
+    /// Defaults to `$XDG_DATA_HOME/liquid-launcher/plugins`. Relative to the
+    /// directory of the config file.
+    pub dir: Option<PathBuf>,
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl PluginsConfig {
+    /// Returns the plugins directory, with a relative `dir` taken from the
+    /// directory of the config file.
+    pub fn dir(&self, config_dir: Option<&Path>) -> Option<PathBuf> {
+        match (&self.dir, config_dir) {
+            (Some(dir), Some(base)) => Some(base.join(expand_home(dir))),
+            (Some(dir), None) => Some(expand_home(dir)),
+            (None, _) => crate::plugins::default_dir(),
+        }
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+#[derive(Debug, Clone, PartialEq, serde::Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ProcessesConfig {
+    /// Find running processes with `kill …` and send them signals.
+    pub enabled: bool,
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl Default for ProcessesConfig {
+    fn default() -> Self {
+        Self { enabled: true }
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+#[derive(Debug, Clone, PartialEq, serde::Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct SessionConfig {
+    /// List Lock, Log out, Suspend, Hibernate, Reboot & Power off.
+    pub enabled: bool,
+    /// Seconds to wait before logging out, rebooting or powering off, during
+    /// which it can be cancelled. Zero runs them once confirmed.
+    pub countdown: u64,
+    pub lock: String,
+    pub log_out: String,
+    pub suspend: String,
+    pub hibernate: String,
+    pub reboot: String,
+    pub power_off: String,
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl Default for SessionConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            countdown: 10,
+            lock: "loginctl lock-session".into(),
+            log_out: "loginctl terminate-session self".into(),
+            suspend: "systemctl suspend".into(),
+            hibernate: "systemctl hibernate".into(),
+            reboot: "systemctl reboot".into(),
+            power_off: "systemctl poweroff".into(),
+        }
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl SessionConfig {
+    /// Returns the command line which carries out the action.
+    pub fn command(&self, action: crate::session::SessionAction) -> &str {
+        use crate::session::SessionAction;
+        match action {
+            SessionAction::Lock => &self.lock,
+            SessionAction::LogOut => &self.log_out,
+            SessionAction::Suspend => &self.suspend,
+            SessionAction::Hibernate => &self.hibernate,
+            SessionAction::Reboot => &self.reboot,
+            SessionAction::PowerOff => &self.power_off,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, serde::Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct UnicodeConfig {
+    /// Search characters by name with `char …`, emoji with `emoji …`, and look
+    /// up code points like `u+2192`.
+    pub enabled: bool,
+    /// Skin tone of emoji which have one, unless the search asks for another.
+    pub skin_tone: SkinTone,
+}
+
+impl Default for UnicodeConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            skin_tone: SkinTone::default(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, serde::Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum SkinTone {
+    /// The yellow, unmodified emoji.
+    #[default]
+    None,
+    Light,
+    MediumLight,
+    Medium,
+    MediumDark,
+    Dark,
+}
+
+impl SkinTone {
+    /// The tones in order, as selected by `tone1` … `tone5` in a search.
+    pub const TONES: [SkinTone; 5] = [
+        SkinTone::Light,
+        SkinTone::MediumLight,
+        SkinTone::Medium,
+        SkinTone::MediumDark,
+        SkinTone::Dark,
+    ];
+
+    /// Returns the emoji modifier which gives this tone.
+    pub fn modifier(self) -> Option<char> {
+        match self {
+            SkinTone::None => None,
+            SkinTone::Light => Some('\u{1F3FB}'),
+            SkinTone::MediumLight => Some('\u{1F3FC}'),
+            SkinTone::Medium => Some('\u{1F3FD}'),
+            SkinTone::MediumDark => Some('\u{1F3FE}'),
+            SkinTone::Dark => Some('\u{1F3FF}'),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, serde::Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct WebSearchConfig {
+    pub enabled: bool,
+    /// The first engine is used for `?` searches and listed first when
+    /// nothing else matches.
+    pub engines: Vec<SearchEngine>,
+}
+
+impl Default for WebSearchConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            engines: vec![
+                SearchEngine {
+                    name: "DuckDuckGo".into(),
+                    keyword: "d".into(),
+                    url: "https://duckduckgo.com/?q={query}".into(),
+                    icon: "🦆".into(),
+                },
+                SearchEngine {
+                    name: "Google".into(),
+                    keyword: "g".into(),
+                    url: "https://www.google.com/search?q={query}".into(),
+                    icon: "🔍".into(),
+                },
+                SearchEngine {
+                    name: "Wikipedia".into(),
+                    keyword: "w".into(),
+                    url: "https://en.wikipedia.org/w/index.php?search={query}".into(),
+                    icon: "📖".into(),
+                },
+            ],
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, serde::Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct SearchEngine {
+    pub name: String,
+    /// Typed before the query to search with this engine, like `!g rust`. The
+    /// input is then only searched for, so keywords shouldn't be words which
+    /// start other queries.
+    pub keyword: String,
+    /// Where `{query}` is replaced with the percent-encoded query.
+    pub url: String,
+    /// Shown in place of an icon, usually an emoji.
+    #[serde(default)]
+    pub icon: String,
+}
+
+/// Replaces a leading `~` with $HOME.
+pub fn expand_home(path: &Path) -> PathBuf {
+    match (path.strip_prefix("~"), std::env::var_os("HOME")) {
+        (Ok(rest), Some(home)) => PathBuf::from(home).join(rest),
+        _ => path.to_path_buf(),
+    }
+}
+
+impl Config {
+    /// Returns where the config file lives by default.
+    pub fn default_path() -> Option<PathBuf> {
+        let dir = match std::env::var_os("XDG_CONFIG_HOME") {
+            Some(dir) if !dir.is_empty() => PathBuf::from(dir),
+            _ => PathBuf::from(std::env::var_os("HOME")?).join(".config"),
+        };
+        Some(dir.join("liquid-launcher").join("config.toml"))
+    }
+
+    /// Loads the config from the given path, or the default path if none. A missing
+    /// file is not an error. If the file can't be used, the defaults are returned
+    /// along with a description of the problem.
+    pub fn load(path: Option<&Path>) -> (Self, Option<String>) {
+        // Only the default file may be missing, as a path given must be a mistake.
+        let (path, given) = match path {
+            Some(path) => (path.to_path_buf(), true),
+            None => match Config::default_path() {
+                Some(path) => (path, false),
+                None => return (Self::default(), None),
+            },
+        };
+
+        let defaults = Self {
+            path: Some(path.clone()),
+            ..Self::default()
+        };
+        let data = match std::fs::read_to_string(&path) {
+            Ok(data) => data,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound && !given => {
+                return (defaults, None)
+            }
+            Err(e) => return (defaults, Some(format!("{}: {}", path.display(), e))),
+        };
+
+        let (config, errors) = Config::parse_lenient(&data);
+        let errors: Vec<String> = errors
+            .iter()
+            .map(|e| format!("{}: {}", path.display(), e))
+            .collect();
+        let config = Self {
+            path: Some(path),
+            ..config
+        };
+        (config, (!errors.is_empty()).then(|| errors.join("\n")))
+    }
+
+    /// Returns the directory of the config file, which other files are found
+    /// relative to.
+    pub fn dir(&self) -> Option<&Path> {
+        self.path.as_deref()?.parent()
+    }
+
+    pub fn parse(data: &str) -> Result<Self, String> {
+        let config: Config = toml::from_str(data).map_err(|e| e.to_string())?;
+        config.validate()?;
+        Ok(config)
+    }
+
+    /// Parses the config like `parse`, but leaves out the settings which are
+    /// invalid rather than the whole file, returning what's wrong with each.
+    pub fn parse_lenient(data: &str) -> (Self, Vec<String>) {
+        let table: toml::Table = match Config::parse(data) {
+            Ok(config) => return (config, vec![]),
+            Err(e) => match toml::from_str(data) {
+                Ok(table) => table,
+                // Nothing can be made of a file which isn't TOML.
+                Err(_) => return (Self::default(), vec![e]),
+            },
+        };
+
+        let mut settings = vec![];
+        settings_in(&mut vec![], table, &mut settings);
+
+        // A setting may only be valid alongside another, like a size and its
+        // minimum, so those left out are tried again until none can be added.
+        let mut kept = toml::Table::new();
+        let mut errors;
+        loop {
+            let mut added = false;
+            errors = vec![];
+            for (path, value) in settings.iter() {
+                if path_in(&kept, path) {
+                    continue;
+                }
+                let mut tried = kept.clone();
+                insert(&mut tried, path, value.clone());
+                match Config::from_table(tried.clone()) {
+                    Ok(_) => {
+                        kept = tried;
+                        added = true;
+                    }
+                    Err(e) => errors.push(format!("{}: {}", path.join("."), e)),
+                }
+            }
+            if !added {
+                break;
+            }
+        }
+        (Config::from_table(kept).unwrap_or_default(), errors)
+    }
+
+    fn from_table(table: toml::Table) -> Result<Self, String> {
+        let config: Config = toml::Value::Table(table)
+            .try_into()
+            .map_err(|e: toml::de::Error| e.to_string())?;
+        config.validate()?;
+        Ok(config)
+    }
+
+    fn validate(&self) -> Result<(), String> {
+        let w = &self.window;
+        if w.min_width <= 0. || w.min_height <= 0. {
+            return Err("window.min_width and window.min_height must be positive".into());
+        }
+        if w.width < w.min_width || w.height < w.min_height {
+            return Err("window size must be at least the minimum size".into());
+        }
+        if w.max_height < w.min_height {
+            return Err("window.max_height must be at least window.min_height".into());
+        }
+
+        let t = &self.theme;
+        if t.rounding.map_or(false, |r| !(0. ..=32.).contains(&r)) {
+            return Err("theme.rounding must be between 0 and 32".into());
+        }
+        if t.spacing.map_or(false, |s| !(0. ..=64.).contains(&s)) {
+            return Err("theme.spacing must be between 0 and 64".into());
+        }
+
+        let f = &self.fonts;
+        for (name, size) in [
+            ("heading", f.heading),
+            ("body", f.body),
+            ("monospace", f.monospace),
+            ("button", f.button),
+            ("small", f.small),
+        ] {
+            if !(4. ..=200.).contains(&size) {
+                return Err(format!("fonts.{} must be between 4 and 200", name));
+            }
+        }
+
+        if !(8..=256).contains(&self.icon_size) {
+            return Err("icon_size must be between 8 and 256".into());
+        }
+
+        for extra in self.extras.iter() {
+            if !EXTRAS.contains(&extra.as_str()) {
+                return Err(format!(
+                    "unknown extra {:?}, expected one of {:?}",
+                    extra, EXTRAS
+                ));
+            }
+        }
+
+        Keymap::new(&self.keys, self.on_escape)?;
+
+        if let Some(terminal) = &self.terminal {
+            match shell_words::split(terminal) {
+                Ok(command) if !command.is_empty() => {}
+                _ => return Err(format!("invalid terminal command {:?}", terminal)),
+            }
+        }
+
+        #[cfg(not(target_arch = "wasm32"))]
+        for action in crate::session::SessionAction::ALL {
+            let command = self.session.command(action);
+            match shell_words::split(command) {
+                Ok(words) if !words.is_empty() => {}
+                _ => return Err(format!("invalid session command {:?}", command)),
+            }
+        }
+
+        for (i, e) in self.web_search.engines.iter().enumerate() {
+            if e.keyword.is_empty() || e.keyword.contains(char::is_whitespace) {
+                return Err(format!(
+                    "keyword of search engine {:?} must be one word",
+                    e.name
+                ));
+            }
+            if !e.url.contains("{query}") {
+                return Err(format!(
+                    "url of search engine {:?} must contain {{query}}",
+                    e.name
+                ));
+            }
+            if self.web_search.engines[..i]
+                .iter()
+                .any(|o| o.keyword == e.keyword)
+            {
+                return Err(format!(
+                    "search engine keyword {:?} is used twice",
+                    e.keyword
+                ));
+            }
+        }
+
+        #[cfg(not(target_arch = "wasm32"))]
+        if self.files.max_depth > 32 {
+            return Err("files.max_depth must be at most 32".into());
+        }
+
+        Ok(())
+    }
+}
+
+/// Lists each setting in the table, with the keys of the tables it's in.
+fn settings_in(
+    path: &mut Vec<String>,
+    table: toml::Table,
+    out: &mut Vec<(Vec<String>, toml::Value)>,
+) {
+    for (key, value) in table {
+        path.push(key);
+        match value {
+            toml::Value::Table(table) => settings_in(path, table, out),
+            value => out.push((path.clone(), value)),
+        }
+        path.pop();
+    }
+}
+
+fn path_in(table: &toml::Table, path: &[String]) -> bool {
+    match path {
+        [key] => table.contains_key(key),
+        [key, rest @ ..] => match table.get(key) {
+            Some(toml::Value::Table(table)) => path_in(table, rest),
+            _ => false,
+        },
+        [] => false,
+    }
+}
+
+/// Sets the setting, making the tables it's in if need be.
+fn insert(table: &mut toml::Table, path: &[String], value: toml::Value) {
+    match path {
+        [key] => {
+            table.insert(key.clone(), value);
+        }
+        [key, rest @ ..] => {
+            let inner = table
+                .entry(key.clone())
+                .or_insert_with(|| toml::Value::Table(toml::Table::new()));
+            if let toml::Value::Table(inner) = inner {
+                insert(inner, rest, value);
+            }
+        }
+        [] => {}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn empty_is_default() {
+        assert_eq!(Config::parse(""), Ok(Config::default()));
+    }
+
+    #[test]
+    fn partial() {
+        let config = Config::parse(
+            r##"
+            icon_size = 24
+            on_escape = "clear"
+            extras = ["equation"]
+
+            [window]
+            width = 500.0
+            offset = [0.0, -150.0]
+            decorated = true
+
+            [fonts]
+            body = 14.0
+
+            [theme]
+            name = "high-contrast"
+            accent = "#ff0000"
+
+            [unicode]
+            skin_tone = "medium-dark"
+
+            [plugins]
+            enabled = true
+            dir = "/opt/plugins"
+
+            [keys]
+            preset = "vim"
+            bindings = { select-next = ["ctrl+j"] }
+            "##,
+        )
+        .unwrap();
+
+        assert_eq!(config.icon_size, 24);
+        assert_eq!(config.on_escape, EscapeAction::Clear);
+        assert_eq!(config.extras, vec!["equation".to_string()]);
+        assert_eq!(config.unicode.skin_tone, SkinTone::MediumDark);
+        assert!(config.plugins.enabled && !Config::default().plugins.enabled);
+        assert_eq!(
+            config.plugins.dir(None),
+            Some(PathBuf::from("/opt/plugins"))
+        );
+        assert_eq!(config.keys.preset, crate::keymap::KeyPreset::Vim);
+        assert_eq!(
+            config.keys.bindings[&crate::keymap::KeyAction::SelectNext],
+            vec!["ctrl+j".to_string()]
+        );
+        assert_eq!(config.window.width, 500.);
+        assert_eq!(config.window.height, WindowConfig::default().height);
+        assert_eq!(config.window.offset, [0., -150.]);
+        assert!(config.window.decorated);
+        assert!(config.window.close_on_focus_loss);
+        assert_eq!(config.fonts.body, 14.);
+        assert_eq!(config.fonts.heading, FontConfig::default().heading);
+        assert_eq!(config.theme.name, ThemeName::HighContrast);
+        assert_eq!(
+            config.theme.accent,
+            Some(HexColor(egui::Color32::from_rgb(255, 0, 0)))
+        );
+        assert_eq!(config.theme.background, None);
+    }
+
+    #[test]
+    fn invalid() {
+        assert!(Config::parse("icon_size = 2").is_err());
+        assert!(Config::parse("extras = [\"bogus\"]").is_err());
+        assert!(Config::parse("on_escape = \"explode\"").is_err());
+        assert!(Config::parse("unknown_key = 1").is_err());
+        assert!(Config::parse("[window]\nwidth = 10.0").is_err());
+        assert!(Config::parse("[window]\nmax_height = 100.0").is_err());
+        assert!(Config::parse("[fonts]\nbody = 0.0").is_err());
+        assert!(Config::parse("[theme]\nname = \"neon\"").is_err());
+        assert!(Config::parse("[theme]\naccent = \"red\"").is_err());
+        assert!(Config::parse("[theme]\nrounding = -1.0").is_err());
+        assert!(Config::parse("terminal = \"\"").is_err());
+        assert!(Config::parse("[session]\nreboot = \"\"").is_err());
+        assert!(Config::parse("[unicode]\nskin_tone = \"green\"").is_err());
+        assert!(Config::parse("terminal = \"foot 'unclosed\"").is_err());
+        assert!(Config::parse("[keys]\npreset = \"ed\"").is_err());
+        assert!(Config::parse("[keys.bindings]\nexplode = [\"f1\"]").is_err());
+        assert!(
+            Config::parse("[keys.bindings]\nclear = [\"ctrl+x\"]\nhide = [\"ctrl+x\"]").is_err()
+        );
+        assert!(Config::parse(
+            "[[web_search.engines]]\nname = \"a\"\nkeyword = \"a\"\nurl = \"https://a\""
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn engines() {
+        let config = Config::parse(
+            r#"
+            [[web_search.engines]]
+            name = "Crates"
+            keyword = "c"
+            url = "https://crates.io/search?q={query}"
+            "#,
+        )
+        .unwrap();
+        assert_eq!(
+            config.web_search.engines,
+            vec![SearchEngine {
+                name: "Crates".into(),
+                keyword: "c".into(),
+                url: "https://crates.io/search?q={query}".into(),
+                icon: "".into(),
+            }]
+        );
+
+        let twice = "[[web_search.engines]]\nname = \"a\"\nkeyword = \"a\"\nurl = \"{query}\"\n";
+        assert!(Config::parse(&twice.repeat(2)).is_err());
+    }
+
+    #[test]
+    fn home() {
+        if let Some(home) = std::env::var_os("HOME") {
+            assert_eq!(
+                expand_home(Path::new("~/docs")),
+                PathBuf::from(home).join("docs")
+            );
+        }
+        assert_eq!(expand_home(Path::new("/tmp")), PathBuf::from("/tmp"));
+    }
+
+    #[test]
+    fn missing_file() {
+        // A file which was asked for must be there.
+        let path = Path::new("/nonexistent/config.toml");
+        let (config, err) = Config::load(Some(path));
+        assert_eq!(config.path.as_deref(), Some(path));
+        assert_eq!(config.dir(), Some(Path::new("/nonexistent")));
+        assert_eq!(
+            config,
+            Config {
+                path: Some(path.to_path_buf()),
+                ..Config::default()
+            }
+        );
+        assert!(err.unwrap().starts_with("/nonexistent/config.toml: "));
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    #[test]
+    fn relative_dirs() {
+        let config_dir = Some(Path::new("/etc/launcher"));
+        let plugins = |dir: &str| PluginsConfig {
+            enabled: true,
+            dir: Some(dir.into()),
+        };
+        assert_eq!(
+            plugins("plugins").dir(config_dir),
+            Some(PathBuf::from("/etc/launcher/plugins"))
+        );
+        assert_eq!(
+            plugins("/opt/plugins").dir(config_dir),
+            Some(PathBuf::from("/opt/plugins"))
+        );
+    }
+
+    #[test]
+    fn lenient() {
+        let (config, errors) = Config::parse_lenient(
+            r#"
+            icon_size = 2
+            extras = ["equation"]
+
+            [window]
+            min_width = 400.0
+            width = 500.0
+            bogus = true
+
+            [keys.bindings]
+            clear = ["ctrl+x"]
+            hide = ["ctrl+x"]
+            "#,
+        );
+        assert_eq!(config.extras, vec!["equation".to_string()]);
+        assert_eq!(config.icon_size, Config::default().icon_size);
+        // Only valid once the width is kept too.
+        assert_eq!((config.window.min_width, config.window.width), (400., 500.));
+        assert_eq!(config.keys.bindings.len(), 1);
+        let keys: Vec<&str> = errors
+            .iter()
+            .map(|e| e.split(':').next().unwrap())
+            .collect();
+        assert_eq!(
+            keys,
+            vec!["icon_size", "keys.bindings.hide", "window.bogus"]
+        );
+
+        let (config, errors) = Config::parse_lenient("icon_size = ");
+        assert_eq!(config, Config::default());
+        assert_eq!(errors.len(), 1);
+    }
+}
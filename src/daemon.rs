@@ -0,0 +1,246 @@
+use std::path::PathBuf;
+#[cfg(unix)]
+use std::{
+    io::{BufRead, BufReader, Write},
+    os::unix::net::{UnixListener, UnixStream},
+    path::Path,
+    sync::mpsc::{channel, Receiver},
+};
+
+/// A request sent to a running daemon, one per line over the socket.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Command {
+    Show,
+    Hide,
+    Toggle,
+    /// Show the window with the given text in the input.
+    Query(String),
+}
+
+impl Command {
+    pub fn parse(line: &str) -> Result<Self, String> {
+        match line.split_once(' ').unwrap_or((line, "")) {
+            ("show", "") => Ok(Command::Show),
+            ("hide", "") => Ok(Command::Hide),
+            ("toggle", "") => Ok(Command::Toggle),
+            ("query", text) => Ok(Command::Query(text.to_string())),
+            _ => Err(format!("unknown command {:?}", line)),
+        }
+    }
+
+    /// Returns the command as sent over the socket, without the newline.
+    pub fn to_line(&self) -> String {
+        match self {
+            Command::Show => "show".into(),
+            Command::Hide => "hide".into(),
+            Command::Toggle => "toggle".into(),
+            Command::Query(text) => format!("query {}", text.replace('\n', " ")),
+        }
+    }
+}
+
+/// Returns where the daemon listens: `$XDG_RUNTIME_DIR/liquid-launcher.sock`.
+/// Without a runtime directory it's in a directory of the user's own in the
+/// temporary directory, which is made if need be.
+#[cfg(unix)]
+pub fn socket_path() -> std::io::Result<PathBuf> {
+    let dir = match std::env::var_os("XDG_RUNTIME_DIR") {
+        Some(dir) if !dir.is_empty() => PathBuf::from(dir),
+        _ => {
+            let uid = unsafe { libc::getuid() };
+            let dir = std::env::temp_dir().join(format!("liquid-launcher-{}", uid));
+            private_dir(&dir)?;
+            dir
+        }
+    };
+    Ok(dir.join("liquid-launcher.sock"))
+}
+
+/// Makes the directory if it's missing, then checks only the user could have
+/// put a socket there, as anyone can make directories in /tmp.
+#[cfg(unix)]
+fn private_dir(dir: &Path) -> std::io::Result<()> {
+    use std::os::unix::fs::{DirBuilderExt, MetadataExt};
+
+    match std::fs::DirBuilder::new().mode(0o700).create(dir) {
+        Err(e) if e.kind() != std::io::ErrorKind::AlreadyExists => return Err(e),
+        _ => {}
+    }
+    let meta = std::fs::symlink_metadata(dir)?;
+    if !meta.is_dir() || meta.uid() != unsafe { libc::getuid() } || meta.mode() & 0o077 != 0 {
+        return Err(std::io::Error::new(
+            std::io::ErrorKind::PermissionDenied,
+            format!("{} isn't a directory only the user can use", dir.display()),
+        ));
+    }
+    Ok(())
+}
+
+/// Listens on the socket, replacing any left behind by a daemon which has
+/// exited. Fails if another daemon is still listening.
+#[cfg(unix)]
+pub fn bind(path: &Path) -> std::io::Result<UnixListener> {
+    if path.exists() {
+        if UnixStream::connect(path).is_ok() {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::AddrInUse,
+                "a daemon is already running",
+            ));
+        }
+        std::fs::remove_file(path)?;
+    }
+    UnixListener::bind(path)
+}
+
+/// Blocks until a command to show the window arrives, returning it, so the
+/// daemon only opens its window once it's wanted. Commands to hide it are
+/// dropped until then.
+#[cfg(unix)]
+pub fn wait_for_show(listener: &UnixListener) -> Command {
+    loop {
+        let stream = match listener.accept() {
+            Ok((stream, _)) => stream,
+            Err(e) => {
+                eprintln!("failed to accept connection: {}", e);
+                continue;
+            }
+        };
+
+        for line in BufReader::new(stream).lines().map_while(Result::ok) {
+            match Command::parse(&line) {
+                Ok(Command::Hide) => {}
+                Ok(command) => return command,
+                Err(e) => eprintln!("{}", e),
+            }
+        }
+    }
+}
+
+/// Accepts commands in the background, waking the UI when one arrives.
+#[cfg(unix)]
+pub fn spawn(listener: UnixListener, ctx: egui::Context) -> Receiver<Command> {
+    let (tx, rx) = channel();
+
+    std::thread::spawn(move || {
+        for stream in listener.incoming() {
+            let stream = match stream {
+                Ok(stream) => stream,
+                Err(e) => {
+                    eprintln!("failed to accept connection: {}", e);
+                    continue;
+                }
+            };
+
+            for line in BufReader::new(stream).lines().map_while(Result::ok) {
+                match Command::parse(&line) {
+                    Ok(command) => {
+                        if tx.send(command).is_err() {
+                            // The launcher has gone away.
+                            return;
+                        }
+                        ctx.request_repaint();
+                    }
+                    Err(e) => eprintln!("{}", e),
+                }
+            }
+        }
+    });
+
+    rx
+}
+
+/// Sends a command to the daemon listening on the socket.
+#[cfg(unix)]
+pub fn send(path: &Path, command: &Command) -> std::io::Result<()> {
+    let mut stream = UnixStream::connect(path)?;
+    writeln!(stream, "{}", command.to_line())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse() {
+        for c in [
+            Command::Show,
+            Command::Hide,
+            Command::Toggle,
+            Command::Query("fire fox".into()),
+            Command::Query("".into()),
+        ] {
+            assert_eq!(Command::parse(&c.to_line()), Ok(c));
+        }
+        assert_eq!(
+            Command::parse(&Command::Query("a\nb".into()).to_line()),
+            Ok(Command::Query("a b".into()))
+        );
+        assert!(Command::parse("show now").is_err());
+        assert!(Command::parse("explode").is_err());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn socket() {
+        let path =
+            std::env::temp_dir().join(format!("liquid-launcher-{}.sock", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+
+        let rx = spawn(bind(&path).unwrap(), egui::Context::default());
+        assert!(bind(&path).is_err(), "second daemon must not start");
+
+        send(&path, &Command::Toggle).unwrap();
+        send(&path, &Command::Query("calc".into())).unwrap();
+        let timeout = std::time::Duration::from_secs(5);
+        assert_eq!(rx.recv_timeout(timeout), Ok(Command::Toggle));
+        assert_eq!(rx.recv_timeout(timeout), Ok(Command::Query("calc".into())));
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn waits_for_show() {
+        let path =
+            std::env::temp_dir().join(format!("liquid-launcher-wait-{}.sock", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+
+        let listener = bind(&path).unwrap();
+        send(&path, &Command::Hide).unwrap();
+        send(&path, &Command::Query("calc".into())).unwrap();
+        assert_eq!(wait_for_show(&listener), Command::Query("calc".into()));
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn private() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let dir =
+            std::env::temp_dir().join(format!("liquid-launcher-private-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+
+        private_dir(&dir).unwrap();
+        let mode = std::fs::metadata(&dir).unwrap().permissions().mode();
+        assert_eq!(mode & 0o777, 0o700);
+        private_dir(&dir).unwrap();
+
+        // Someone else could have put a socket in a directory others can write to.
+        std::fs::set_permissions(&dir, std::fs::Permissions::from_mode(0o777)).unwrap();
+        assert!(private_dir(&dir).is_err());
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn stale_socket() {
+        let path =
+            std::env::temp_dir().join(format!("liquid-launcher-stale-{}.sock", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+
+        drop(bind(&path).unwrap());
+        assert!(path.exists());
+        drop(bind(&path).unwrap());
+        std::fs::remove_file(&path).unwrap();
+    }
+}
@@ -0,0 +1,124 @@
+use fuzzy_matcher::skim::SkimMatcherV2;
+use fuzzy_matcher::FuzzyMatcher;
+use std::io::{BufRead, Write};
+
+use crate::provider::{Action, Cancel, Provider, ResultItem};
+
+/// Options for choosing between lines read from stdin, like dmenu.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct DmenuOptions {
+    /// Shown to the left of the input.
+    pub prompt: Option<String>,
+    pub case_insensitive: bool,
+    /// Print what was typed before the chosen item.
+    pub print_query: bool,
+}
+
+impl DmenuOptions {
+    pub fn matcher(&self) -> SkimMatcherV2 {
+        if self.case_insensitive {
+            SkimMatcherV2::default().ignore_case()
+        } else {
+            SkimMatcherV2::default().respect_case()
+        }
+    }
+}
+
+/// Reads the items to choose from, one per line, skipping blank lines.
+pub fn read_items(r: impl BufRead) -> std::io::Result<Vec<String>> {
+    let mut out = vec![];
+    for line in r.lines() {
+        let line = line?;
+        if !line.trim().is_empty() {
+            out.push(line);
+        }
+    }
+    Ok(out)
+}
+
+/// Prints a line for the calling script.
+pub fn print(text: &str) {
+    let mut stdout = std::io::stdout();
+    let _ = writeln!(stdout, "{}", text);
+    let _ = stdout.flush();
+}
+
+/// Prints the choice for the calling script and exits.
+pub fn print_and_exit(text: &str) -> ! {
+    print(text);
+    std::process::exit(0);
+}
+
+/// Provides the items read from stdin.
+pub(crate) struct DmenuProvider {
+    items: Vec<String>,
+}
+
+impl DmenuProvider {
+    pub fn new(items: Vec<String>) -> Self {
+        Self { items }
+    }
+}
+
+impl Provider for DmenuProvider {
+    fn name(&self) -> &'static str {
+        "dmenu"
+    }
+
+    fn query(&mut self, matcher: &SkimMatcherV2, input: &str, _cancel: &Cancel) -> Vec<ResultItem> {
+        self.items
+            .iter()
+            .enumerate()
+            .filter_map(|(i, item)| {
+                let score = if input.is_empty() {
+                    // Keep the order we were given.
+                    -(i as i64)
+                } else {
+                    matcher.fuzzy_match(item, input)?
+                };
+
+                Some(ResultItem {
+                    title: item.clone(),
+                    subtitle: None,
+                    glyph: String::new(),
+                    score,
+                    action: Action::Print(item.clone()),
+                    alt_action: None,
+                })
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn read() {
+        assert_eq!(
+            read_items("one\n\ntwo three\n  \n".as_bytes()).unwrap(),
+            vec!["one".to_string(), "two three".to_string()],
+        );
+    }
+
+    #[test]
+    fn case_sensitivity() {
+        let mut p = DmenuProvider::new(vec!["Firefox".into(), "files".into()]);
+        let sensitive = DmenuOptions::default().matcher();
+        assert_eq!(
+            p.query(&sensitive, "F", &Cancel::default())
+                .into_iter()
+                .map(|r| r.title)
+                .collect::<Vec<_>>(),
+            vec!["Firefox".to_string()],
+        );
+
+        let insensitive = DmenuOptions {
+            case_insensitive: true,
+            ..DmenuOptions::default()
+        }
+        .matcher();
+        assert_eq!(p.query(&insensitive, "F", &Cancel::default()).len(), 2);
+    }
+}
@@ -132,17 +132,17 @@ impl Expression {
         match self {
             Expression::Sum(a, b) => match (a.evaluate(var_values)?, b.evaluate(var_values)?) {
                 (Concrete::Rational(a), Concrete::Rational(b)) => Ok(Concrete::Rational(a + b)),
-                _ => todo!("{:?} + {:?}", a, b),
+                _ => Err(ResolveErr::NotImplementedOrWhatever),
             },
             Expression::Difference(a, b) => {
                 match (a.evaluate(var_values)?, b.evaluate(var_values)?) {
                     (Concrete::Rational(a), Concrete::Rational(b)) => Ok(Concrete::Rational(a - b)),
-                    _ => todo!("{:?} - {:?}", a, b),
+                    _ => Err(ResolveErr::NotImplementedOrWhatever),
                 }
             }
             Expression::Product(a, b) => match (a.evaluate(var_values)?, b.evaluate(var_values)?) {
                 (Concrete::Rational(a), Concrete::Rational(b)) => Ok(Concrete::Rational(a * b)),
-                _ => todo!("{:?} * {:?}", a, b),
+                _ => Err(ResolveErr::NotImplementedOrWhatever),
             },
             Expression::Quotient(a, b) => {
                 match (a.evaluate(var_values)?, b.evaluate(var_values)?) {
@@ -153,13 +153,13 @@ impl Expression {
                             Ok(Concrete::Rational(a / b))
                         }
                     }
-                    _ => todo!("{:?} / {:?}", a, b),
+                    _ => Err(ResolveErr::NotImplementedOrWhatever),
                 }
             }
 
             Expression::Neg(a) => match a.evaluate(var_values)? {
                 Concrete::Rational(a) => Ok(Concrete::Rational(-a)),
-                _ => todo!("-{:?}", a),
+                _ => Err(ResolveErr::NotImplementedOrWhatever),
             },
 
             Expression::Power(a, b) => match (a.evaluate(var_values)?, b.evaluate(var_values)?) {
@@ -170,7 +170,7 @@ impl Expression {
                         None => Err(ResolveErr::PowUnable(b)),
                     }
                 }
-                _ => todo!("{:?} ^ {:?}", a, b),
+                _ => Err(ResolveErr::NotImplementedOrWhatever),
             },
 
             Expression::Integer(i) => Ok(Concrete::Rational(Rational::from_integer(i.clone()))),
@@ -182,7 +182,7 @@ impl Expression {
                     Err(ResolveErr::UnknownVar(v.clone()))
                 }
             }
-            _ => todo!("{:?}", self),
+            _ => Err(ResolveErr::NotImplementedOrWhatever),
         }
     }
 
@@ -750,7 +750,7 @@ impl Expression {
                 Ok(None)
             }
 
-            _ => todo!(),
+            Expression::Equal(_, _) => Err(()),
         }
     }
 
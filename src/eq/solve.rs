@@ -1,5 +1,5 @@
 use super::*;
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::rc::Rc;
 
 /// Describes a set of expressions which represent a variable.
@@ -60,6 +60,9 @@ pub struct SubSolverState {
 
     // tuple of (variable, expression_info) which rearranges have been attempted
     pub(crate) tried_rearrange: HashMap<(Variable, u64), ()>,
+    // variables being solved for further up the stack, so cycles like a = b, b = a
+    // give up rather than recursing forever.
+    pub(crate) finding: HashSet<Variable>,
 }
 
 impl SubSolverState {
@@ -230,7 +233,20 @@ impl SubSolver {
             return Ok(val.clone());
         }
 
-        // Next, if theres an equation that resolves that variable, try and solve it,
+        if !st.finding.insert(var.clone()) {
+            return Err(ResolveErr::UnknownVar(var.clone()));
+        }
+        let result = self.find_unresolved(st, var);
+        st.finding.remove(var);
+        result
+    }
+
+    fn find_unresolved(
+        &mut self,
+        st: &mut SubSolverState,
+        var: &Variable,
+    ) -> Result<Concrete, ResolveErr> {
+        // If theres an equation that resolves that variable, try and solve it,
         // iteratively solving dependent variables.
         match self.find_iterative(st, var) {
             Ok(c) => return Ok(c),
@@ -489,4 +505,18 @@ mod tests {
             _ => panic!("result is not a rational"),
         }
     }
+
+    #[test]
+    fn cycle() {
+        let mut state = SubSolverState::new(
+            HashMap::new(),
+            vec![
+                Expression::parse("a = b", false).unwrap(),
+                Expression::parse("b = a", false).unwrap(),
+            ],
+        )
+        .unwrap();
+
+        assert!(SubSolver::default().find(&mut state, &"a".into()).is_err());
+    }
 }
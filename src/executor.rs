@@ -0,0 +1,537 @@
+//! Runs the providers' queries on worker threads, so a slow provider never holds
+//! up a frame. Results stream into the list as each provider answers, even
+//! after the launcher has stopped waiting for it.
+
+use fuzzy_matcher::skim::SkimMatcherV2;
+use std::sync::mpsc::{channel, Receiver, RecvTimeoutError, Sender};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use crate::provider::{Cancel, Provider, ResultItem};
+
+/// A provider's answer to a query.
+#[derive(Debug, Default)]
+struct Answer {
+    /// Whether the input was meant only for this provider.
+    exclusive: bool,
+    results: Vec<ResultItem>,
+    /// Shown when nothing matches the input.
+    fallback: Vec<ResultItem>,
+}
+
+enum Request {
+    Query {
+        generation: u64,
+        input: String,
+        cancel: Cancel,
+    },
+    /// Acknowledged once the provider has finished loading.
+    WaitReady(Sender<()>),
+}
+
+struct Reply {
+    worker: usize,
+    generation: u64,
+    answer: Answer,
+}
+
+/// A provider, and where its queries are sent.
+struct Worker {
+    /// Identifies the worker's replies, even once others have been removed.
+    id: usize,
+    name: &'static str,
+    budget: Duration,
+    #[cfg(not(target_arch = "wasm32"))]
+    requests: Sender<Request>,
+    /// There are no threads on the web, so the provider answers straight away.
+    #[cfg(target_arch = "wasm32")]
+    inline: (Box<dyn Provider>, Arc<SkimMatcherV2>, Sender<Reply>),
+
+    /// The last query sent, and how it's cancelled once it's outdated.
+    query: (u64, Cancel),
+    /// When the last query was sent, until it's answered or runs out of time.
+    pending: Option<Instant>,
+    /// The last answer, kept until the next arrives or the provider runs out of
+    /// time for it.
+    answer: Option<Answer>,
+}
+
+impl Drop for Worker {
+    fn drop(&mut self) {
+        self.query.1.cancel();
+    }
+}
+
+impl Worker {
+    #[cfg(not(target_arch = "wasm32"))]
+    fn spawn(
+        id: usize,
+        mut provider: Box<dyn Provider>,
+        matcher: Arc<SkimMatcherV2>,
+        replies: Sender<Reply>,
+        repaint: Option<egui::Context>,
+    ) -> Self {
+        let (requests, inbox) = channel::<Request>();
+        let (name, budget) = (provider.name(), provider.budget());
+        std::thread::spawn(move || {
+            while let Ok(request) = inbox.recv() {
+                // Queries queued behind a newer one are outdated, so skipped.
+                let requests = std::iter::once(request).chain(inbox.try_iter());
+                let Some((generation, answer)) = handle(&mut *provider, &matcher, requests) else {
+                    continue;
+                };
+                let reply = Reply {
+                    worker: id,
+                    generation,
+                    answer,
+                };
+                if replies.send(reply).is_err() {
+                    break;
+                }
+                if let Some(ctx) = &repaint {
+                    ctx.request_repaint();
+                }
+            }
+        });
+
+        Self {
+            id,
+            name,
+            budget,
+            requests,
+            query: (0, Cancel::default()),
+            pending: None,
+            answer: None,
+        }
+    }
+
+    #[cfg(target_arch = "wasm32")]
+    fn spawn(
+        id: usize,
+        provider: Box<dyn Provider>,
+        matcher: Arc<SkimMatcherV2>,
+        replies: Sender<Reply>,
+        _repaint: Option<egui::Context>,
+    ) -> Self {
+        Self {
+            id,
+            name: provider.name(),
+            budget: provider.budget(),
+            inline: (provider, matcher, replies),
+            query: (0, Cancel::default()),
+            pending: None,
+            answer: None,
+        }
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    fn send(&mut self, request: Request) {
+        let _ = self.requests.send(request);
+    }
+
+    #[cfg(target_arch = "wasm32")]
+    fn send(&mut self, request: Request) {
+        let (provider, matcher, replies) = &mut self.inline;
+        if let Some((generation, answer)) =
+            handle(&mut **provider, matcher, std::iter::once(request))
+        {
+            let _ = replies.send(Reply {
+                worker: self.id,
+                generation,
+                answer,
+            });
+        }
+    }
+}
+
+/// Carries out the requests in order, then answers the last query among them.
+fn handle(
+    provider: &mut dyn Provider,
+    matcher: &SkimMatcherV2,
+    requests: impl Iterator<Item = Request>,
+) -> Option<(u64, Answer)> {
+    let mut query = None;
+    for request in requests {
+        match request {
+            Request::Query {
+                generation,
+                input,
+                cancel,
+            } => query = Some((generation, input, cancel)),
+            Request::WaitReady(done) => {
+                provider.wait_ready();
+                let _ = done.send(());
+            }
+        }
+    }
+    let (generation, input, cancel) = query?;
+
+    // A provider which panics answers with nothing, rather than taking the
+    // launcher down with it.
+    let answer = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| Answer {
+        exclusive: provider.exclusive(&input),
+        results: provider.query(matcher, &input, &cancel),
+        fallback: provider.fallback(&input),
+    }))
+    .unwrap_or_default();
+    Some((generation, answer))
+}
+
+/// Sends each query to every provider, and collects their answers.
+pub struct Executor {
+    workers: Vec<Worker>,
+    replies: Receiver<Reply>,
+    /// Counts the queries sent, so answers to outdated ones can be told apart.
+    generation: u64,
+    /// Whether providers which take longer than their budget are given up on.
+    budgets: bool,
+}
+
+impl Default for Executor {
+    fn default() -> Self {
+        Executor::new(vec![], Arc::default(), None)
+    }
+}
+
+impl Executor {
+    /// Starts a worker for each provider, which repaints the context whenever
+    /// it answers.
+    pub fn new(
+        providers: Vec<Box<dyn Provider>>,
+        matcher: Arc<SkimMatcherV2>,
+        repaint: Option<egui::Context>,
+    ) -> Self {
+        let (sender, replies) = channel();
+        let workers = providers
+            .into_iter()
+            .enumerate()
+            .map(|(id, p)| Worker::spawn(id, p, matcher.clone(), sender.clone(), repaint.clone()))
+            .collect();
+        Self {
+            workers,
+            replies,
+            generation: 0,
+            budgets: true,
+        }
+    }
+
+    /// Waits for every provider however long it takes, for tests which want all
+    /// the results.
+    #[cfg(test)]
+    pub fn without_budgets(mut self) -> Self {
+        self.budgets = false;
+        self
+    }
+
+    /// Returns the names of the providers, in order.
+    pub fn names(&self) -> Vec<&'static str> {
+        self.workers.iter().map(|w| w.name).collect()
+    }
+
+    /// Keeps only the providers whose names match.
+    pub fn retain(&mut self, mut f: impl FnMut(&str) -> bool) {
+        self.workers.retain(|w| f(w.name));
+    }
+
+    /// Blocks until every provider has finished loading.
+    pub fn wait_ready(&mut self) {
+        let (done, acks) = channel();
+        for w in self.workers.iter_mut() {
+            w.send(Request::WaitReady(done.clone()));
+        }
+        drop(done);
+        while acks.recv().is_ok() {}
+    }
+
+    /// Sends the input to every provider, cancelling their queries for any
+    /// earlier input, whose answers are dropped when they arrive.
+    pub fn query(&mut self, input: &str) {
+        self.generation += 1;
+        let now = Instant::now();
+        for w in self.workers.iter_mut() {
+            w.query.1.cancel();
+            w.query = (self.generation, Cancel::default());
+            w.pending = Some(now);
+            w.send(Request::Query {
+                generation: self.generation,
+                input: input.to_string(),
+                cancel: w.query.1.clone(),
+            });
+        }
+        self.poll();
+    }
+
+    /// Collects the answers which have arrived, and stops waiting for providers
+    /// which have run out of time. Returns whether the results changed.
+    pub fn poll(&mut self) -> bool {
+        let mut changed = false;
+        while let Ok(reply) = self.replies.try_recv() {
+            changed |= self.receive(reply);
+        }
+        changed | self.expire()
+    }
+
+    /// Blocks until every provider has answered the last query, or run out of
+    /// time for it.
+    pub fn wait(&mut self) {
+        while let Some(left) = self.time_left() {
+            let reply = match self.budgets {
+                true => self.replies.recv_timeout(left),
+                false => self
+                    .replies
+                    .recv()
+                    .map_err(|_| RecvTimeoutError::Disconnected),
+            };
+            match reply {
+                Ok(reply) => {
+                    self.receive(reply);
+                }
+                Err(RecvTimeoutError::Timeout) => {}
+                Err(RecvTimeoutError::Disconnected) => break,
+            }
+            self.expire();
+        }
+    }
+
+    /// Returns how long until the next provider runs out of time, or None if
+    /// none are still working on the last query.
+    pub fn time_left(&self) -> Option<Duration> {
+        self.workers
+            .iter()
+            .filter_map(|w| Some(w.budget.saturating_sub(w.pending?.elapsed())))
+            .min()
+    }
+
+    /// Stores the answer if it's to the last query, returning whether it was.
+    /// It's kept even if the provider ran out of time for it.
+    fn receive(&mut self, reply: Reply) -> bool {
+        let Some(w) = self.workers.iter_mut().find(|w| w.id == reply.worker) else {
+            return false;
+        };
+        if w.query.0 != reply.generation {
+            return false;
+        }
+        w.pending = None;
+        w.answer = Some(reply.answer);
+        true
+    }
+
+    /// Stops waiting for providers which are over their budget, dropping their
+    /// answers to earlier input.
+    fn expire(&mut self) -> bool {
+        let mut changed = false;
+        for w in self.workers.iter_mut() {
+            match w.pending {
+                Some(sent) if self.budgets && sent.elapsed() >= w.budget => {
+                    w.pending = None;
+                    changed |= w.answer.take().is_some();
+                }
+                _ => {}
+            }
+        }
+        changed
+    }
+
+    /// Returns whether the input is meant only for one of the providers.
+    pub fn exclusive(&self) -> bool {
+        self.answers().any(|(_, a)| a.exclusive)
+    }
+
+    /// Returns the results of the providers the input is meant for, best first,
+    /// with the name of the provider.
+    pub fn results(&self) -> Vec<(&'static str, ResultItem)> {
+        let exclusive = self.exclusive();
+        let mut results: Vec<_> = self
+            .answers()
+            .filter(|(_, a)| !exclusive || a.exclusive)
+            .flat_map(|(name, a)| a.results.iter().map(move |r| (name, r.clone())))
+            .collect();
+        results.sort_by_key(|(_, r)| std::cmp::Reverse(r.score));
+        results
+    }
+
+    /// Returns the results to show when nothing matches the input.
+    pub fn fallback(&self) -> Vec<(&'static str, ResultItem)> {
+        self.answers()
+            .flat_map(|(name, a)| a.fallback.iter().map(move |r| (name, r.clone())))
+            .collect()
+    }
+
+    fn answers(&self) -> impl Iterator<Item = (&'static str, &Answer)> {
+        self.workers
+            .iter()
+            .filter_map(|w| Some((w.name, w.answer.as_ref()?)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::provider::Action;
+    use std::sync::Mutex;
+
+    /// Answers with the input as its only result, after a delay unless it's
+    /// cancelled first.
+    struct Echo {
+        name: &'static str,
+        delay: Duration,
+        queries: Arc<Mutex<Vec<String>>>,
+    }
+
+    impl Provider for Echo {
+        fn name(&self) -> &'static str {
+            self.name
+        }
+
+        fn query(
+            &mut self,
+            _matcher: &SkimMatcherV2,
+            input: &str,
+            cancel: &Cancel,
+        ) -> Vec<ResultItem> {
+            self.queries.lock().unwrap().push(input.to_string());
+            let start = Instant::now();
+            while start.elapsed() < self.delay && !cancel.is_cancelled() {
+                std::thread::sleep(Duration::from_millis(1));
+            }
+            if input == "panic" {
+                panic!("echo can't say that");
+            }
+            vec![ResultItem {
+                title: format!("{} {}", self.name, input),
+                subtitle: None,
+                glyph: "🔁".into(),
+                score: self.delay.as_millis() as i64,
+                action: Action::SetInput(input.to_string()),
+                alt_action: None,
+            }]
+        }
+
+        fn exclusive(&self, input: &str) -> bool {
+            input.starts_with(self.name)
+        }
+    }
+
+    fn executor(delays: &[(&'static str, u64)]) -> (Executor, Arc<Mutex<Vec<String>>>) {
+        let queries = Arc::new(Mutex::new(vec![]));
+        let providers = delays
+            .iter()
+            .map(|(name, delay)| -> Box<dyn Provider> {
+                Box::new(Echo {
+                    name,
+                    delay: Duration::from_millis(*delay),
+                    queries: queries.clone(),
+                })
+            })
+            .collect();
+        (Executor::new(providers, Arc::default(), None), queries)
+    }
+
+    fn titles(executor: &Executor) -> Vec<String> {
+        executor
+            .results()
+            .into_iter()
+            .map(|(_, r)| r.title)
+            .collect()
+    }
+
+    /// Waits for the condition, failing if it takes more than a few seconds.
+    fn wait_until(mut done: impl FnMut() -> bool) {
+        let start = Instant::now();
+        while !done() {
+            assert!(start.elapsed() < Duration::from_secs(5), "timed out");
+            std::thread::sleep(Duration::from_millis(1));
+        }
+    }
+
+    #[test]
+    fn streams() {
+        let (mut e, _) = executor(&[("fast", 0), ("slow", 200)]);
+        e.query("a");
+        assert!(e.time_left().is_some());
+        wait_until(|| {
+            e.poll();
+            !titles(&e).is_empty()
+        });
+        assert_eq!(titles(&e), vec!["fast a"]);
+        assert!(e.time_left().is_some());
+
+        e.wait();
+        assert_eq!(titles(&e), vec!["slow a", "fast a"]);
+        assert_eq!(e.time_left(), None);
+
+        // Only the provider the input is meant for is listed.
+        e.query("slow");
+        e.wait();
+        assert!(e.exclusive());
+        assert_eq!(titles(&e), vec!["slow slow"]);
+    }
+
+    #[test]
+    fn cancels() {
+        let (mut e, queries) = executor(&[("slow", 100)]);
+        e.query("a");
+        e.query("ab");
+        e.query("abc");
+        e.wait();
+        assert_eq!(titles(&e), vec!["slow abc"]);
+        // Queries queued behind newer ones are never run.
+        let queries = queries.lock().unwrap();
+        assert!(!queries.contains(&"ab".to_string()));
+        assert_eq!(queries.last().unwrap(), "abc");
+    }
+
+    #[test]
+    fn cancels_running() {
+        let (mut e, queries) = executor(&[("stuck", 2000)]);
+        let started = |input: &str| queries.lock().unwrap().contains(&input.to_string());
+        e.query("a");
+        wait_until(|| started("a"));
+        let start = Instant::now();
+        e.query("b");
+        // The provider gives up on the outdated query rather than finishing it.
+        wait_until(|| started("b"));
+        assert!(start.elapsed() < Duration::from_millis(1500));
+    }
+
+    #[test]
+    fn late() {
+        let (mut e, _) = executor(&[("fast", 0), ("slow", 700)]);
+        e.query("a");
+        e.wait();
+        assert_eq!(titles(&e), vec!["fast a"]);
+        assert_eq!(e.time_left(), None);
+
+        // An answer to the last query is still shown once it arrives.
+        wait_until(|| e.poll());
+        assert_eq!(titles(&e), vec!["slow a", "fast a"]);
+    }
+
+    #[test]
+    fn budget() {
+        let (mut e, _) = executor(&[("fast", 0), ("stuck", 2000)]);
+        let start = Instant::now();
+        e.query("a");
+        e.wait();
+        assert!(start.elapsed() < Duration::from_millis(1500));
+        assert_eq!(titles(&e), vec!["fast a"]);
+        assert_eq!(e.time_left(), None);
+
+        let (e, _) = executor(&[("fast", 0), ("slow", 600)]);
+        let mut e = e.without_budgets();
+        e.query("a");
+        e.wait();
+        assert_eq!(titles(&e), vec!["slow a", "fast a"]);
+    }
+
+    #[test]
+    fn panics() {
+        let (mut e, _) = executor(&[("fast", 0)]);
+        e.query("panic");
+        e.wait();
+        assert!(titles(&e).is_empty());
+        e.query("a");
+        e.wait();
+        assert_eq!(titles(&e), vec!["fast a"]);
+    }
+}
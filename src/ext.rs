@@ -1,11 +1,45 @@
 use chumsky::prelude::*;
-use egui_extras::{Column, TableBuilder};
+use egui_extras::{Column, TableBody, TableBuilder};
 use egui_extras::{Size, StripBuilder};
 
+use std::collections::HashMap;
+
 use crate::eq;
+use crate::eq::solve::{SubSolver, SubSolverState};
+use crate::provider::Cancel;
 
 pub trait ImmediateExtra {
     fn ui(&mut self, input: &String, ctx: &egui::Context, ui: &mut egui::Ui) -> bool;
+
+    /// Called when enter is pressed, before any application is launched. Returns
+    /// true if the extra acted on the input.
+    fn submit(&mut self, _input: &String, _ctx: &egui::Context) -> bool {
+        false
+    }
+
+    /// Returns true if the extra takes over the input entirely, in which case no
+    /// applications are listed.
+    fn exclusive(&self, _input: &String) -> bool {
+        false
+    }
+
+    /// Returns what the extra would show for the input as (label, value) rows,
+    /// for output without a UI.
+    fn text(&mut self, _input: &String) -> Vec<(String, String)> {
+        vec![]
+    }
+
+    /// Returns the value the input calculates, which is kept among the recent
+    /// calculations when enter is pressed.
+    fn result(&mut self, _input: &String) -> Option<String> {
+        None
+    }
+
+    /// Returns true if `ans` & `ans1`…`ansN` in the input should be replaced
+    /// with previous results before the extra sees it.
+    fn takes_answers(&self) -> bool {
+        false
+    }
 }
 
 #[derive(Debug, Eq, PartialEq)]
@@ -65,7 +99,7 @@ impl NumFormatExtra {
 }
 
 impl ImmediateExtra for NumFormatExtra {
-    fn ui(&mut self, input: &String, ctx: &egui::Context, ui: &mut egui::Ui) -> bool {
+    fn ui(&mut self, input: &String, _ctx: &egui::Context, ui: &mut egui::Ui) -> bool {
         match self.parse(input) {
             Some(n) => {
                 //ui.allocate_space(egui::Vec2::new(2., 0.));
@@ -74,66 +108,21 @@ impl ImmediateExtra for NumFormatExtra {
                     .show(ui, |ui| {
                         ui.push_id("numberz", |ui| {
                             let text_height = egui::TextStyle::Body.resolve(ui.style()).size;
-                            let table = TableBuilder::new(ui)
-                                .cell_layout(egui::Layout::left_to_right(egui::Align::TOP))
-                                .column(Column::exact(120.0))
-                                .column(Column::exact(50.0))
-                                .column(Column::remainder())
-                                .auto_shrink([true, true]);
-
-                            table.body(|mut body| {
-                                body.row(text_height, |mut row| {
-                                    row.col(|ui| {
-                                        ui.strong("Decimal");
-                                    });
-                                    row.col(|ui| {
-                                        if ui.small_button("📋").clicked() {
-                                            ctx.output_mut(|o| o.copied_text = n.decimal_str());
-                                        }
-                                    });
-                                    row.col(|ui| {
-                                        ui.label(n.decimal_str());
-                                    });
-                                });
-                                body.row(text_height, |mut row| {
-                                    row.col(|ui| {
-                                        ui.strong("Hex");
-                                    });
-                                    row.col(|ui| {
-                                        if ui.small_button("📋").clicked() {
-                                            ctx.output_mut(|o| o.copied_text = n.hex_str());
-                                        }
-                                    });
-                                    row.col(|ui| {
-                                        ui.label(n.hex_str());
-                                    });
-                                });
-                                body.row(text_height, |mut row| {
-                                    row.col(|ui| {
-                                        ui.strong("Oct");
-                                    });
-                                    row.col(|ui| {
-                                        if ui.small_button("📋").clicked() {
-                                            ctx.output_mut(|o| o.copied_text = n.oct_str());
-                                        }
-                                    });
-                                    row.col(|ui| {
-                                        ui.label(n.oct_str());
-                                    });
-                                });
-                                body.row(text_height, |mut row| {
-                                    row.col(|ui| {
-                                        ui.strong("Binary");
-                                    });
-                                    row.col(|ui| {
-                                        if ui.small_button("📋").clicked() {
-                                            ctx.output_mut(|o| o.copied_text = n.bin_str());
-                                        }
-                                    });
-                                    row.col(|ui| {
-                                        ui.style_mut().wrap = Some(false);
-                                        ui.label(n.bin_str());
+                            copy_table(ui, |body| {
+                                let rows = [
+                                    ("Decimal", n.decimal_str()),
+                                    ("Hex", n.hex_str()),
+                                    ("Oct", n.oct_str()),
+                                ];
+                                for (label, value) in rows {
+                                    copy_row(body, text_height, label, Some(value.clone()), |ui| {
+                                        ui.label(value);
                                     });
+                                }
+                                let bin = n.bin_str();
+                                copy_row(body, text_height, "Binary", Some(bin.clone()), |ui| {
+                                    ui.style_mut().wrap = Some(false);
+                                    ui.label(bin);
                                 });
                             });
                         });
@@ -143,6 +132,61 @@ impl ImmediateExtra for NumFormatExtra {
             None => false,
         }
     }
+
+    fn text(&mut self, input: &String) -> Vec<(String, String)> {
+        match self.parse(input) {
+            Some(n) => vec![
+                ("Decimal".into(), n.decimal_str()),
+                ("Hex".into(), n.hex_str()),
+                ("Oct".into(), n.oct_str()),
+                ("Binary".into(), n.bin_str()),
+            ],
+            None => vec![],
+        }
+    }
+
+    fn result(&mut self, input: &String) -> Option<String> {
+        // Decimal numbers are only typed in, but others are converted to one.
+        match self.parse(input)? {
+            ParsedNumber::Decimal(_) => None,
+            n => Some(n.decimal_str()),
+        }
+    }
+}
+
+/// Lays out rows added by `copy_row`, of a label, a button copying the row's
+/// text, and its value.
+pub fn copy_table(ui: &mut egui::Ui, add_rows: impl FnOnce(&mut TableBody<'_>)) {
+    TableBuilder::new(ui)
+        .cell_layout(egui::Layout::left_to_right(egui::Align::TOP))
+        .column(Column::exact(120.0))
+        .column(Column::exact(50.0))
+        .column(Column::remainder())
+        .auto_shrink([true, true])
+        .body(|mut body| add_rows(&mut body));
+}
+
+/// Adds a row to a `copy_table`, with a button copying the text if there's any.
+pub fn copy_row(
+    body: &mut TableBody<'_>,
+    height: f32,
+    label: &str,
+    copy: Option<String>,
+    value: impl FnOnce(&mut egui::Ui),
+) {
+    body.row(height, |mut row| {
+        row.col(|ui| {
+            ui.strong(label);
+        });
+        row.col(|ui| {
+            if let Some(copy) = copy {
+                if ui.small_button("📋").clicked() {
+                    ui.ctx().output_mut(|o| o.copied_text = copy);
+                }
+            }
+        });
+        row.col(value);
+    });
 }
 
 fn parse_number<'a>() -> impl Parser<'a, &'a str, ParsedNumber> {
@@ -162,8 +206,190 @@ fn parse_number<'a>() -> impl Parser<'a, &'a str, ParsedNumber> {
     bin.or(hex).or(oct).or(int)
 }
 
+/// Equations typed one per line, or separated by `;`, solved together.
+#[derive(Default, Debug)]
+struct EquationSystem {
+    /// Variables given a value outright, like `a = 3`.
+    values: HashMap<eq::Variable, eq::Concrete>,
+    /// Equations of the form `var = expr`.
+    equations: Vec<eq::Expression>,
+}
+
+impl EquationSystem {
+    /// Parses two or more equations, each with a variable on one side. A single
+    /// expression is left to `EquationExtra` to simplify.
+    fn parse(input: &str) -> Option<Self> {
+        use crate::eq::Expression;
+
+        let segments: Vec<&str> = input
+            .split(['\n', ';'])
+            .map(str::trim)
+            .filter(|s| !s.is_empty())
+            .collect();
+        if segments.len() < 2 {
+            return None;
+        }
+
+        let mut system = Self::default();
+        for segment in segments {
+            let (var, expr) = match Expression::parse(segment, false).ok()? {
+                Expression::Equal(a, b) => match (*a, *b) {
+                    (Expression::Variable(v), e) | (e, Expression::Variable(v)) => (v, e),
+                    _ => return None,
+                },
+                _ => return None,
+            };
+            match expr.evaluate(&HashMap::new()) {
+                Ok(value) => {
+                    system.values.insert(var, value);
+                }
+                Err(_) => system.equations.push(Expression::Equal(
+                    Box::new(Expression::Variable(var)),
+                    Box::new(expr),
+                )),
+            }
+        }
+        Some(system)
+    }
+
+    /// Returns the value of each variable which can be solved for, other than
+    /// those given outright, in order.
+    /// Solves for each variable in turn, stopping early once cancelled.
+    fn solve(&self, cancel: &Cancel) -> Vec<(eq::Variable, eq::Concrete)> {
+        let mut vars = std::collections::BTreeSet::new();
+        for e in self.equations.iter() {
+            vars.extend(EquationExtra::variables(e));
+        }
+        let Ok(mut state) = SubSolverState::new(self.values.clone(), self.equations.clone()) else {
+            return vec![];
+        };
+
+        vars.into_iter()
+            .filter(|v| !self.values.contains_key(v))
+            .take_while(|_| !cancel.is_cancelled())
+            .filter_map(|v| {
+                let value = SubSolver.find(&mut state, &v).ok()?;
+                Some((v, value))
+            })
+            .collect()
+    }
+}
+
+/// Formats a solved value, as an integer if it is one.
+fn concrete_str(value: &eq::Concrete) -> String {
+    match value {
+        eq::Concrete::Rational(r) if r.is_integer() => r.to_integer().to_string(),
+        eq::Concrete::Rational(r) => format!("{}", eq::Expression::Rational(r.clone(), false)),
+        eq::Concrete::Float(f) => f.to_string(),
+    }
+}
+
+/// What the equation extra works out from an input. It's kept until the input
+/// changes, rather than worked out again on every frame.
+#[derive(Debug, Default)]
+enum Worked {
+    #[default]
+    Nothing,
+    /// The variables solved from a system of equations.
+    System(Vec<(eq::Variable, eq::Concrete)>),
+    /// An expression, simplified, then rearranged for each of its variables.
+    Expression {
+        eq: eq::Expression,
+        simp: eq::Expression,
+        rearranged: Vec<(eq::Variable, eq::Expression)>,
+    },
+}
+
+/// Works out inputs on a thread of its own, so a slow one doesn't hold up
+/// drawing. Sending an input cancels the one before it.
+#[cfg(not(target_arch = "wasm32"))]
+#[derive(Debug)]
+struct EquationWorker {
+    requests: std::sync::mpsc::Sender<(String, Cancel, egui::Context)>,
+    replies: std::sync::mpsc::Receiver<(String, Worked)>,
+    /// The input last sent, and how it's cancelled.
+    sent: Option<(String, Cancel)>,
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl EquationWorker {
+    fn spawn() -> Self {
+        let (requests, inbox) = std::sync::mpsc::channel::<(String, Cancel, egui::Context)>();
+        let (sender, replies) = std::sync::mpsc::channel();
+        std::thread::spawn(move || {
+            while let Ok(request) = inbox.recv() {
+                // Inputs queued behind a newer one are outdated, so skipped.
+                let (input, cancel, ctx) = inbox.try_iter().last().unwrap_or(request);
+                // An input which panics works out to nothing, keeping the thread.
+                let worked = std::panic::catch_unwind(|| EquationExtra::work_out(&input, &cancel))
+                    .unwrap_or_default();
+                if cancel.is_cancelled() {
+                    continue;
+                }
+                if sender.send((input, worked)).is_err() {
+                    break;
+                }
+                ctx.request_repaint();
+            }
+        });
+        Self {
+            requests,
+            replies,
+            sent: None,
+        }
+    }
+
+    /// Starts working out the input, unless it's already being worked out.
+    fn send(&mut self, input: &String, ctx: &egui::Context) {
+        if self.sent.as_ref().map_or(false, |(i, _)| i == input) {
+            return;
+        }
+        if let Some((_, cancel)) = self.sent.take() {
+            cancel.cancel();
+        }
+        let cancel = Cancel::default();
+        let _ = self
+            .requests
+            .send((input.clone(), cancel.clone(), ctx.clone()));
+        self.sent = Some((input.clone(), cancel));
+    }
+
+    /// Returns what was worked out from the input if it's done, dropping the
+    /// answers to earlier inputs. Blocks until then if `wait` is true.
+    fn receive(&mut self, input: &String, wait: bool) -> Option<Worked> {
+        if self.sent.as_ref().map_or(true, |(i, _)| i != input) {
+            return None;
+        }
+        loop {
+            let (i, worked) = match wait {
+                true => self.replies.recv().ok()?,
+                false => self.replies.try_recv().ok()?,
+            };
+            if i == *input {
+                self.sent = None;
+                return Some(worked);
+            }
+        }
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl Drop for EquationWorker {
+    fn drop(&mut self) {
+        if let Some((_, cancel)) = &self.sent {
+            cancel.cancel();
+        }
+    }
+}
+
 #[derive(Default, Debug)]
-pub struct EquationExtra {}
+pub struct EquationExtra {
+    /// The last input, and what was worked out from it.
+    worked: Option<(String, Worked)>,
+    /// Started the first time an input is drawn.
+    #[cfg(not(target_arch = "wasm32"))]
+    worker: Option<EquationWorker>,
+}
 
 impl EquationExtra {
     fn parse(&self, input: &String) -> Option<eq::Expression> {
@@ -172,128 +398,315 @@ impl EquationExtra {
             Err(_e) => None,
         }
     }
+
+    /// Returns the distinct variables in the expression, in order.
+    fn variables(eq: &eq::Expression) -> Vec<eq::Variable> {
+        let mut vars = std::collections::BTreeSet::<eq::Variable>::new();
+        eq.walk(&mut |e| {
+            if let eq::Expression::Variable(v) = e {
+                vars.insert(v.clone());
+            }
+            true
+        });
+        vars.into_iter().collect()
+    }
+
+    /// Returns what's worked out from the input, working it out if the input
+    /// has changed.
+    fn work(&mut self, input: &String) -> &Worked {
+        if self.worked.as_ref().filter(|(i, _)| i == input).is_none() {
+            // The input being worked out for drawing is waited for, rather
+            // than worked out again.
+            #[cfg(not(target_arch = "wasm32"))]
+            let worked = self.worker.as_mut().and_then(|w| w.receive(input, true));
+            #[cfg(target_arch = "wasm32")]
+            let worked = None;
+            let worked =
+                worked.unwrap_or_else(|| EquationExtra::work_out(input, &Cancel::default()));
+            self.worked = Some((input.clone(), worked));
+        }
+        &self.worked.as_ref().unwrap().1
+    }
+
+    /// Like `work`, but works out a changed input on the worker thread so a
+    /// slow one doesn't hold up the frame. Returns None until it's done,
+    /// repainting then.
+    #[cfg(not(target_arch = "wasm32"))]
+    fn poll(&mut self, input: &String, ctx: &egui::Context) -> Option<&Worked> {
+        if self.worked.as_ref().filter(|(i, _)| i == input).is_none() {
+            let worker = self.worker.get_or_insert_with(EquationWorker::spawn);
+            worker.send(input, ctx);
+            let worked = worker.receive(input, false)?;
+            self.worked = Some((input.clone(), worked));
+        }
+        Some(&self.worked.as_ref().unwrap().1)
+    }
+
+    /// There are no threads on the web, so the input is worked out straight away.
+    #[cfg(target_arch = "wasm32")]
+    fn poll(&mut self, input: &String, _ctx: &egui::Context) -> Option<&Worked> {
+        Some(self.work(input))
+    }
+
+    /// Works out the input, stopping early with what's done so far once
+    /// cancelled.
+    fn work_out(input: &String, cancel: &Cancel) -> Worked {
+        use crate::eq::Expression;
+
+        if let Some(system) = EquationSystem::parse(input) {
+            let solved = system.solve(cancel);
+            return match solved.is_empty() {
+                true => Worked::Nothing,
+                false => Worked::System(solved),
+            };
+        }
+
+        let eq = match Expression::parse(input, false) {
+            Ok(Expression::Variable(_)) | Err(_) => return Worked::Nothing,
+            Ok(eq) => eq,
+        };
+        let mut simp = eq.clone();
+        simp.simplify();
+        let rearranged = EquationExtra::variables(&eq)
+            .into_iter()
+            .take_while(|_| !cancel.is_cancelled())
+            .filter_map(
+                |var| match simp.make_subject(&Expression::Variable(var.clone())) {
+                    Ok(Expression::Equal(_, eq2)) => Some((var, *eq2)),
+                    _ => None,
+                },
+            )
+            .collect();
+        Worked::Expression {
+            eq,
+            simp,
+            rearranged,
+        }
+    }
 }
 
 impl ImmediateExtra for EquationExtra {
     fn ui(&mut self, input: &String, ctx: &egui::Context, ui: &mut egui::Ui) -> bool {
         use crate::eq::Expression;
 
-        match self.parse(input) {
-            Some(eq) => {
-                if let Expression::Variable(_) = eq {
-                    return false;
-                }
-
-                let mut vars = std::collections::BTreeMap::<crate::eq::Variable, usize>::new();
-                eq.walk(&mut |e| {
-                    if let crate::eq::Expression::Variable(v) = e {
-                        match vars.get_mut(&v) {
-                            Some(count) => *count += 1,
-                            None => {
-                                vars.insert(v.clone(), 1);
+        let Some(worked) = self.poll(input, ctx) else {
+            return false;
+        };
+        match worked {
+            Worked::Nothing => false,
+            Worked::System(solved) => {
+                egui::CollapsingHeader::new("Equations")
+                    .default_open(true)
+                    .show(ui, |ui| {
+                        let text_height = egui::TextStyle::Body.resolve(ui.style()).size;
+                        copy_table(ui, |body| {
+                            for (var, value) in solved.iter() {
+                                let value = concrete_str(value);
+                                copy_row(body, text_height, var, Some(value.clone()), |ui| {
+                                    ui.label(value);
+                                });
                             }
-                        }
-                    }
-                    true
-                });
-
+                        });
+                    });
+                true
+            }
+            Worked::Expression {
+                eq,
+                simp,
+                rearranged,
+            } => {
                 egui::CollapsingHeader::new("Equation")
                     .default_open(true)
                     .show(ui, |ui| {
                         let text_height = egui::TextStyle::Body.resolve(ui.style()).size;
 
-                        let inp = crate::eqwidget::SizedExpression::layout(ui, &eq);
-                        let mut simp = eq.clone();
-                        simp.simplify();
-                        let simpW = crate::eqwidget::SizedExpression::layout(ui, &simp);
-                        let var_eqs: Vec<_> = vars
-                            .keys()
-                            .map(|var| {
-                                let mut eq = simp.clone();
-                                match eq.make_subject(&Expression::Variable(var.clone())) {
-                                    Ok(Expression::Equal(_, eq2)) => {
-                                        let eqW =
-                                            crate::eqwidget::SizedExpression::layout(ui, &eq2);
-
-                                        Some((var, eqW, eq2))
-                                    }
-                                    Err(_) => None,
-                                    _ => None,
-                                }
+                        let inp = crate::eqwidget::SizedExpression::layout(ui, eq);
+                        let simpW = crate::eqwidget::SizedExpression::layout(ui, simp);
+                        let var_eqs: Vec<_> = rearranged
+                            .iter()
+                            .map(|(var, eq2)| {
+                                let eqW = crate::eqwidget::SizedExpression::layout(ui, eq2);
+                                (var, eqW, eq2)
                             })
-                            .filter(|e| e.is_some())
-                            .map(|e| e.unwrap())
                             .collect();
 
-                        let table = TableBuilder::new(ui)
-                            .cell_layout(egui::Layout::left_to_right(egui::Align::TOP))
-                            .column(Column::exact(120.0))
-                            .column(Column::exact(50.0))
-                            .column(Column::remainder())
-                            .auto_shrink([true, true]);
-
-                        table.body(|mut body| {
-                            body.row(inp.dims().y, |mut row| {
-                                row.col(|ui| {
-                                    ui.strong("Input");
-                                });
-                                row.col(|ui| {});
-                                row.col(|ui| {
-                                    inp.ui(ui);
-                                });
+                        copy_table(ui, |body| {
+                            copy_row(body, inp.dims().y, "Input", None, |ui| {
+                                inp.ui(ui);
                             });
-
-                            body.row(simpW.dims().y, |mut row| {
-                                row.col(|ui| {
-                                    ui.strong("Simplified");
-                                });
-                                row.col(|ui| {
-                                    if ui.small_button("📋").clicked() {
-                                        ctx.output_mut(|o| o.copied_text = format!("{}", simp));
-                                    }
-                                });
-                                row.col(|ui| {
-                                    simpW.ui(ui);
-                                });
+                            let copy = Some(format!("{}", simp));
+                            copy_row(body, simpW.dims().y, "Simplified", copy, |ui| {
+                                simpW.ui(ui);
                             });
-                            if let Expression::Rational(r, true) = &simp {
-                                let dec = Expression::Rational(r.clone(), false);
-                                body.row(text_height, |mut row| {
-                                    row.col(|ui| {
-                                        ui.strong("As decimal");
-                                    });
-                                    row.col(|ui| {
-                                        if ui.small_button("📋").clicked() {
-                                            ctx.output_mut(|o| o.copied_text = format!("{}", dec));
-                                        }
-                                    });
-                                    row.col(|ui| {
-                                        ui.label(format!("{}", dec));
-                                    });
-                                });
+                            if let Expression::Rational(r, true) = simp {
+                                let dec = format!("{}", Expression::Rational(r.clone(), false));
+                                copy_row(
+                                    body,
+                                    text_height,
+                                    "As decimal",
+                                    Some(dec.clone()),
+                                    |ui| {
+                                        ui.label(dec);
+                                    },
+                                );
                             }
 
                             for (var, eqW, eq) in var_eqs {
-                                body.row(eqW.dims().y, |mut row| {
-                                    row.col(|ui| {
-                                        ui.strong("Rearranged: ".to_string() + var);
-                                    });
-                                    row.col(|ui| {
-                                        if ui.small_button("📋").clicked() {
-                                            ctx.output_mut(|o| o.copied_text = format!("{}", eq));
-                                        }
-                                    });
-                                    row.col(|ui| {
-                                        eqW.ui(ui);
-                                    });
+                                let label = "Rearranged: ".to_string() + var.as_str();
+                                let copy = Some(format!("{}", eq));
+                                copy_row(body, eqW.dims().y, &label, copy, |ui| {
+                                    eqW.ui(ui);
                                 });
                             }
                         });
                     });
-
                 true
             }
-            None => false,
         }
     }
+
+    fn text(&mut self, input: &String) -> Vec<(String, String)> {
+        use crate::eq::Expression;
+
+        match self.work(input) {
+            Worked::Nothing => vec![],
+            Worked::System(solved) => solved
+                .iter()
+                .map(|(var, value)| (var.to_string(), concrete_str(value)))
+                .collect(),
+            Worked::Expression {
+                simp, rearranged, ..
+            } => {
+                let mut out = vec![("Simplified".to_string(), format!("{}", simp))];
+                if let Expression::Rational(r, true) = simp {
+                    let dec = Expression::Rational(r.clone(), false);
+                    out.push(("As decimal".into(), format!("{}", dec)));
+                }
+                for (var, eq2) in rearranged {
+                    out.push((
+                        "Rearranged: ".to_string() + var.as_str(),
+                        format!("{}", eq2),
+                    ));
+                }
+                out
+            }
+        }
+    }
+
+    fn result(&mut self, input: &String) -> Option<String> {
+        use crate::eq::Expression;
+
+        // Only keep calculations, not numbers typed in or simplified algebra.
+        let eq = self.parse(input)?;
+        if matches!(eq, Expression::Integer(_) | Expression::Rational(..)) {
+            return None;
+        }
+        let mut simp = eq;
+        simp.simplify();
+        match simp {
+            Expression::Integer(_) | Expression::Rational(..) => Some(format!("{}", simp)),
+            _ => None,
+        }
+    }
+
+    fn takes_answers(&self) -> bool {
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn equation_system() {
+        let mut extra = EquationExtra::default();
+        let rows = |extra: &mut EquationExtra, input: &str| extra.text(&input.to_string());
+
+        assert_eq!(
+            rows(&mut extra, "w = 3\nh = 2w\narea = w * h"),
+            vec![
+                ("area".to_string(), "18".to_string()),
+                ("h".to_string(), "6".to_string()),
+            ]
+        );
+        // Equations can be rearranged, & share a line when separated by `;`.
+        assert_eq!(
+            rows(&mut extra, "c = 3b; 6 = c"),
+            vec![("b".to_string(), "2".to_string())]
+        );
+        assert_eq!(
+            rows(&mut extra, "x = 1/4; y = x + 1"),
+            vec![("y".to_string(), "1.25".to_string())]
+        );
+
+        assert!(rows(&mut extra, "a = b\nb = a").is_empty());
+        // Square roots can't be worked out yet, which mustn't panic.
+        assert_eq!(rows(&mut extra, "a = 4; d = sqrt(a)"), vec![]);
+        // A single expression is simplified as before.
+        assert_eq!(rows(&mut extra, "1 + 2")[0].1, "3");
+        assert!(EquationSystem::parse("a = 1\n2 + 3").is_none());
+    }
+
+    #[test]
+    fn worked_out_once() {
+        let mut extra = EquationExtra::default();
+        let input = "2x = 4".to_string();
+        let rows = extra.text(&input);
+        assert!(rows.contains(&("Rearranged: x".to_string(), "2".to_string())));
+
+        // Drawing the same input again reuses what was worked out.
+        assert_eq!(extra.worked.as_ref().map(|(i, _)| i), Some(&input));
+        assert_eq!(extra.text(&input), rows);
+        assert!(matches!(extra.work(&"x".to_string()), Worked::Nothing));
+        assert_eq!(extra.worked.as_ref().unwrap().0, "x");
+    }
+
+    #[test]
+    fn worked_out_in_background() {
+        let mut extra = EquationExtra::default();
+        let ctx = egui::Context::default();
+        let input = "2x = 4".to_string();
+        let deadline = std::time::Instant::now() + std::time::Duration::from_secs(10);
+        while extra.poll(&input, &ctx).is_none() {
+            assert!(std::time::Instant::now() < deadline, "not worked out");
+            std::thread::sleep(std::time::Duration::from_millis(1));
+        }
+        assert!(matches!(
+            extra.poll(&input, &ctx),
+            Some(Worked::Expression { .. })
+        ));
+
+        // Text for the input being worked out waits for it, rather than starting
+        // over, & the earlier input's answer is dropped.
+        let _ = extra.poll(&"3y = 9".to_string(), &ctx);
+        let input = "a = 2; b = a * 3".to_string();
+        let _ = extra.poll(&input, &ctx);
+        let rows = extra.text(&input);
+        assert!(rows.contains(&("b".to_string(), "6".to_string())));
+        assert!(extra.worker.as_ref().unwrap().sent.is_none());
+    }
+
+    #[test]
+    fn work_out_cancelled() {
+        let cancel = Cancel::default();
+        cancel.cancel();
+        let input = "w = 3\nh = 2w\narea = w * h".to_string();
+        assert!(matches!(
+            EquationExtra::work_out(&input, &cancel),
+            Worked::Nothing
+        ));
+    }
+
+    #[test]
+    fn results() {
+        let mut numbers = NumFormatExtra::default();
+        assert_eq!(numbers.result(&"0xff".to_string()), Some("255".into()));
+        assert_eq!(numbers.result(&"0b101".to_string()), Some("5".into()));
+        assert_eq!(numbers.result(&"255".to_string()), None);
+
+        let mut equation = EquationExtra::default();
+        assert_eq!(equation.result(&"(4) * 3".to_string()), Some("12".into()));
+        assert_eq!(equation.result(&"2*(4)".to_string()), Some("8".into()));
+    }
 }
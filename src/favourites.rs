@@ -0,0 +1,417 @@
+use fuzzy_matcher::skim::SkimMatcherV2;
+use fuzzy_matcher::FuzzyMatcher;
+use std::collections::HashMap;
+
+use crate::provider::{Action, ResultItem};
+use crate::sys_apps::{App, AppList};
+
+/// At most this many frecent apps are shown with an empty query.
+const MAX_FRECENT: usize = 8;
+
+/// Usage is kept for at most this many apps, dropping the least frecent.
+const MAX_USAGE: usize = 200;
+
+/// At most this many calculations are kept.
+const MAX_CALCULATIONS: usize = 100;
+
+/// At most this many calculations are shown with an empty query.
+const MAX_SHOWN_CALCULATIONS: usize = 5;
+
+/// Typing this lists all the calculations kept, searching them by what follows.
+const CALCULATIONS_PREFIX: char = '=';
+
+const DAY: u64 = 24 * 60 * 60;
+
+/// How often & how recently an app was launched.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, serde::Deserialize, serde::Serialize)]
+pub struct Usage {
+    pub count: u32,
+    /// Seconds since the epoch.
+    pub last_used: u64,
+}
+
+impl Usage {
+    /// Weighs the launch count by how recently the app was last used, so apps
+    /// used a lot a long time ago drop below those used a little lately.
+    pub fn frecency(&self, now: u64) -> u64 {
+        let age = now.saturating_sub(self.last_used);
+        let weight = match age {
+            a if a < 4 * DAY => 100,
+            a if a < 14 * DAY => 70,
+            a if a < 31 * DAY => 50,
+            a if a < 90 * DAY => 30,
+            _ => 10,
+        };
+        self.count as u64 * weight
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, serde::Deserialize, serde::Serialize)]
+pub struct Calculation {
+    pub input: String,
+    pub result: String,
+}
+
+/// What the launcher shows with an empty query: pinned apps, the apps launched
+/// most, and the last few calculations. Persisted with the launcher's state.
+#[derive(Debug, Clone, Default, PartialEq, serde::Deserialize, serde::Serialize)]
+#[serde(default)]
+pub struct Favourites {
+    /// Keys of the pinned apps, in the order shown.
+    pub pinned: Vec<String>,
+    /// Launches of each app, by key.
+    pub usage: HashMap<String, Usage>,
+    /// Newest first.
+    pub calculations: Vec<Calculation>,
+}
+
+/// Returns what identifies the app across runs: its desktop file ID, or its
+/// name if it doesn't have one.
+pub fn key(app: &App) -> &str {
+    if app.id.is_empty() {
+        &app.name
+    } else {
+        &app.id
+    }
+}
+
+/// Returns what to search the calculations for, if the input asks to.
+pub fn calculation_query(input: &str) -> Option<&str> {
+    input
+        .trim_start()
+        .strip_prefix(CALCULATIONS_PREFIX)
+        .map(str::trim)
+}
+
+/// Returns the current time in seconds since the epoch.
+pub fn now() -> u64 {
+    // There's no clock on the web without JS bindings, so everything is as old.
+    #[cfg(target_arch = "wasm32")]
+    return 0;
+
+    #[cfg(not(target_arch = "wasm32"))]
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
+
+impl Favourites {
+    pub fn is_pinned(&self, app: &App) -> bool {
+        self.pinned.iter().any(|k| k == key(app))
+    }
+
+    /// Pins the app at the end of the pinned apps, or unpins it if it was pinned.
+    pub fn toggle_pin(&mut self, app: &App) {
+        match self.pinned.iter().position(|k| k == key(app)) {
+            Some(i) => {
+                self.pinned.remove(i);
+            }
+            None => self.pinned.push(key(app).to_string()),
+        }
+    }
+
+    /// Moves a pinned app to where another is, shifting those in between.
+    pub fn move_pin(&mut self, app: &App, to: &App) {
+        let from = self.pinned.iter().position(|k| k == key(app));
+        let to = self.pinned.iter().position(|k| k == key(to));
+        if let (Some(from), Some(to)) = (from, to) {
+            let k = self.pinned.remove(from);
+            self.pinned.insert(to, k);
+        }
+    }
+
+    pub fn record_launch(&mut self, app: &App, now: u64) {
+        let usage = self.usage.entry(key(app).to_string()).or_default();
+        usage.count = usage.count.saturating_add(1);
+        usage.last_used = now;
+
+        if self.usage.len() > MAX_USAGE {
+            let least = self
+                .usage
+                .iter()
+                .min_by_key(|(_, u)| (u.frecency(now), u.last_used))
+                .map(|(k, _)| k.clone());
+            self.usage.remove(&least.unwrap());
+        }
+    }
+
+    /// Keeps the calculation at the top of the recent calculations.
+    pub fn record_calculation(&mut self, input: &str, result: &str) {
+        let input = input.trim();
+        self.calculations.retain(|c| c.input != input);
+        self.calculations.insert(
+            0,
+            Calculation {
+                input: input.to_string(),
+                result: result.to_string(),
+            },
+        );
+        self.calculations.truncate(MAX_CALCULATIONS);
+    }
+
+    /// Returns the indices of the pinned apps, in order, followed by those of
+    /// the most frecent apps. Apps which have gone are skipped.
+    pub fn app_indices(&self, apps_list: &AppList, now: u64) -> Vec<usize> {
+        let index: HashMap<&str, usize> = apps_list
+            .apps
+            .iter()
+            .enumerate()
+            .map(|(i, app)| (key(app), i))
+            .collect();
+
+        let mut idxs: Vec<usize> = self
+            .pinned
+            .iter()
+            .filter_map(|k| index.get(k.as_str()).copied())
+            .collect();
+
+        let mut frecent: Vec<(&String, &Usage)> = self
+            .usage
+            .iter()
+            .filter(|(k, _)| !self.pinned.contains(k))
+            .collect();
+        frecent.sort_by_key(|(k, u)| (std::cmp::Reverse(u.frecency(now)), k.as_str()));
+        idxs.extend(
+            frecent
+                .into_iter()
+                .filter_map(|(k, _)| index.get(k.as_str()).copied())
+                .take(MAX_FRECENT),
+        );
+        idxs
+    }
+
+    /// Returns the last few calculations as results which put them back in the input.
+    pub fn calculation_results(&self) -> Vec<ResultItem> {
+        self.calculations
+            .iter()
+            .take(MAX_SHOWN_CALCULATIONS)
+            .enumerate()
+            .map(|(i, c)| c.result_item(-(i as i64)))
+            .collect()
+    }
+
+    /// Returns the calculations matching the query, best first, or all of them
+    /// newest first if the query is empty.
+    pub fn search_calculations(&self, matcher: &SkimMatcherV2, query: &str) -> Vec<ResultItem> {
+        if query.is_empty() {
+            return self
+                .calculations
+                .iter()
+                .enumerate()
+                .map(|(i, c)| c.result_item(-(i as i64)))
+                .collect();
+        }
+        let mut results: Vec<ResultItem> = self
+            .calculations
+            .iter()
+            .filter_map(|c| {
+                let score = matcher.fuzzy_match(&format!("{} = {}", c.input, c.result), query)?;
+                Some(c.result_item(score))
+            })
+            .collect();
+        // Stable, so newer calculations come first on ties.
+        results.sort_by_key(|r| std::cmp::Reverse(r.score));
+        results
+    }
+
+    /// Replaces `ans` in the input with the last result, and `ans1`…`ansN`
+    /// with the results before it, newest first, so `ans1` is `ans` too. The
+    /// results are bracketed so they keep their value in any expression.
+    pub fn expand_answers(&self, input: &str) -> String {
+        // Names start with a letter, so `2ans` is twice the answer, like `2x`.
+        let is_word = |c: char| c.is_alphanumeric() || c == '_';
+        let mut out = String::with_capacity(input.len());
+        let mut rest = input;
+        while let Some(start) = rest.find(|c: char| c.is_alphabetic() || c == '_') {
+            out.push_str(&rest[..start]);
+            let end = rest[start..]
+                .find(|c: char| !is_word(c))
+                .map_or(rest.len(), |e| start + e);
+            let word = &rest[start..end];
+
+            let answer = word
+                .strip_prefix("ans")
+                .and_then(|n| match n {
+                    "" => Some(0),
+                    n => n.parse::<usize>().ok()?.checked_sub(1),
+                })
+                .and_then(|i| self.calculations.get(i));
+            match answer {
+                // Brackets after a number aren't taken as multiplying it.
+                Some(c) if out.ends_with(|c: char| c.is_ascii_digit()) => {
+                    out.push_str(&format!("*({})", c.result))
+                }
+                Some(c) => out.push_str(&format!("({})", c.result)),
+                None => out.push_str(word),
+            }
+            rest = &rest[end..];
+        }
+        out.push_str(rest);
+        out
+    }
+}
+
+impl Calculation {
+    /// Returns a result which puts the calculation back in the input, or copies
+    /// its result with alt.
+    fn result_item(&self, score: i64) -> ResultItem {
+        ResultItem {
+            title: format!("{} = {}", self.input, self.result),
+            subtitle: None,
+            glyph: "🖩".into(),
+            score,
+            action: Action::SetInput(self.input.clone()),
+            alt_action: Some(Action::Copy(self.result.clone())),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn apps(names: &[&str]) -> AppList {
+        AppList {
+            apps: names
+                .iter()
+                .map(|n| {
+                    let mut app = App::default();
+                    app.name = n.to_string();
+                    app.id = format!("{}.desktop", n.to_lowercase());
+                    app
+                })
+                .collect(),
+        }
+    }
+
+    #[test]
+    fn pins() {
+        let list = apps(&["Firefox", "Files", "Terminal", "Editor"]);
+        let mut f = Favourites::default();
+        for i in [2, 0, 3] {
+            f.toggle_pin(&list.apps[i]);
+        }
+        assert!(f.is_pinned(&list.apps[0]));
+        assert_eq!(f.app_indices(&list, 0), vec![2, 0, 3]);
+
+        f.move_pin(&list.apps[3], &list.apps[2]);
+        assert_eq!(f.app_indices(&list, 0), vec![3, 2, 0]);
+        f.move_pin(&list.apps[3], &list.apps[0]);
+        assert_eq!(f.app_indices(&list, 0), vec![2, 0, 3]);
+        // Only pinned apps can be moved.
+        f.move_pin(&list.apps[1], &list.apps[2]);
+        assert_eq!(f.app_indices(&list, 0), vec![2, 0, 3]);
+
+        f.toggle_pin(&list.apps[2]);
+        assert!(!f.is_pinned(&list.apps[2]));
+        assert_eq!(f.app_indices(&list, 0), vec![0, 3]);
+
+        // Pins of uninstalled apps are kept, in case they come back.
+        assert_eq!(f.app_indices(&apps(&["Editor"]), 0), vec![0]);
+    }
+
+    #[test]
+    fn frecency() {
+        let now = 1000 * DAY;
+        let recent = Usage {
+            count: 3,
+            last_used: now - DAY,
+        };
+        let old = Usage {
+            count: 20,
+            last_used: now - 200 * DAY,
+        };
+        assert!(recent.frecency(now) > old.frecency(now));
+
+        let list = apps(&["Firefox", "Files", "Terminal", "Editor"]);
+        let mut f = Favourites::default();
+        f.toggle_pin(&list.apps[3]);
+        for (i, days_ago) in [(1, 40), (1, 30), (2, 1), (3, 2), (0, 100)] {
+            f.record_launch(&list.apps[i], now - days_ago * DAY);
+        }
+        assert_eq!(f.usage["files.desktop"].count, 2);
+        // Pinned apps aren't repeated among the frecent ones.
+        assert_eq!(f.app_indices(&list, now), vec![3, 1, 2, 0]);
+    }
+
+    #[test]
+    fn usage_is_capped() {
+        let names: Vec<String> = (0..=MAX_USAGE).map(|i| format!("App{}", i)).collect();
+        let list = apps(&names.iter().map(String::as_str).collect::<Vec<_>>());
+        let mut f = Favourites::default();
+        for (i, app) in list.apps.iter().enumerate() {
+            f.record_launch(app, i as u64);
+        }
+        assert_eq!(f.usage.len(), MAX_USAGE);
+        assert!(!f.usage.contains_key("app0.desktop"));
+    }
+
+    #[test]
+    fn calculations() {
+        let mut f = Favourites::default();
+        for (input, result) in [("1+1", "2"), ("2*3", "6"), (" 1+1 ", "2")] {
+            f.record_calculation(input, result);
+        }
+        let results = f.calculation_results();
+        assert_eq!(results[0].title, "1+1 = 2");
+        assert_eq!(results[0].action, Action::SetInput("1+1".into()));
+        assert_eq!(results[1].alt_action, Some(Action::Copy("6".into())));
+
+        for i in 0..MAX_CALCULATIONS + 10 {
+            f.record_calculation(&format!("{}+0", i), &i.to_string());
+        }
+        assert_eq!(f.calculations.len(), MAX_CALCULATIONS);
+        assert_eq!(f.calculations[0].input, "109+0");
+        // Only the last few are shown with an empty query.
+        assert_eq!(f.calculation_results().len(), MAX_SHOWN_CALCULATIONS);
+    }
+
+    #[test]
+    fn search() {
+        let mut f = Favourites::default();
+        for (input, result) in [("sqrt 2 * 2", "2.83"), ("0xff", "255"), ("2 * 2", "4")] {
+            f.record_calculation(input, result);
+        }
+        let matcher = SkimMatcherV2::default();
+
+        let titles = |results: Vec<ResultItem>| -> Vec<String> {
+            results.into_iter().map(|r| r.title).collect()
+        };
+        assert_eq!(
+            titles(f.search_calculations(&matcher, "")),
+            vec!["2 * 2 = 4", "0xff = 255", "sqrt 2 * 2 = 2.83"]
+        );
+        assert_eq!(
+            titles(f.search_calculations(&matcher, "255")),
+            vec!["0xff = 255"]
+        );
+        assert_eq!(
+            f.search_calculations(&matcher, "sqrt")[0].title,
+            "sqrt 2 * 2 = 2.83"
+        );
+
+        assert_eq!(calculation_query("= sqrt "), Some("sqrt"));
+        assert_eq!(calculation_query("="), Some(""));
+        assert_eq!(calculation_query("2 = x"), None);
+    }
+
+    #[test]
+    fn answers() {
+        let mut f = Favourites::default();
+        assert_eq!(f.expand_answers("ans + 1"), "ans + 1");
+
+        for (input, result) in [("1/2 + 0", "1/2"), ("2+2", "4"), ("5 - 8", "-3")] {
+            f.record_calculation(input, result);
+        }
+        assert_eq!(f.expand_answers("ans * 2"), "(-3) * 2");
+        assert_eq!(f.expand_answers("ans1+ans2*ans3"), "(-3)+(4)*(1/2)");
+        assert_eq!(f.expand_answers("2ans"), "2*(-3)");
+        // Other names, & answers which weren't kept, are left alone.
+        assert_eq!(
+            f.expand_answers("answer + ans4 + ans0"),
+            "answer + ans4 + ans0"
+        );
+        assert_eq!(f.expand_answers("x = ans"), "x = (-3)");
+    }
+}
@@ -0,0 +1,455 @@
+use fuzzy_matcher::skim::SkimMatcherV2;
+use fuzzy_matcher::FuzzyMatcher;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, SystemTime};
+
+use crate::provider::{Action, Cancel, Provider, ResultItem};
+
+/// How often the index is checked for changed directories.
+const REFRESH_INTERVAL: Duration = Duration::from_secs(30);
+
+/// Upper bound on the number of paths searched, so huge trees don't stall typing.
+const MAX_INDEXED: usize = 200_000;
+
+/// At most this many files are listed.
+const MAX_RESULTS: usize = 20;
+
+/// Queries shorter than this don't search files, as they'd match almost everything.
+const MIN_QUERY_LEN: usize = 2;
+
+/// Added to the score for each term of the query which starts a path segment.
+const SEGMENT_BONUS: i64 = 20;
+
+/// Which files are indexed.
+#[derive(Debug, Clone)]
+pub struct FileIndexConfig {
+    pub roots: Vec<PathBuf>,
+    /// `.gitignore`-style patterns of paths to leave out.
+    pub excludes: Vec<String>,
+    /// How many directories deep below each root to index.
+    pub max_depth: usize,
+}
+
+impl Default for FileIndexConfig {
+    fn default() -> Self {
+        Self {
+            roots: std::env::var_os("HOME")
+                .map(|h| vec![PathBuf::from(h)])
+                .unwrap_or_default(),
+            excludes: vec![
+                "node_modules/".into(),
+                "target/".into(),
+                "__pycache__/".into(),
+            ],
+            max_depth: 6,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct IndexedPath {
+    path: PathBuf,
+    is_dir: bool,
+}
+
+/// A directory whose contents have been indexed.
+#[derive(Debug)]
+struct DirState {
+    mtime: Option<SystemTime>,
+    depth: usize,
+    children: Vec<IndexedPath>,
+}
+
+/// Walks the configured roots, remembering the modification time of each directory
+/// so later refreshes only need to re-read directories which changed.
+#[derive(Debug)]
+struct Indexer {
+    config: FileIndexConfig,
+    dirs: HashMap<PathBuf, DirState>,
+}
+
+impl Indexer {
+    fn new(config: FileIndexConfig) -> Self {
+        Self {
+            config,
+            dirs: HashMap::with_capacity(1024),
+        }
+    }
+
+    fn walker(&self, dir: &Path, max_depth: usize) -> ignore::Walk {
+        let mut overrides = ignore::overrides::OverrideBuilder::new(dir);
+        for pattern in self.config.excludes.iter() {
+            if let Err(e) = overrides.add(&format!("!{}", pattern)) {
+                eprintln!("bad exclude pattern {:?}: {:?}", pattern, e);
+            }
+        }
+
+        let mut builder = ignore::WalkBuilder::new(dir);
+        builder
+            .max_depth(Some(max_depth))
+            .follow_links(false)
+            .require_git(false);
+        match overrides.build() {
+            Ok(o) => {
+                builder.overrides(o);
+            }
+            Err(e) => eprintln!("failed to build exclude rules: {:?}", e),
+        }
+        builder.build()
+    }
+
+    /// Indexes everything below dir, which is depth levels below its root.
+    fn scan(&mut self, dir: &Path, depth: usize) {
+        if depth > self.config.max_depth {
+            return;
+        }
+
+        for entry in self.walker(dir, self.config.max_depth - depth).flatten() {
+            let abs_depth = depth + entry.depth();
+            let is_dir = entry.file_type().map(|t| t.is_dir()).unwrap_or(false);
+
+            if is_dir && abs_depth < self.config.max_depth {
+                self.dirs.insert(
+                    entry.path().to_path_buf(),
+                    DirState {
+                        mtime: entry.metadata().ok().and_then(|m| m.modified().ok()),
+                        depth: abs_depth,
+                        children: vec![],
+                    },
+                );
+            }
+            if entry.depth() > 0 {
+                self.add_child(entry.path(), is_dir);
+            }
+        }
+    }
+
+    /// Re-reads the direct contents of a directory which changed, fully indexing
+    /// any new directories within it.
+    fn rescan(&mut self, dir: &Path, depth: usize) {
+        let mut new_dirs = vec![];
+        for entry in self.walker(dir, 1).flatten() {
+            let is_dir = entry.file_type().map(|t| t.is_dir()).unwrap_or(false);
+
+            if entry.depth() == 0 {
+                self.dirs.insert(
+                    dir.to_path_buf(),
+                    DirState {
+                        mtime: entry.metadata().ok().and_then(|m| m.modified().ok()),
+                        depth,
+                        children: vec![],
+                    },
+                );
+                continue;
+            }
+
+            self.add_child(entry.path(), is_dir);
+            if is_dir && !self.dirs.contains_key(entry.path()) {
+                new_dirs.push(entry.path().to_path_buf());
+            }
+        }
+
+        for new_dir in new_dirs {
+            self.scan(&new_dir, depth + 1);
+        }
+    }
+
+    fn add_child(&mut self, path: &Path, is_dir: bool) {
+        if let Some(parent) = path.parent().and_then(|p| self.dirs.get_mut(p)) {
+            parent.children.push(IndexedPath {
+                path: path.to_path_buf(),
+                is_dir,
+            });
+        }
+    }
+
+    fn index_all(&mut self) {
+        for root in self.config.roots.clone() {
+            self.scan(&root, 0);
+        }
+    }
+
+    /// Re-indexes any directories which changed since they were last read,
+    /// returning true if anything did.
+    fn refresh(&mut self) -> bool {
+        let mut changed = vec![];
+        let mut removed = vec![];
+        for (dir, st) in self.dirs.iter() {
+            match std::fs::metadata(dir).and_then(|m| m.modified()) {
+                Ok(mtime) if Some(mtime) == st.mtime => {}
+                Ok(_) => changed.push((dir.clone(), st.depth)),
+                Err(_) => removed.push(dir.clone()),
+            }
+        }
+
+        for dir in removed.iter() {
+            self.dirs.retain(|p, _| !p.starts_with(dir));
+        }
+        for (dir, depth) in changed.iter() {
+            if self.dirs.contains_key(dir) {
+                self.rescan(dir, *depth);
+            }
+        }
+
+        !changed.is_empty() || !removed.is_empty()
+    }
+
+    fn paths(&self) -> Vec<IndexedPath> {
+        self.dirs
+            .values()
+            .flat_map(|d| d.children.iter().cloned())
+            .take(MAX_INDEXED)
+            .collect()
+    }
+}
+
+/// Fuzzy-finds files and directories, from an index kept up to date in the background.
+pub struct FileProvider {
+    home: Option<PathBuf>,
+    paths: Arc<Mutex<Arc<Vec<IndexedPath>>>>,
+    indexed: Arc<AtomicBool>,
+}
+
+impl FileProvider {
+    pub fn new(config: FileIndexConfig) -> Self {
+        let paths = Arc::new(Mutex::new(Arc::new(vec![])));
+        let paths2 = paths.clone();
+        let indexed = Arc::new(AtomicBool::new(false));
+        let indexed2 = indexed.clone();
+
+        std::thread::spawn(move || {
+            let mut indexer = Indexer::new(config);
+            indexer.index_all();
+            *paths2.lock().unwrap() = Arc::new(indexer.paths());
+            indexed2.store(true, Ordering::SeqCst);
+
+            loop {
+                std::thread::sleep(REFRESH_INTERVAL);
+                if indexer.refresh() {
+                    *paths2.lock().unwrap() = Arc::new(indexer.paths());
+                }
+            }
+        });
+
+        Self {
+            home: std::env::var_os("HOME").map(PathBuf::from),
+            paths,
+            indexed,
+        }
+    }
+
+    /// Returns the path as shown to the user, relative to $HOME where possible.
+    fn display_path(&self, path: &Path) -> String {
+        match self.home.as_ref().and_then(|h| path.strip_prefix(h).ok()) {
+            Some(rel) => format!("~/{}", rel.display()),
+            None => path.display().to_string(),
+        }
+    }
+}
+
+/// Returns whether the query's characters, lowercased & without spaces, are in
+/// the path in order, ignoring ASCII case. Any path the query matches has them,
+/// so checking this first cheaply rules out most paths.
+fn may_match(path: &str, query: &[u8]) -> bool {
+    let mut query = query.iter().peekable();
+    for b in path.bytes() {
+        match query.peek() {
+            Some(q) if **q == b.to_ascii_lowercase() => {
+                query.next();
+            }
+            Some(_) => {}
+            None => return true,
+        }
+    }
+    query.peek().is_none()
+}
+
+/// Scores a path against the query, favouring matches on the file name and on
+/// the start of path segments.
+fn score(matcher: &SkimMatcherV2, path: &str, query: &str) -> Option<i64> {
+    // Words in the query probably refer to different segments.
+    let joined_query = query.split_whitespace().collect::<Vec<_>>().join("/");
+    let mut score = matcher.fuzzy_match(path, &joined_query)?;
+
+    let file_name = path.rsplit('/').next().unwrap_or(path);
+    if let Some(s) = matcher.fuzzy_match(file_name, &joined_query) {
+        score += s;
+    }
+
+    let path_lower = path.to_lowercase();
+    for term in joined_query.split('/').filter(|t| !t.is_empty()) {
+        let term = term.to_lowercase();
+        if path_lower.split('/').any(|seg| seg.starts_with(&term)) {
+            score += SEGMENT_BONUS;
+        }
+    }
+
+    Some(score)
+}
+
+impl Provider for FileProvider {
+    fn name(&self) -> &'static str {
+        "files"
+    }
+
+    fn wait_ready(&self) {
+        while !self.indexed.load(Ordering::SeqCst) {
+            std::thread::sleep(Duration::from_millis(10));
+        }
+    }
+
+    fn query(&mut self, matcher: &SkimMatcherV2, input: &str, cancel: &Cancel) -> Vec<ResultItem> {
+        let query = input.trim();
+        if query.len() < MIN_QUERY_LEN {
+            return vec![];
+        }
+
+        // Checked against the full path, which has $HOME where it's shown as ~.
+        // Letters outside ASCII may match in another case, so aren't checked.
+        let prefilter: Option<Vec<u8>> = query.is_ascii().then(|| {
+            query
+                .bytes()
+                .filter(|b| !b.is_ascii_whitespace() && *b != b'~')
+                .map(|b| b.to_ascii_lowercase())
+                .collect()
+        });
+
+        let paths = self.paths.lock().unwrap().clone();
+        let mut scored: Vec<(i64, &IndexedPath, String)> = paths
+            .iter()
+            .take_while(|_| !cancel.is_cancelled())
+            .filter(|p| match &prefilter {
+                Some(prefilter) => may_match(&p.path.to_string_lossy(), prefilter),
+                None => true,
+            })
+            .filter_map(|p| {
+                let display = self.display_path(&p.path);
+                score(matcher, &display, query).map(|s| (s, p, display))
+            })
+            .collect();
+        scored.sort_by_key(|s| std::cmp::Reverse(s.0));
+        scored.truncate(MAX_RESULTS);
+
+        scored
+            .into_iter()
+            .map(|(score, p, display)| ResultItem {
+                title: p
+                    .path
+                    .file_name()
+                    .map(|n| n.to_string_lossy().to_string())
+                    .unwrap_or(display.clone()),
+                subtitle: Some(display),
+                glyph: if p.is_dir { "📁" } else { "📄" }.into(),
+                score,
+                action: Action::Open(p.path.to_string_lossy().to_string()),
+                alt_action: Some(Action::Reveal(p.path.clone())),
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn test_dir(name: &str) -> PathBuf {
+        let dir =
+            std::env::temp_dir().join(format!("liquid-launcher-{}-{}", name, std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    fn indexed(indexer: &Indexer, root: &Path) -> Vec<String> {
+        let mut out: Vec<String> = indexer
+            .paths()
+            .into_iter()
+            .map(|p| {
+                p.path
+                    .strip_prefix(root)
+                    .unwrap()
+                    .to_string_lossy()
+                    .to_string()
+            })
+            .collect();
+        out.sort();
+        out
+    }
+
+    #[test]
+    fn segment_bonus() {
+        let matcher = SkimMatcherV2::default();
+        assert!(
+            score(&matcher, "~/docs/report.pdf", "rep").unwrap()
+                > score(&matcher, "~/docs/xrxexp.pdf", "rep").unwrap()
+        );
+        assert!(
+            score(&matcher, "~/src/proj/README.md", "proj readme").unwrap()
+                > score(&matcher, "~/src/oldproj/notreadme.md", "proj readme").unwrap()
+        );
+        assert_eq!(score(&matcher, "~/docs/report.pdf", "zzz"), None);
+    }
+
+    #[test]
+    fn prefilter() {
+        assert!(may_match("/home/me/Docs/Report.pdf", b"docsrep"));
+        assert!(may_match("/home/me/docs/report.pdf", b""));
+        assert!(!may_match("/home/me/docs/report.pdf", b"repdocs"));
+        assert!(!may_match("/home/me/docs/report.pdf", b"reportx"));
+    }
+
+    #[test]
+    fn index_excludes_and_depth() {
+        let root = test_dir("index");
+        std::fs::create_dir_all(root.join("a/b/c")).unwrap();
+        std::fs::create_dir_all(root.join("node_modules/x")).unwrap();
+        std::fs::write(root.join("a/one.txt"), "").unwrap();
+        std::fs::write(root.join("a/b/c/deep.txt"), "").unwrap();
+        std::fs::write(root.join("a/skipped.log"), "").unwrap();
+        std::fs::write(root.join(".gitignore"), "*.log\n").unwrap();
+
+        let mut indexer = Indexer::new(FileIndexConfig {
+            roots: vec![root.clone()],
+            excludes: vec!["node_modules/".into()],
+            max_depth: 3,
+        });
+        indexer.index_all();
+
+        assert_eq!(
+            indexed(&indexer, &root),
+            vec!["a", "a/b", "a/b/c", "a/one.txt"],
+        );
+        std::fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn refresh() {
+        let root = test_dir("refresh");
+        std::fs::create_dir_all(root.join("a")).unwrap();
+        std::fs::write(root.join("a/one.txt"), "").unwrap();
+
+        let mut indexer = Indexer::new(FileIndexConfig {
+            roots: vec![root.clone()],
+            excludes: vec![],
+            max_depth: 4,
+        });
+        indexer.index_all();
+        assert!(!indexer.refresh());
+
+        // Directory mtimes may have a coarse resolution.
+        std::thread::sleep(Duration::from_millis(20));
+        std::fs::remove_file(root.join("a/one.txt")).unwrap();
+        std::fs::create_dir_all(root.join("a/new")).unwrap();
+        std::fs::write(root.join("a/new/two.txt"), "").unwrap();
+
+        assert!(indexer.refresh());
+        assert_eq!(
+            indexed(&indexer, &root),
+            vec!["a", "a/new", "a/new/two.txt"],
+        );
+        std::fs::remove_dir_all(&root).unwrap();
+    }
+}
@@ -0,0 +1,125 @@
+use std::io::Write;
+
+use crate::cli::{Args, OutputFormat};
+
+/// A row of output from an extra, such as a calculator result.
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize)]
+pub struct ExtraRow {
+    pub label: String,
+    pub value: String,
+}
+
+/// An entry in the results list.
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize)]
+pub struct QueryResult {
+    /// Name of the provider the result came from, `apps` for applications.
+    pub kind: String,
+    pub title: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub subtitle: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub score: Option<i64>,
+}
+
+/// Everything the launcher would show for a query, in display order.
+#[derive(Debug, Clone, Default, PartialEq, Eq, serde::Serialize)]
+pub struct QueryOutput {
+    pub query: String,
+    pub extras: Vec<ExtraRow>,
+    pub results: Vec<QueryResult>,
+}
+
+impl QueryOutput {
+    pub fn write_text(&self, w: &mut impl Write) -> std::io::Result<()> {
+        for row in self.extras.iter() {
+            writeln!(w, "{}: {}", row.label, row.value)?;
+        }
+        if !self.extras.is_empty() && !self.results.is_empty() {
+            writeln!(w)?;
+        }
+        for r in self.results.iter() {
+            match &r.subtitle {
+                Some(subtitle) => writeln!(w, "{}\t{}", r.title, subtitle)?,
+                None => writeln!(w, "{}", r.title)?,
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Runs the query from the arguments without opening a window, printing the
+/// results to stdout. Returns the exit code.
+pub fn run(args: &Args) -> i32 {
+    let mut launcher = crate::Launcher::new_headless(args);
+    let output = launcher.query(args.query.as_deref().unwrap_or(""));
+
+    let mut stdout = std::io::stdout().lock();
+    let res = match args.format {
+        OutputFormat::Text => output.write_text(&mut stdout),
+        OutputFormat::Json => serde_json::to_writer_pretty(&mut stdout, &output)
+            .map_err(std::io::Error::from)
+            .and_then(|_| writeln!(stdout)),
+    };
+
+    match res {
+        Ok(_) => 0,
+        Err(e) => {
+            eprintln!("failed to write results: {}", e);
+            1
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn output() -> QueryOutput {
+        QueryOutput {
+            query: "0x10".into(),
+            extras: vec![ExtraRow {
+                label: "Decimal".into(),
+                value: "16".into(),
+            }],
+            results: vec![
+                QueryResult {
+                    kind: "apps".into(),
+                    title: "Firefox".into(),
+                    subtitle: None,
+                    score: None,
+                },
+                QueryResult {
+                    kind: "files".into(),
+                    title: "a.txt".into(),
+                    subtitle: Some("~/a.txt".into()),
+                    score: Some(42),
+                },
+            ],
+        }
+    }
+
+    #[test]
+    fn text() {
+        let mut out = vec![];
+        output().write_text(&mut out).unwrap();
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "Decimal: 16\n\nFirefox\na.txt\t~/a.txt\n"
+        );
+    }
+
+    #[test]
+    fn json() {
+        assert_eq!(
+            serde_json::to_value(output()).unwrap(),
+            serde_json::json!({
+                "query": "0x10",
+                "extras": [{"label": "Decimal", "value": "16"}],
+                "results": [
+                    {"kind": "apps", "title": "Firefox"},
+                    {"kind": "files", "title": "a.txt", "subtitle": "~/a.txt", "score": 42},
+                ],
+            })
+        );
+    }
+}
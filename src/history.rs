@@ -0,0 +1,183 @@
+use crate::provider::{Action, ResultItem};
+
+/// At most this many queries are kept, dropping the oldest.
+const MAX_ENTRIES: usize = 500;
+
+/// Submitted queries, recalled like a shell's history: Up & Down step through
+/// them, and Ctrl+R searches them. Persisted with the launcher's state.
+#[derive(Debug, Clone, Default, PartialEq, serde::Deserialize, serde::Serialize)]
+#[serde(default)]
+pub struct History {
+    /// Oldest first, without duplicates.
+    pub entries: Vec<String>,
+
+    /// Index of the entry recalled by Up & Down.
+    #[serde(skip)]
+    position: Option<usize>,
+    /// What was typed before stepping into the history or searching it,
+    /// restored when stepping back out.
+    #[serde(skip)]
+    draft: String,
+    /// Whether the input is searching the history rather than querying.
+    #[serde(skip)]
+    searching: bool,
+}
+
+impl History {
+    /// Adds the query as the newest entry, moving it there if it was already
+    /// in the history.
+    pub fn push(&mut self, query: &str) {
+        self.position = None;
+        let query = query.trim();
+        if query.is_empty() {
+            return;
+        }
+        self.entries.retain(|e| e != query);
+        self.entries.push(query.to_string());
+        if self.entries.len() > MAX_ENTRIES {
+            self.entries.drain(..self.entries.len() - MAX_ENTRIES);
+        }
+    }
+
+    /// Whether Up & Down are stepping through the history.
+    pub fn is_browsing(&self) -> bool {
+        self.position.is_some()
+    }
+
+    /// Stops stepping through the history, keeping whatever is in the input.
+    pub fn stop_browsing(&mut self) {
+        self.position = None;
+    }
+
+    /// Returns the entry before the one last recalled, remembering the current
+    /// input if this is the first step back.
+    pub fn older(&mut self, current: &str) -> Option<&str> {
+        let position = match self.position {
+            None => {
+                self.draft = current.to_string();
+                self.entries.len()
+            }
+            Some(p) => p,
+        };
+        let position = position.checked_sub(1)?;
+        self.position = Some(position);
+        Some(&self.entries[position])
+    }
+
+    /// Returns the entry after the one last recalled, or what was typed before
+    /// stepping back once past the newest.
+    pub fn newer(&mut self) -> Option<String> {
+        let position = self.position? + 1;
+        if position < self.entries.len() {
+            self.position = Some(position);
+            Some(self.entries[position].clone())
+        } else {
+            self.position = None;
+            Some(std::mem::take(&mut self.draft))
+        }
+    }
+
+    pub fn is_searching(&self) -> bool {
+        self.searching
+    }
+
+    /// Starts searching, remembering the current input to restore if cancelled.
+    pub fn start_search(&mut self, current: &str) {
+        if !self.searching {
+            self.searching = true;
+            self.position = None;
+            self.draft = current.to_string();
+        }
+    }
+
+    /// Stops searching, returning what was typed before it started.
+    pub fn stop_search(&mut self) -> String {
+        self.searching = false;
+        std::mem::take(&mut self.draft)
+    }
+
+    /// Returns the entries containing the pattern, newest first, as results
+    /// which put them back in the input.
+    pub fn search(&self, pattern: &str) -> Vec<ResultItem> {
+        let pattern = pattern.trim().to_lowercase();
+        self.entries
+            .iter()
+            .rev()
+            .filter(|e| e.to_lowercase().contains(&pattern))
+            .enumerate()
+            .map(|(i, e)| ResultItem {
+                title: e.clone(),
+                subtitle: None,
+                glyph: "⟲".into(),
+                score: -(i as i64),
+                action: Action::SetInput(e.clone()),
+                alt_action: None,
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn history(entries: &[&str]) -> History {
+        let mut h = History::default();
+        for e in entries {
+            h.push(e);
+        }
+        h
+    }
+
+    #[test]
+    fn push() {
+        let mut h = history(&["firefox", "2+2", " ", "files", "firefox\n"]);
+        assert_eq!(h.entries, vec!["2+2", "files", "firefox"]);
+
+        for i in 0..MAX_ENTRIES {
+            h.push(&i.to_string());
+        }
+        assert_eq!(h.entries.len(), MAX_ENTRIES);
+        assert_eq!(h.entries[0], "0");
+    }
+
+    #[test]
+    fn recall() {
+        let mut h = history(&["firefox", "2+2", "files"]);
+        assert_eq!(h.newer(), None);
+
+        assert_eq!(h.older("fi"), Some("files"));
+        assert!(h.is_browsing());
+        assert_eq!(h.older("files"), Some("2+2"));
+        assert_eq!(h.older("2+2"), Some("firefox"));
+        // The oldest entry stays put.
+        assert_eq!(h.older("firefox"), None);
+        assert_eq!(h.newer().as_deref(), Some("2+2"));
+        assert_eq!(h.newer().as_deref(), Some("files"));
+        // Stepping past the newest brings back what was being typed.
+        assert_eq!(h.newer().as_deref(), Some("fi"));
+        assert!(!h.is_browsing());
+
+        assert_eq!(h.older("term"), Some("files"));
+        h.push("term");
+        assert!(!h.is_browsing());
+        assert_eq!(h.older(""), Some("term"));
+    }
+
+    #[test]
+    fn search() {
+        let mut h = history(&["firefox", "2+2", "Files", "fire"]);
+        let titles = |results: Vec<ResultItem>| -> Vec<String> {
+            results.into_iter().map(|r| r.title).collect()
+        };
+        assert_eq!(titles(h.search("fi")), vec!["fire", "Files", "firefox"]);
+        assert_eq!(titles(h.search("FIRE")), vec!["fire", "firefox"]);
+        assert_eq!(h.search("2")[0].action, Action::SetInput("2+2".into()));
+
+        h.start_search("draft");
+        assert!(h.is_searching());
+        h.start_search("fi");
+        assert_eq!(h.stop_search(), "draft");
+        assert!(!h.is_searching());
+    }
+}
@@ -0,0 +1,742 @@
+use std::collections::BTreeMap;
+use std::fmt;
+
+use crate::config::EscapeAction;
+
+/// Something a key can be bound to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, serde::Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum KeyAction {
+    SelectNext,
+    SelectPrevious,
+    SelectPageDown,
+    SelectPageUp,
+    SelectFirst,
+    SelectLast,
+    /// Launch the selected result, or submit the input to an extra.
+    Launch,
+    /// Use the selected result's alternate action, like revealing a file.
+    LaunchAlt,
+    /// Copy the selected result's text.
+    CopyResult,
+    /// Delete the word before the text cursor.
+    DeleteWord,
+    /// Clear the input.
+    Clear,
+    /// Quit the launcher, or hide it when running as a daemon.
+    Hide,
+    TogglePin,
+    SearchHistory,
+    /// Switch to insert mode, where typing goes into the input.
+    InsertMode,
+    /// Switch to normal mode, where keys act on the results without a modifier.
+    NormalMode,
+}
+
+impl KeyAction {
+    fn name(self) -> &'static str {
+        match self {
+            KeyAction::SelectNext => "select-next",
+            KeyAction::SelectPrevious => "select-previous",
+            KeyAction::SelectPageDown => "select-page-down",
+            KeyAction::SelectPageUp => "select-page-up",
+            KeyAction::SelectFirst => "select-first",
+            KeyAction::SelectLast => "select-last",
+            KeyAction::Launch => "launch",
+            KeyAction::LaunchAlt => "launch-alt",
+            KeyAction::CopyResult => "copy-result",
+            KeyAction::DeleteWord => "delete-word",
+            KeyAction::Clear => "clear",
+            KeyAction::Hide => "hide",
+            KeyAction::TogglePin => "toggle-pin",
+            KeyAction::SearchHistory => "search-history",
+            KeyAction::InsertMode => "insert-mode",
+            KeyAction::NormalMode => "normal-mode",
+        }
+    }
+}
+
+/// Bindings to start from, which the config file can then change.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, serde::Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum KeyPreset {
+    /// Arrows, Page Up & Down, Home & End, plus Ctrl+N & Ctrl+P. Ctrl+D pins.
+    #[default]
+    Default,
+    /// The defaults plus Ctrl+W to delete a word, Ctrl+U to clear & Ctrl+G to hide.
+    Emacs,
+    /// Insert mode as the defaults, except escape enters normal mode, where
+    /// j & k select, g & G jump, y copies & i goes back to insert mode.
+    Vim,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, serde::Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct KeysConfig {
+    pub preset: KeyPreset,
+    /// Keys for actions, like `select-next = ["ctrl+j"]`, replacing the keys
+    /// the preset binds the action to. An empty list unbinds the action.
+    pub bindings: BTreeMap<KeyAction, Vec<String>>,
+    /// As `bindings`, for normal mode.
+    pub normal: BTreeMap<KeyAction, Vec<String>>,
+}
+
+/// Names of the keys which aren't letters, digits or function keys.
+const NAMED_KEYS: [egui::Key; 17] = [
+    egui::Key::ArrowDown,
+    egui::Key::ArrowLeft,
+    egui::Key::ArrowRight,
+    egui::Key::ArrowUp,
+    egui::Key::Escape,
+    egui::Key::Tab,
+    egui::Key::Backspace,
+    egui::Key::Enter,
+    egui::Key::Space,
+    egui::Key::Insert,
+    egui::Key::Delete,
+    egui::Key::Home,
+    egui::Key::End,
+    egui::Key::PageUp,
+    egui::Key::PageDown,
+    egui::Key::Minus,
+    egui::Key::PlusEquals,
+];
+
+const LETTER_KEYS: [egui::Key; 26] = [
+    egui::Key::A,
+    egui::Key::B,
+    egui::Key::C,
+    egui::Key::D,
+    egui::Key::E,
+    egui::Key::F,
+    egui::Key::G,
+    egui::Key::H,
+    egui::Key::I,
+    egui::Key::J,
+    egui::Key::K,
+    egui::Key::L,
+    egui::Key::M,
+    egui::Key::N,
+    egui::Key::O,
+    egui::Key::P,
+    egui::Key::Q,
+    egui::Key::R,
+    egui::Key::S,
+    egui::Key::T,
+    egui::Key::U,
+    egui::Key::V,
+    egui::Key::W,
+    egui::Key::X,
+    egui::Key::Y,
+    egui::Key::Z,
+];
+
+const OTHER_KEYS: [egui::Key; 30] = [
+    egui::Key::Num0,
+    egui::Key::Num1,
+    egui::Key::Num2,
+    egui::Key::Num3,
+    egui::Key::Num4,
+    egui::Key::Num5,
+    egui::Key::Num6,
+    egui::Key::Num7,
+    egui::Key::Num8,
+    egui::Key::Num9,
+    egui::Key::F1,
+    egui::Key::F2,
+    egui::Key::F3,
+    egui::Key::F4,
+    egui::Key::F5,
+    egui::Key::F6,
+    egui::Key::F7,
+    egui::Key::F8,
+    egui::Key::F9,
+    egui::Key::F10,
+    egui::Key::F11,
+    egui::Key::F12,
+    egui::Key::F13,
+    egui::Key::F14,
+    egui::Key::F15,
+    egui::Key::F16,
+    egui::Key::F17,
+    egui::Key::F18,
+    egui::Key::F19,
+    egui::Key::F20,
+];
+
+/// Keys left to the input in normal mode, as they move the cursor rather than
+/// edit the text.
+const CURSOR_KEYS: [egui::Key; 6] = [
+    egui::Key::ArrowDown,
+    egui::Key::ArrowLeft,
+    egui::Key::ArrowRight,
+    egui::Key::ArrowUp,
+    egui::Key::Home,
+    egui::Key::End,
+];
+
+/// A key pressed with modifiers, written like `ctrl+shift+c`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Chord {
+    pub modifiers: egui::Modifiers,
+    pub key: egui::Key,
+}
+
+impl Chord {
+    pub fn parse(s: &str) -> Result<Self, String> {
+        let mut modifiers = egui::Modifiers::NONE;
+        let mut parts: Vec<&str> = s.split('+').map(str::trim).collect();
+        // `ctrl++` binds the plus key.
+        if s.ends_with("++") {
+            parts.truncate(parts.len() - 2);
+            parts.push("plus");
+        }
+        let (name, mods) = parts.split_last().ok_or("empty key")?;
+        for m in mods {
+            match m.to_lowercase().as_str() {
+                "ctrl" | "control" => modifiers.ctrl = true,
+                "shift" => modifiers.shift = true,
+                "alt" => modifiers.alt = true,
+                _ => return Err(format!("unknown modifier {:?} in {:?}", m, s)),
+            }
+        }
+
+        let name = match name.to_lowercase().as_str() {
+            "esc" => "escape".to_string(),
+            "return" => "enter".to_string(),
+            "pgup" => "pageup".to_string(),
+            "pgdn" => "pagedown".to_string(),
+            n => n.to_string(),
+        };
+        let key = NAMED_KEYS
+            .iter()
+            .chain(LETTER_KEYS.iter())
+            .chain(OTHER_KEYS.iter())
+            .find(|k| k.name().eq_ignore_ascii_case(&name))
+            .ok_or_else(|| format!("unknown key {:?} in {:?}", name, s))?;
+        Ok(Chord {
+            modifiers,
+            key: *key,
+        })
+    }
+
+    fn matches(&self, key: egui::Key, modifiers: egui::Modifiers) -> bool {
+        key == self.key
+            && modifiers.ctrl == self.modifiers.ctrl
+            && modifiers.shift == self.modifiers.shift
+            && modifiers.alt == self.modifiers.alt
+    }
+}
+
+impl fmt::Display for Chord {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (held, name) in [
+            (self.modifiers.ctrl, "ctrl+"),
+            (self.modifiers.shift, "shift+"),
+            (self.modifiers.alt, "alt+"),
+        ] {
+            if held {
+                write!(f, "{}", name)?;
+            }
+        }
+        write!(f, "{}", self.key.name().to_lowercase())
+    }
+}
+
+/// Actions with the keys a preset binds them to.
+type PresetBindings = Vec<(KeyAction, &'static [&'static str])>;
+
+/// Returns the keys of each action in insert & normal mode for the preset.
+fn preset(preset: KeyPreset, on_escape: EscapeAction) -> (PresetBindings, PresetBindings) {
+    use KeyAction::*;
+
+    let escape = match (preset, on_escape) {
+        (KeyPreset::Vim, _) => NormalMode,
+        (_, EscapeAction::Exit) => Hide,
+        (_, EscapeAction::Clear) => Clear,
+    };
+    let mut insert: PresetBindings = vec![
+        (SelectNext, &["down", "ctrl+n"]),
+        (SelectPrevious, &["up", "ctrl+p"]),
+        (SelectPageDown, &["pagedown"]),
+        (SelectPageUp, &["pageup"]),
+        (SelectFirst, &["home"]),
+        (SelectLast, &["end"]),
+        (Launch, &["enter"]),
+        (LaunchAlt, &["alt+enter"]),
+        (CopyResult, &["ctrl+shift+c"]),
+        (DeleteWord, &["ctrl+backspace"]),
+        // Ctrl+P used to pin, before it selected the previous result.
+        (TogglePin, &["ctrl+d"]),
+        (SearchHistory, &["ctrl+r"]),
+        (escape, &["escape"]),
+    ];
+
+    match preset {
+        KeyPreset::Default => (insert, vec![]),
+        KeyPreset::Emacs => {
+            insert.extend([
+                (DeleteWord, &["ctrl+w"] as &[&str]),
+                (Clear, &["ctrl+u"]),
+                (Hide, &["ctrl+g"]),
+            ]);
+            (insert, vec![])
+        }
+        KeyPreset::Vim => {
+            let normal: PresetBindings = vec![
+                (SelectNext, &["j", "down", "ctrl+n"]),
+                (SelectPrevious, &["k", "up", "ctrl+p"]),
+                (SelectPageDown, &["ctrl+d", "pagedown"]),
+                (SelectPageUp, &["ctrl+u", "pageup"]),
+                (SelectFirst, &["g", "home"]),
+                (SelectLast, &["shift+g", "end"]),
+                (Launch, &["enter"]),
+                (LaunchAlt, &["alt+enter"]),
+                (CopyResult, &["y"]),
+                (DeleteWord, &["ctrl+w"]),
+                (Clear, &["d"]),
+                (TogglePin, &["p"]),
+                (SearchHistory, &["ctrl+r"]),
+                (InsertMode, &["i", "a"]),
+                (Hide, &["escape", "q"]),
+            ];
+            (insert, normal)
+        }
+    }
+}
+
+/// Applies the configured bindings on top of the preset's, returning the key
+/// bound to each action. Keys the config binds are taken from the actions the
+/// preset bound them to, but binding a key to two actions is an error.
+fn bind(
+    preset: PresetBindings,
+    config: &BTreeMap<KeyAction, Vec<String>>,
+    table: &str,
+) -> Result<Vec<(Chord, KeyAction)>, String> {
+    let mut configured: Vec<(Chord, KeyAction)> = vec![];
+    for (action, keys) in config.iter() {
+        for key in keys {
+            let chord =
+                Chord::parse(key).map_err(|e| format!("{}.{}: {}", table, action.name(), e))?;
+            if let Some((_, other)) = configured.iter().find(|(c, _)| *c == chord) {
+                return Err(format!(
+                    "{}: {} is bound to both {} and {}",
+                    table,
+                    chord,
+                    other.name(),
+                    action.name()
+                ));
+            }
+            configured.push((chord, *action));
+        }
+    }
+
+    let mut bindings = vec![];
+    for (action, keys) in preset {
+        if config.contains_key(&action) {
+            continue;
+        }
+        for key in keys {
+            let chord = Chord::parse(key).expect("preset keys are valid");
+            if !configured.iter().any(|(c, _)| *c == chord) {
+                bindings.push((chord, action));
+            }
+        }
+    }
+    bindings.extend(configured);
+    Ok(bindings)
+}
+
+/// Whether typing goes into the input, or keys act on the results.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Mode {
+    #[default]
+    Insert,
+    Normal,
+}
+
+/// Where the input's text cursor is. Keys which move the cursor are left to
+/// the input until it can't move any further that way.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Cursor {
+    pub at_start: bool,
+    pub at_end: bool,
+    pub on_first_line: bool,
+    pub on_last_line: bool,
+}
+
+impl Cursor {
+    /// Returns where the cursor at the character index of the text is.
+    pub fn new(text: &str, index: usize) -> Self {
+        let split = text
+            .char_indices()
+            .nth(index)
+            .map_or(text.len(), |(i, _)| i);
+        let (before, after) = text.split_at(split);
+        Cursor {
+            at_start: before.is_empty(),
+            at_end: after.is_empty(),
+            on_first_line: !before.contains('\n'),
+            on_last_line: !after.contains('\n'),
+        }
+    }
+
+    /// Returns whether the input would move the cursor for the key.
+    fn moves(self, key: egui::Key) -> bool {
+        match key {
+            egui::Key::Home => !self.at_start,
+            egui::Key::End => !self.at_end,
+            egui::Key::ArrowUp => !self.on_first_line,
+            egui::Key::ArrowDown => !self.on_last_line,
+            _ => false,
+        }
+    }
+}
+
+/// Turns key presses into actions.
+#[derive(Debug, Clone)]
+pub struct Keymap {
+    insert: Vec<(Chord, KeyAction)>,
+    /// Empty unless something switches to normal mode.
+    normal: Vec<(Chord, KeyAction)>,
+    mode: Mode,
+}
+
+impl Default for Keymap {
+    fn default() -> Self {
+        Keymap::new(&KeysConfig::default(), EscapeAction::default()).unwrap()
+    }
+}
+
+impl Keymap {
+    /// Builds the keymap, or describes the problem with the bindings.
+    pub fn new(config: &KeysConfig, on_escape: EscapeAction) -> Result<Self, String> {
+        let (insert, normal) = preset(config.preset, on_escape);
+        let keymap = Keymap {
+            insert: bind(insert, &config.bindings, "keys.bindings")?,
+            normal: bind(normal, &config.normal, "keys.normal")?,
+            mode: Mode::Insert,
+        };
+
+        let binds =
+            |bindings: &[(Chord, KeyAction)], action| bindings.iter().any(|(_, a)| *a == action);
+        if binds(&keymap.insert, KeyAction::NormalMode)
+            && !binds(&keymap.normal, KeyAction::InsertMode)
+        {
+            return Err(
+                "keys: normal-mode is bound, but no key in keys.normal goes back to insert-mode"
+                    .into(),
+            );
+        }
+        Ok(keymap)
+    }
+
+    pub fn mode(&self) -> Mode {
+        self.mode
+    }
+
+    /// Returns the first key bound to the action in the current mode.
+    pub fn chord(&self, action: KeyAction) -> Option<Chord> {
+        let bindings = match self.mode {
+            Mode::Insert => &self.insert,
+            Mode::Normal => &self.normal,
+        };
+        bindings
+            .iter()
+            .find(|(_, a)| *a == action)
+            .map(|(chord, _)| *chord)
+    }
+
+    /// Goes back to insert mode, for when the launcher is shown again.
+    pub fn reset(&mut self) {
+        self.mode = Mode::Insert;
+    }
+
+    /// Takes the key presses bound to actions out of the input, so the text box
+    /// doesn't also act on them, and returns the actions in the order pressed.
+    /// Switching mode is done here rather than returned. Normal mode drops the
+    /// text typed, pasted or cut, & keys other than those moving the cursor, so
+    /// the input's text isn't edited. In insert mode, keys which would move the cursor are
+    /// left to the input.
+    pub fn take_actions(&mut self, input: &mut egui::InputState, cursor: Cursor) -> Vec<KeyAction> {
+        let mut actions = vec![];
+        // Text follows the key press which typed it, so goes with that key's mode.
+        let mut typing = self.mode == Mode::Insert;
+        input.events.retain(|event| match event {
+            egui::Event::Key {
+                key,
+                pressed,
+                modifiers,
+                ..
+            } => {
+                typing = self.mode == Mode::Insert;
+                let bindings = match self.mode {
+                    Mode::Insert => &self.insert,
+                    Mode::Normal => &self.normal,
+                };
+                let action = bindings
+                    .iter()
+                    .find(|(chord, _)| chord.matches(*key, *modifiers))
+                    .map(|(_, action)| *action)
+                    .filter(|_| !(typing && cursor.moves(*key)));
+                match (action, pressed) {
+                    (Some(KeyAction::InsertMode), true) => self.mode = Mode::Insert,
+                    (Some(KeyAction::NormalMode), true) => self.mode = Mode::Normal,
+                    (Some(action), true) => actions.push(action),
+                    _ => {}
+                }
+                action.is_none() && (typing || CURSOR_KEYS.contains(key))
+            }
+            egui::Event::Text(_) | egui::Event::Paste(_) | egui::Event::Cut => typing,
+            _ => true,
+        });
+        actions
+    }
+}
+
+/// Deletes the word before the character index, along with the spaces after
+/// it, returning the text & where the cursor goes.
+pub fn delete_word(text: &str, cursor: usize) -> (String, usize) {
+    let chars: Vec<char> = text.chars().collect();
+    let cursor = cursor.min(chars.len());
+    let mut start = cursor;
+    while start > 0 && chars[start - 1].is_whitespace() {
+        start -= 1;
+    }
+    while start > 0 && !chars[start - 1].is_whitespace() {
+        start -= 1;
+    }
+    let text = chars[..start]
+        .iter()
+        .chain(chars[cursor..].iter())
+        .collect();
+    (text, start)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn press(key: egui::Key, modifiers: egui::Modifiers, text: Option<&str>) -> Vec<egui::Event> {
+        let mut events = vec![egui::Event::Key {
+            key,
+            pressed: true,
+            repeat: false,
+            modifiers,
+        }];
+        events.extend(text.map(|t| egui::Event::Text(t.into())));
+        events
+    }
+
+    /// Feeds the events through the keymap, returning the actions & the events left.
+    fn take(keymap: &mut Keymap, events: Vec<egui::Event>) -> (Vec<KeyAction>, Vec<egui::Event>) {
+        let mut input = egui::InputState::default();
+        input.events = events;
+        let actions = keymap.take_actions(&mut input, Cursor::new("", 0));
+        (actions, input.events)
+    }
+
+    #[test]
+    fn chords() {
+        let chord = Chord::parse("Ctrl+Shift+C").unwrap();
+        assert_eq!(chord.key, egui::Key::C);
+        assert!(chord.modifiers.ctrl && chord.modifiers.shift && !chord.modifiers.alt);
+        assert_eq!(chord.to_string(), "ctrl+shift+c");
+        assert_eq!(Chord::parse("esc").unwrap().key, egui::Key::Escape);
+        assert_eq!(Chord::parse("pagedown").unwrap().key, egui::Key::PageDown);
+        assert_eq!(Chord::parse("ctrl++").unwrap().key, egui::Key::PlusEquals);
+        assert_eq!(Chord::parse("f5").unwrap().key, egui::Key::F5);
+        assert!(Chord::parse("hyper+a").is_err());
+        assert!(Chord::parse("ctrl+nope").is_err());
+        assert!(Chord::parse("").is_err());
+    }
+
+    #[test]
+    fn presets() {
+        for preset in [KeyPreset::Default, KeyPreset::Emacs, KeyPreset::Vim] {
+            for on_escape in [EscapeAction::Exit, EscapeAction::Clear] {
+                let config = KeysConfig {
+                    preset,
+                    ..Default::default()
+                };
+                assert!(Keymap::new(&config, on_escape).is_ok(), "{:?}", preset);
+            }
+        }
+
+        let ctrl = egui::Modifiers::CTRL;
+        let mut emacs = Keymap::new(
+            &KeysConfig {
+                preset: KeyPreset::Emacs,
+                ..Default::default()
+            },
+            EscapeAction::Exit,
+        )
+        .unwrap();
+        let mut events = press(egui::Key::N, ctrl, None);
+        events.extend(press(egui::Key::W, ctrl, None));
+        events.extend(press(egui::Key::U, ctrl, None));
+        events.extend(press(egui::Key::A, egui::Modifiers::NONE, Some("a")));
+        let (actions, left) = take(&mut emacs, events);
+        assert_eq!(
+            actions,
+            vec![
+                KeyAction::SelectNext,
+                KeyAction::DeleteWord,
+                KeyAction::Clear
+            ]
+        );
+        assert_eq!(left.len(), 2);
+    }
+
+    #[test]
+    fn vim_modes() {
+        let none = egui::Modifiers::NONE;
+        let mut vim = Keymap::new(
+            &KeysConfig {
+                preset: KeyPreset::Vim,
+                ..Default::default()
+            },
+            EscapeAction::Exit,
+        )
+        .unwrap();
+
+        // j is typed in insert mode.
+        let (actions, left) = take(&mut vim, press(egui::Key::J, none, Some("j")));
+        assert!(actions.is_empty());
+        assert_eq!(left.len(), 2);
+
+        let mut events = press(egui::Key::Escape, none, None);
+        events.extend(press(egui::Key::J, none, Some("j")));
+        events.extend(press(egui::Key::G, egui::Modifiers::SHIFT, Some("G")));
+        events.extend(press(egui::Key::X, none, Some("x")));
+        let (actions, left) = take(&mut vim, events);
+        assert_eq!(vim.mode(), Mode::Normal);
+        assert_eq!(actions, vec![KeyAction::SelectNext, KeyAction::SelectLast]);
+        // Unbound keys don't type, nor edit the input, but still move its cursor.
+        assert!(left.is_empty());
+        let mut events = press(egui::Key::Backspace, none, None);
+        events.extend(press(egui::Key::Delete, none, None));
+        events.extend(press(egui::Key::ArrowLeft, none, None));
+        events.push(egui::Event::Paste("x".into()));
+        events.push(egui::Event::Cut);
+        events.push(egui::Event::Copy);
+        let (actions, left) = take(&mut vim, events);
+        assert!(actions.is_empty());
+        assert_eq!(left.len(), 2);
+
+        // Nor is the i which goes back to insert mode.
+        let mut events = press(egui::Key::I, none, Some("i"));
+        events.extend(press(egui::Key::K, none, Some("k")));
+        let (actions, left) = take(&mut vim, events);
+        assert_eq!(vim.mode(), Mode::Insert);
+        assert!(actions.is_empty());
+        assert_eq!(left.len(), 2);
+    }
+
+    #[test]
+    fn cursor_keys() {
+        let none = egui::Modifiers::NONE;
+        let mut keymap = Keymap::default();
+        let keys = || {
+            let mut events = press(egui::Key::Home, none, None);
+            events.extend(press(egui::Key::End, none, None));
+            events.extend(press(egui::Key::ArrowUp, none, None));
+            events.extend(press(egui::Key::ArrowDown, none, None));
+            events
+        };
+        let mut take_at = |text: &str, index: usize| {
+            let mut input = egui::InputState::default();
+            input.events = keys();
+            let actions = keymap.take_actions(&mut input, Cursor::new(text, index));
+            (actions, input.events.len())
+        };
+
+        // Home & End select while the cursor is already at that end.
+        let (actions, left) = take_at("fire", 4);
+        assert_eq!(
+            actions,
+            vec![
+                KeyAction::SelectLast,
+                KeyAction::SelectPrevious,
+                KeyAction::SelectNext
+            ]
+        );
+        assert_eq!(left, 1);
+        let (actions, left) = take_at("fire", 2);
+        assert_eq!(
+            actions,
+            vec![KeyAction::SelectPrevious, KeyAction::SelectNext]
+        );
+        assert_eq!(left, 2);
+        // Up & down move between lines before selecting.
+        let (actions, left) = take_at("a\nb\nc", 2);
+        assert!(actions.is_empty());
+        assert_eq!(left, 4);
+        let (actions, _) = take_at("a\nb", 0);
+        assert_eq!(
+            actions,
+            vec![KeyAction::SelectFirst, KeyAction::SelectPrevious]
+        );
+
+        assert_eq!(
+            keymap.chord(KeyAction::TogglePin).unwrap().to_string(),
+            "ctrl+d"
+        );
+    }
+
+    #[test]
+    fn configured() {
+        let mut config = KeysConfig::default();
+        config
+            .bindings
+            .insert(KeyAction::Launch, vec!["ctrl+n".into()]);
+        config.bindings.insert(KeyAction::CopyResult, vec![]);
+        let mut keymap = Keymap::new(&config, EscapeAction::Exit).unwrap();
+        // Ctrl+N is taken from select-next, & enter no longer launches.
+        let mut events = press(egui::Key::N, egui::Modifiers::CTRL, None);
+        events.extend(press(egui::Key::Enter, egui::Modifiers::NONE, None));
+        events.extend(press(
+            egui::Key::C,
+            egui::Modifiers {
+                ctrl: true,
+                shift: true,
+                ..Default::default()
+            },
+            None,
+        ));
+        let (actions, left) = take(&mut keymap, events);
+        assert_eq!(actions, vec![KeyAction::Launch]);
+        assert_eq!(left.len(), 2);
+
+        config
+            .bindings
+            .insert(KeyAction::Clear, vec!["Ctrl+N".into()]);
+        assert_eq!(
+            Keymap::new(&config, EscapeAction::Exit).unwrap_err(),
+            "keys.bindings: ctrl+n is bound to both launch and clear"
+        );
+
+        let mut config = KeysConfig::default();
+        config
+            .bindings
+            .insert(KeyAction::NormalMode, vec!["f1".into()]);
+        assert!(Keymap::new(&config, EscapeAction::Exit).is_err());
+        config
+            .normal
+            .insert(KeyAction::InsertMode, vec!["f1".into()]);
+        assert!(Keymap::new(&config, EscapeAction::Exit).is_ok());
+
+        config
+            .normal
+            .insert(KeyAction::Hide, vec!["ctrl+q+x".into()]);
+        assert!(Keymap::new(&config, EscapeAction::Exit).is_err());
+    }
+
+    #[test]
+    fn delete_words() {
+        assert_eq!(delete_word("open firefox", 12), ("open ".into(), 5));
+        assert_eq!(delete_word("open firefox  ", 14), ("open ".into(), 5));
+        assert_eq!(delete_word("open firefox", 4), (" firefox".into(), 0));
+        assert_eq!(delete_word("", 0), ("".into(), 0));
+    }
+}
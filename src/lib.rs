@@ -1,10 +1,43 @@
 #![warn(clippy::all, rust_2018_idioms)]
 
 mod app;
+#[cfg(not(target_arch = "wasm32"))]
+mod bookmarks;
+mod executor;
 mod ext;
+mod favourites;
+#[cfg(not(target_arch = "wasm32"))]
+mod files;
+mod history;
+mod keymap;
+mod percent;
+#[cfg(not(target_arch = "wasm32"))]
+mod plugins;
+#[cfg(not(target_arch = "wasm32"))]
+mod processes;
+mod provider;
+#[cfg(not(target_arch = "wasm32"))]
+mod recent;
+#[cfg(not(target_arch = "wasm32"))]
+mod scripts;
+#[cfg(not(target_arch = "wasm32"))]
+mod session;
+#[cfg(not(target_arch = "wasm32"))]
+mod shell;
+#[cfg(not(target_arch = "wasm32"))]
+mod ssh;
+mod unicode;
+mod websearch;
 pub use app::Launcher;
 
+pub mod cli;
+pub mod config;
+pub mod daemon;
+pub mod dmenu;
 pub mod eq;
+pub mod headless;
 pub mod sys_apps;
+pub mod theme;
+pub mod window;
 
 pub(crate) mod eqwidget;
@@ -6,15 +6,114 @@
 fn main() -> eframe::Result<()> {
     env_logger::init(); // Log to stderr (if you run with `RUST_LOG=debug`).
 
+    use liquid_launcher::cli::{Args, USAGE};
+    use liquid_launcher::config::Config;
+
+    let args = match Args::parse(&std::env::args().skip(1).collect::<Vec<_>>()) {
+        Ok(args) => args,
+        Err(e) => {
+            eprintln!("{}\n\n{}", e, USAGE);
+            std::process::exit(2);
+        }
+    };
+    if args.help {
+        println!("{}", USAGE);
+        return Ok(());
+    }
+    if args.headless {
+        std::process::exit(liquid_launcher::headless::run(&args));
+    }
+
+    #[cfg(not(unix))]
+    if args.daemon || args.command.is_some() {
+        eprintln!("--daemon, --show, --hide and --toggle are only supported on unix");
+        std::process::exit(2);
+    }
+
+    #[cfg(unix)]
+    let listener = {
+        use liquid_launcher::daemon;
+
+        let path = match daemon::socket_path() {
+            Ok(path) => path,
+            Err(e) => {
+                eprintln!("failed to find where the daemon listens: {}", e);
+                std::process::exit(1);
+            }
+        };
+        if let Some(command) = &args.command {
+            if let Err(e) = daemon::send(&path, command) {
+                eprintln!("failed to reach the daemon at {}: {}", path.display(), e);
+                std::process::exit(1);
+            }
+            return Ok(());
+        }
+
+        if args.daemon {
+            match daemon::bind(&path) {
+                // The window isn't opened until it's first shown, as it can't be
+                // hidden under Wayland.
+                Ok(listener) => Some((daemon::wait_for_show(&listener), listener)),
+                Err(e) => {
+                    eprintln!("failed to listen on {}: {}", path.display(), e);
+                    std::process::exit(1);
+                }
+            }
+        } else {
+            None
+        }
+    };
+
+    let (config, config_error) = Config::load(args.config.as_deref());
+    if let Some(e) = &config_error {
+        eprintln!("{}", e);
+    }
+
+    let w = &config.window;
     let native_options = eframe::NativeOptions {
-        initial_window_size: Some([300.0, 260.0].into()),
-        min_window_size: Some([200.0, 220.0].into()),
+        initial_window_size: Some([w.width, w.height].into()),
+        min_window_size: Some([w.min_width, w.min_height].into()),
+        decorated: w.decorated,
+        always_on_top: w.always_on_top,
+        transparent: w.transparent,
+        // Moved to the configured offset once the monitor size is known.
+        centered: true,
         ..Default::default()
     };
+
+    if let Some(options) = args.dmenu.clone() {
+        let items = liquid_launcher::dmenu::read_items(std::io::stdin().lock())
+            .expect("failed to read items from stdin");
+
+        eframe::run_native(
+            "liquid launcher",
+            native_options,
+            Box::new(|cc| {
+                Box::new(liquid_launcher::Launcher::new_dmenu(
+                    cc, config, options, items,
+                ))
+            }),
+        )?;
+        // Choosing exits straight away, so the window was closed without a choice.
+        std::process::exit(1);
+    }
+
     eframe::run_native(
         "liquid launcher",
         native_options,
-        Box::new(|cc| Box::new(liquid_launcher::Launcher::new(cc))),
+        Box::new(move |cc| {
+            #[allow(unused_mut)]
+            let mut launcher = liquid_launcher::Launcher::new(cc, config)
+                .with_config_error(config_error)
+                .with_args(&args);
+
+            #[cfg(unix)]
+            if let Some((shown_by, listener)) = listener {
+                let commands = liquid_launcher::daemon::spawn(listener, cc.egui_ctx.clone());
+                launcher = launcher.with_commands(shown_by, commands);
+            }
+            Box::new(launcher)
+        }),
     )
 }
 
@@ -31,7 +130,12 @@ fn main() {
             .start(
                 "the_canvas_id", // hardcode it
                 web_options,
-                Box::new(|cc| Box::new(liquid_launcher::Launcher::new(cc))),
+                Box::new(|cc| {
+                    Box::new(liquid_launcher::Launcher::new(
+                        cc,
+                        liquid_launcher::config::Config::default(),
+                    ))
+                }),
             )
             .await
             .expect("failed to start eframe");
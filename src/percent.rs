@@ -0,0 +1,64 @@
+//! Percent-encoding of text in URLs & URIs.
+
+/// Encodes everything except the unreserved characters of RFC 3986, so the
+/// text can be used anywhere in a URL.
+pub fn encode(s: &str) -> String {
+    let mut out = String::with_capacity(s.len());
+    for b in s.bytes() {
+        match b {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' => {
+                out.push(b as char)
+            }
+            _ => out.push_str(&format!("%{:02X}", b)),
+        }
+    }
+    out
+}
+
+/// Decodes `%XX` escapes, leaving a `%` which doesn't start one as it is.
+pub fn decode(s: &str) -> String {
+    let bytes = s.as_bytes();
+    let mut out = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        // from_str_radix would also take a sign, like the `+` of `%+1`.
+        let hex = bytes
+            .get(i + 1..i + 3)
+            .filter(|h| h.iter().all(u8::is_ascii_hexdigit))
+            .and_then(|h| std::str::from_utf8(h).ok())
+            .and_then(|h| u8::from_str_radix(h, 16).ok());
+        match (bytes[i], hex) {
+            (b'%', Some(b)) => {
+                out.push(b);
+                i += 3;
+            }
+            (b, _) => {
+                out.push(b);
+                i += 1;
+            }
+        }
+    }
+    String::from_utf8_lossy(&out).to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn encodes() {
+        assert_eq!(encode("rust-lang_1.0~"), "rust-lang_1.0~");
+        assert_eq!(encode("café & tea"), "caf%C3%A9%20%26%20tea");
+        assert_eq!(encode("a+b=c/d?e#f"), "a%2Bb%3Dc%2Fd%3Fe%23f");
+    }
+
+    #[test]
+    fn decodes() {
+        assert_eq!(decode("/tmp/My%20Notes%2Emd%"), "/tmp/My Notes.md%");
+        assert_eq!(decode("caf%C3%A9%zz"), "café%zz");
+        assert_eq!(decode("%+1%-1"), "%+1%-1");
+        for s in ["café & tea", "a+b=c/d?e#f"] {
+            assert_eq!(decode(&encode(s)), s);
+        }
+    }
+}
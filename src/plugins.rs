@@ -0,0 +1,499 @@
+//! Providers run as processes of their own, found in
+//! `$XDG_DATA_HOME/liquid-launcher/plugins` once enabled with `enabled = true`
+//! in the `[plugins]` section of the config. Each plugin has a directory there
+//! holding a `plugin.toml` manifest:
+//!
+//! ```toml
+//! name = "tickets"
+//! # Run in the plugin's directory.
+//! command = ["python3", "tickets.py"]
+//! # The protocol version the plugin speaks.
+//! protocol = 1
+//! # Optional: only query the plugin for inputs like `t 1234`, listing nothing else.
+//! keyword = "t"
+//! # Optional: how long to wait for results, in milliseconds.
+//! timeout_ms = 300
+//! ```
+//!
+//! The plugin is started the first time it's queried, then kept running. The
+//! launcher writes each query to its stdin as a line of JSON:
+//!
+//! ```json
+//! {"version":1,"id":7,"query":"printer"}
+//! ```
+//!
+//! and the plugin answers on stdout with a line of JSON for each query, with the
+//! same `id`:
+//!
+//! ```json
+//! {"id":7,"results":[{"title":"Printer on fire","subtitle":"TICKET-1234","icon":"🎫","score":50,
+//!   "action":{"open":"https://tickets.example.com/1234"},"alt_action":{"copy":"TICKET-1234"}}]}
+//! ```
+//!
+//! Only `title` & `action` are required. Actions are one of `{"open": url or path}`,
+//! `{"reveal": path}`, `{"spawn": [program, args…]}`, `{"copy": text}` or
+//! `{"set_input": text}`. Anything else the plugin writes, and answers to queries
+//! the launcher has given up on, are ignored. A plugin which exits is started
+//! again on the next query, up to a few times in a row.
+
+use fuzzy_matcher::skim::SkimMatcherV2;
+use std::io::{BufRead, BufReader, Write};
+use std::path::{Path, PathBuf};
+use std::process::{Child, ChildStdin, Command, Stdio};
+use std::sync::mpsc::{channel, Receiver, RecvTimeoutError};
+use std::time::{Duration, Instant};
+
+use crate::provider::{Action, Cancel, Provider, ResultItem};
+
+/// Version of the protocol the launcher speaks, sent with every query.
+pub const PROTOCOL_VERSION: u32 = 1;
+
+/// Times in a row a plugin may exit or fail to start before it's given up on.
+const MAX_FAILURES: u32 = 3;
+
+/// How often a plugin's wait for an answer checks whether it's been cancelled.
+const CANCEL_CHECK: Duration = Duration::from_millis(10);
+
+/// Describes a plugin, read from the `plugin.toml` in its directory.
+#[derive(Debug, Clone, PartialEq, Eq, serde::Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Manifest {
+    pub name: String,
+    /// Program & arguments, run in the plugin's directory.
+    pub command: Vec<String>,
+    /// The protocol version the plugin speaks.
+    pub protocol: u32,
+    /// Only query the plugin for inputs starting with this word, without it.
+    #[serde(default)]
+    pub keyword: Option<String>,
+    #[serde(default = "default_timeout_ms")]
+    pub timeout_ms: u64,
+}
+
+fn default_timeout_ms() -> u64 {
+    500
+}
+
+impl Manifest {
+    /// Reads the manifest in the plugin's directory.
+    pub fn load(dir: &Path) -> Result<Self, String> {
+        let path = dir.join("plugin.toml");
+        let text =
+            std::fs::read_to_string(&path).map_err(|e| format!("{}: {}", path.display(), e))?;
+        let manifest: Manifest =
+            toml::from_str(&text).map_err(|e| format!("{}: {}", path.display(), e))?;
+        if manifest.command.is_empty() {
+            return Err(format!("{}: command is empty", path.display()));
+        }
+        if manifest.protocol != PROTOCOL_VERSION {
+            return Err(format!(
+                "{}: protocol version {} isn't supported, only {}",
+                path.display(),
+                manifest.protocol,
+                PROTOCOL_VERSION
+            ));
+        }
+        Ok(manifest)
+    }
+}
+
+#[derive(serde::Serialize)]
+struct Request<'a> {
+    version: u32,
+    id: u64,
+    query: &'a str,
+}
+
+#[derive(serde::Deserialize)]
+struct Response {
+    id: u64,
+    #[serde(default)]
+    results: Vec<PluginItem>,
+}
+
+#[derive(serde::Deserialize)]
+struct PluginItem {
+    title: String,
+    #[serde(default)]
+    subtitle: Option<String>,
+    /// Shown in place of an icon, usually an emoji.
+    #[serde(default)]
+    icon: Option<String>,
+    #[serde(default)]
+    score: i64,
+    action: PluginAction,
+    #[serde(default)]
+    alt_action: Option<PluginAction>,
+}
+
+#[derive(serde::Deserialize)]
+#[serde(rename_all = "snake_case")]
+enum PluginAction {
+    Open(String),
+    Reveal(PathBuf),
+    Spawn(Vec<String>),
+    Copy(String),
+    SetInput(String),
+}
+
+impl From<PluginAction> for Action {
+    fn from(action: PluginAction) -> Self {
+        match action {
+            PluginAction::Open(target) => Action::Open(target),
+            PluginAction::Reveal(path) => Action::Reveal(path),
+            PluginAction::Spawn(command) => Action::Spawn(command),
+            PluginAction::Copy(text) => Action::Copy(text),
+            PluginAction::SetInput(text) => Action::SetInput(text),
+        }
+    }
+}
+
+impl From<PluginItem> for ResultItem {
+    fn from(item: PluginItem) -> Self {
+        ResultItem {
+            title: item.title,
+            subtitle: item.subtitle,
+            glyph: item.icon.unwrap_or_else(|| "🧩".into()),
+            score: item.score,
+            action: item.action.into(),
+            alt_action: item.alt_action.map(Action::from),
+        }
+    }
+}
+
+/// A running plugin, and the lines it has written to stdout.
+struct Process {
+    child: Child,
+    stdin: ChildStdin,
+    lines: Receiver<String>,
+}
+
+impl Drop for Process {
+    /// Kills the plugin along with whatever it started, which is in its process
+    /// group.
+    fn drop(&mut self) {
+        #[cfg(unix)]
+        unsafe {
+            libc::kill(-(self.child.id() as libc::pid_t), libc::SIGKILL);
+        }
+        #[cfg(not(unix))]
+        let _ = self.child.kill();
+        let _ = self.child.wait();
+    }
+}
+
+/// Queries a plugin.
+pub struct PluginProvider {
+    /// Plugins are only loaded once, so their names are leaked to live as long
+    /// as the built-in providers' names.
+    name: &'static str,
+    manifest: Manifest,
+    dir: PathBuf,
+    process: Option<Process>,
+    /// Identifies the last query sent, so answers to earlier ones are skipped.
+    id: u64,
+    /// Times in a row the plugin has exited or failed to start.
+    failures: u32,
+}
+
+impl PluginProvider {
+    pub fn new(manifest: Manifest, dir: PathBuf) -> Self {
+        Self {
+            name: Box::leak(manifest.name.clone().into_boxed_str()),
+            manifest,
+            dir,
+            process: None,
+            id: 0,
+            failures: 0,
+        }
+    }
+
+    /// Returns the query to send for the input, if it's meant for the plugin.
+    fn query_for<'a>(&self, input: &'a str) -> Option<&'a str> {
+        match &self.manifest.keyword {
+            Some(keyword) => input.strip_prefix(keyword.as_str())?.strip_prefix(' '),
+            None => Some(input),
+        }
+    }
+
+    fn start(&self) -> std::io::Result<Process> {
+        let mut command = Command::new(&self.manifest.command[0]);
+        command
+            .args(&self.manifest.command[1..])
+            .current_dir(&self.dir)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped());
+        // In a process group of its own, so whatever it starts can be killed with it.
+        #[cfg(unix)]
+        std::os::unix::process::CommandExt::process_group(&mut command, 0);
+        let mut child = command.spawn()?;
+        let stdin = child.stdin.take().unwrap();
+        let stdout = child.stdout.take().unwrap();
+
+        let (sender, lines) = channel();
+        std::thread::spawn(move || {
+            for line in BufReader::new(stdout).lines().map_while(Result::ok) {
+                if sender.send(line).is_err() {
+                    break;
+                }
+            }
+        });
+        Ok(Process {
+            child,
+            stdin,
+            lines,
+        })
+    }
+
+    /// Notes that the plugin has stopped, giving up on it if it keeps doing so.
+    fn failed(&mut self, why: &str) {
+        self.process = None;
+        self.failures += 1;
+        eprintln!("plugin {:?} {}", self.name, why);
+        if self.failures == MAX_FAILURES {
+            eprintln!(
+                "plugin {:?} has failed too often, so won't be started again",
+                self.name
+            );
+        }
+    }
+
+    /// Sends the query to the plugin, starting it if need be, and waits for its
+    /// answer until the timeout or the query is cancelled.
+    fn ask(&mut self, query: &str, cancel: &Cancel) -> Vec<ResultItem> {
+        if self.failures >= MAX_FAILURES {
+            return vec![];
+        }
+        if self.process.is_none() {
+            match self.start() {
+                Ok(process) => self.process = Some(process),
+                Err(e) => {
+                    self.failed(&format!("failed to start: {}", e));
+                    return vec![];
+                }
+            }
+        }
+
+        self.id += 1;
+        let request = Request {
+            version: PROTOCOL_VERSION,
+            id: self.id,
+            query,
+        };
+        let mut line = serde_json::to_string(&request).unwrap();
+        line.push('\n');
+        let stdin = &mut self.process.as_mut().unwrap().stdin;
+        if stdin
+            .write_all(line.as_bytes())
+            .and_then(|_| stdin.flush())
+            .is_err()
+        {
+            self.failed("exited");
+            return vec![];
+        }
+
+        let deadline = Instant::now() + Duration::from_millis(self.manifest.timeout_ms);
+        loop {
+            let left = deadline.saturating_duration_since(Instant::now());
+            let lines = &self.process.as_ref().unwrap().lines;
+            let line = lines.recv_timeout(left.min(CANCEL_CHECK));
+            match line {
+                Ok(line) => match serde_json::from_str::<Response>(&line) {
+                    Ok(response) if response.id == self.id => {
+                        self.failures = 0;
+                        return response.results.into_iter().map(|r| r.into()).collect();
+                    }
+                    // An answer to a query given up on.
+                    Ok(_) => {}
+                    Err(e) => eprintln!("plugin {:?} wrote {:?}: {}", self.name, line, e),
+                },
+                Err(RecvTimeoutError::Timeout) if left <= CANCEL_CHECK || cancel.is_cancelled() => {
+                    return vec![]
+                }
+                Err(RecvTimeoutError::Timeout) => {}
+                Err(RecvTimeoutError::Disconnected) => {
+                    self.failed("exited");
+                    return vec![];
+                }
+            }
+        }
+    }
+}
+
+impl Provider for PluginProvider {
+    fn name(&self) -> &'static str {
+        self.name
+    }
+
+    fn query(&mut self, _matcher: &SkimMatcherV2, input: &str, cancel: &Cancel) -> Vec<ResultItem> {
+        match self.query_for(input) {
+            Some(query) if !query.trim().is_empty() => self.ask(query, cancel),
+            _ => vec![],
+        }
+    }
+
+    fn exclusive(&self, input: &str) -> bool {
+        self.manifest.keyword.is_some() && self.query_for(input).is_some()
+    }
+
+    fn budget(&self) -> Duration {
+        Duration::from_millis(self.manifest.timeout_ms)
+    }
+}
+
+/// Returns the directory plugins are found in by default.
+pub fn default_dir() -> Option<PathBuf> {
+    let dir = match std::env::var_os("XDG_DATA_HOME") {
+        Some(dir) if !dir.is_empty() => PathBuf::from(dir),
+        _ => PathBuf::from(std::env::var_os("HOME")?).join(".local/share"),
+    };
+    Some(dir.join("liquid-launcher/plugins"))
+}
+
+/// Loads the plugins in the directory, in order of name, along with problems
+/// with those which couldn't be loaded.
+pub fn discover(dir: &Path) -> (Vec<PluginProvider>, Vec<String>) {
+    let mut dirs: Vec<PathBuf> = match std::fs::read_dir(dir) {
+        Ok(entries) => entries
+            .filter_map(|e| Some(e.ok()?.path()))
+            .filter(|p| p.is_dir())
+            .collect(),
+        Err(_) => return (vec![], vec![]),
+    };
+    dirs.sort();
+
+    let (mut plugins, mut errors) = (vec![], vec![]);
+    for dir in dirs {
+        match Manifest::load(&dir) {
+            Ok(manifest) => plugins.push(PluginProvider::new(manifest, dir)),
+            Err(e) => errors.push(e),
+        }
+    }
+    (plugins, errors)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn plugins_dir() -> PathBuf {
+        PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("testdata/plugins")
+    }
+
+    fn echo() -> PluginProvider {
+        let (mut plugins, _) = discover(&plugins_dir());
+        let i = plugins.iter().position(|p| p.name == "echo").unwrap();
+        plugins.remove(i)
+    }
+
+    fn query(p: &mut PluginProvider, input: &str) -> Vec<ResultItem> {
+        p.query(&SkimMatcherV2::default(), input, &Cancel::default())
+    }
+
+    #[test]
+    fn discovers() {
+        let (plugins, errors) = discover(&plugins_dir());
+        let names: Vec<_> = plugins.iter().map(|p| p.name()).collect();
+        assert_eq!(names, vec!["echo"]);
+        assert_eq!(errors.len(), 1);
+        assert!(errors[0].contains("protocol version 2"), "{}", errors[0]);
+
+        assert!(discover(&plugins_dir().join("missing")).0.is_empty());
+    }
+
+    #[test]
+    fn protocol() {
+        let mut p = echo();
+        let results = query(&mut p, "e hello");
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0].title, "You said hello");
+        assert_eq!(results[0].subtitle.as_deref(), Some("echo"));
+        assert_eq!(results[0].glyph, "🔊");
+        assert_eq!(results[0].score, 5);
+        assert_eq!(results[0].action, Action::Copy("hello".into()));
+        assert_eq!(
+            results[0].alt_action,
+            Some(Action::Spawn(vec!["echo".into(), "hello".into()]))
+        );
+
+        // Only inputs starting with the keyword are for the plugin.
+        assert!(p.exclusive("e hello"));
+        assert!(!p.exclusive("hello"));
+        assert!(query(&mut p, "hello").is_empty());
+
+        // Lines which aren't answers are skipped.
+        assert_eq!(query(&mut p, "e garbage")[0].title, "You said garbage");
+    }
+
+    #[test]
+    fn timeout() {
+        let mut p = echo();
+        assert_eq!(p.budget(), Duration::from_millis(300));
+        let start = Instant::now();
+        assert!(query(&mut p, "e hang").is_empty());
+        assert!(start.elapsed() < Duration::from_millis(900));
+        // The plugin answers the query given up on first, which is skipped.
+        assert_eq!(query(&mut p, "e release")[0].title, "You said release");
+    }
+
+    #[test]
+    fn cancelled() {
+        let mut p = echo();
+        p.manifest.timeout_ms = 60_000;
+        let cancel = Cancel::default();
+        cancel.cancel();
+        let start = Instant::now();
+        assert!(p
+            .query(&SkimMatcherV2::default(), "e hang", &cancel)
+            .is_empty());
+        assert!(start.elapsed() < Duration::from_secs(30));
+        assert_eq!(query(&mut p, "e release")[0].title, "You said release");
+    }
+
+    #[test]
+    fn crashes() {
+        let mut p = echo();
+        // Only exiting, not running out of time, ends the wait.
+        p.manifest.timeout_ms = 60_000;
+        assert!(query(&mut p, "e crash").is_empty());
+        assert_eq!(p.failures, 1);
+        // Started again for the next query.
+        assert_eq!(query(&mut p, "e back")[0].title, "You said back");
+        assert_eq!(p.failures, 0);
+
+        for _ in 0..MAX_FAILURES {
+            query(&mut p, "e crash");
+        }
+        assert!(query(&mut p, "e back").is_empty());
+        assert!(p.process.is_none());
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn kills_group() {
+        let mut p = echo();
+        let pid_file =
+            std::env::temp_dir().join(format!("liquid-launcher-plugin-{}", std::process::id()));
+        let script = format!(
+            "sleep 30 & echo $! > '{}'; exec sh echo.sh",
+            pid_file.display()
+        );
+        p.manifest.command = vec!["sh".into(), "-c".into(), script];
+        assert_eq!(query(&mut p, "e hi")[0].title, "You said hi");
+        let pid = std::fs::read_to_string(&pid_file).unwrap();
+        std::fs::remove_file(&pid_file).unwrap();
+        drop(p);
+
+        // What the plugin started is killed too, though it may not have been
+        // reaped yet.
+        let alive = || match std::fs::read_to_string(format!("/proc/{}/stat", pid.trim())) {
+            Ok(stat) => !stat.rsplit(") ").next().unwrap().starts_with('Z'),
+            Err(_) => false,
+        };
+        let start = Instant::now();
+        while alive() {
+            assert!(start.elapsed() < Duration::from_secs(5));
+            std::thread::sleep(Duration::from_millis(10));
+        }
+    }
+}
@@ -0,0 +1,562 @@
+use fuzzy_matcher::skim::SkimMatcherV2;
+use fuzzy_matcher::FuzzyMatcher;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+use crate::provider::{Action, Cancel, Provider, ResultItem};
+
+/// Typing this searches the running processes.
+const PREFIX: &str = "kill";
+
+/// At most this many processes are listed.
+const MAX_RESULTS: usize = 10;
+
+/// Returns the clock ticks a second, the units of the CPU times in
+/// `/proc/<pid>/stat`.
+fn clock_ticks() -> f64 {
+    // SAFETY: sysconf has no preconditions.
+    match unsafe { libc::sysconf(libc::_SC_CLK_TCK) } {
+        ticks if ticks > 0 => ticks as f64,
+        _ => 100.,
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Signal {
+    Term,
+    Kill,
+    Stop,
+    Cont,
+}
+
+impl Signal {
+    const ALL: [Signal; 4] = [Signal::Term, Signal::Kill, Signal::Stop, Signal::Cont];
+
+    fn name(self) -> &'static str {
+        match self {
+            Signal::Term => "TERM",
+            Signal::Kill => "KILL",
+            Signal::Stop => "STOP",
+            Signal::Cont => "CONT",
+        }
+    }
+
+    fn number(self) -> libc::c_int {
+        match self {
+            Signal::Term => libc::SIGTERM,
+            Signal::Kill => libc::SIGKILL,
+            Signal::Stop => libc::SIGSTOP,
+            Signal::Cont => libc::SIGCONT,
+        }
+    }
+
+    fn description(self) -> &'static str {
+        match self {
+            Signal::Term => "Ask the process to quit",
+            Signal::Kill => "Stop the process immediately, without letting it clean up",
+            Signal::Stop => "Pause the process",
+            Signal::Cont => "Resume a paused process",
+        }
+    }
+
+    /// Parses a signal name, with or without the SIG prefix, in any case.
+    fn parse(name: &str) -> Option<Signal> {
+        let name = name.to_uppercase();
+        let name = name.strip_prefix("SIG").unwrap_or(&name);
+        Signal::ALL.into_iter().find(|s| s.name() == name)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Process {
+    pub pid: u32,
+    /// The executable name the kernel keeps, cut to 15 bytes.
+    pub name: String,
+    /// Empty for kernel threads & zombies.
+    pub cmdline: Vec<String>,
+    pub uid: u32,
+    /// Name of the owner, or their uid if they aren't in the passwd file.
+    pub user: String,
+    /// Percentage of a CPU used over the lifetime of the process, like `ps`.
+    pub cpu: f32,
+    /// Resident memory, in KiB.
+    pub rss: u64,
+}
+
+/// The fields of `/proc/<pid>/stat` we use.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Stat {
+    name: String,
+    /// CPU time spent in user & kernel mode, in clock ticks.
+    cpu_ticks: u64,
+    /// When the process started, in clock ticks after boot.
+    start_ticks: u64,
+}
+
+/// Parses `/proc/<pid>/stat`. The name is in parentheses and may contain
+/// anything, including spaces & parentheses, so the fields are counted from the
+/// last closing parenthesis.
+fn parse_stat(data: &str) -> Option<Stat> {
+    let open = data.find('(')?;
+    let close = data.rfind(')')?;
+    let name = data.get(open + 1..close)?.to_string();
+    // Fields after the name, starting with the state, the 3rd field.
+    let fields: Vec<&str> = data[close + 1..].split_whitespace().collect();
+    let field = |n: usize| -> Option<u64> { fields.get(n - 3)?.parse().ok() };
+    Some(Stat {
+        name,
+        cpu_ticks: field(14)? + field(15)?,
+        start_ticks: field(22)?,
+    })
+}
+
+/// Returns the real uid & resident memory in KiB from `/proc/<pid>/status`.
+/// Kernel threads have no memory of their own, so it's zero for them.
+fn parse_status(data: &str) -> Option<(u32, u64)> {
+    let mut uid = None;
+    let mut rss = 0;
+    for line in data.lines() {
+        if let Some(ids) = line.strip_prefix("Uid:") {
+            uid = ids.split_whitespace().next()?.parse().ok();
+        } else if let Some(kib) = line.strip_prefix("VmRSS:") {
+            rss = kib.trim().trim_end_matches("kB").trim().parse().ok()?;
+        }
+    }
+    Some((uid?, rss))
+}
+
+/// Parses `/proc/<pid>/cmdline`, whose arguments are separated by nul bytes.
+fn parse_cmdline(data: &[u8]) -> Vec<String> {
+    data.split(|b| *b == 0)
+        .filter(|arg| !arg.is_empty())
+        .map(|arg| String::from_utf8_lossy(arg).to_string())
+        .collect()
+}
+
+/// Reads the user names by uid from a passwd file.
+fn parse_passwd(data: &str) -> HashMap<u32, String> {
+    data.lines()
+        .filter_map(|line| {
+            let mut fields = line.split(':');
+            let name = fields.next()?;
+            let uid = fields.nth(1)?.parse().ok()?;
+            Some((uid, name.to_string()))
+        })
+        .collect()
+}
+
+/// Reads the processes in a proc filesystem, whose CPU times are in `ticks` a
+/// second. Processes which exit while they're being read are skipped.
+pub fn read_processes(root: &Path, users: &HashMap<u32, String>, ticks: f64) -> Vec<Process> {
+    // Seconds since boot.
+    let uptime: f64 = std::fs::read_to_string(root.join("uptime"))
+        .ok()
+        .and_then(|u| u.split_whitespace().next()?.parse().ok())
+        .unwrap_or(0.);
+
+    let Ok(entries) = std::fs::read_dir(root) else {
+        return vec![];
+    };
+    let mut processes: Vec<Process> = entries
+        .flatten()
+        .filter_map(|entry| {
+            let pid: u32 = entry.file_name().to_str()?.parse().ok()?;
+            let dir = entry.path();
+            let stat = parse_stat(&std::fs::read_to_string(dir.join("stat")).ok()?)?;
+            let (uid, rss) = parse_status(&std::fs::read_to_string(dir.join("status")).ok()?)?;
+            let cmdline = parse_cmdline(&std::fs::read(dir.join("cmdline")).unwrap_or_default());
+
+            let running = uptime - stat.start_ticks as f64 / ticks;
+            let cpu = if running > 0. {
+                (stat.cpu_ticks as f64 / ticks / running * 100.) as f32
+            } else {
+                0.
+            };
+            Some(Process {
+                pid,
+                name: stat.name,
+                cmdline,
+                uid,
+                user: users.get(&uid).cloned().unwrap_or(uid.to_string()),
+                cpu,
+                rss,
+            })
+        })
+        .collect();
+    processes.sort_by_key(|p| p.pid);
+    processes
+}
+
+/// Reads the real uid of the process from a proc filesystem.
+fn owner(root: &Path, pid: &str) -> Option<u32> {
+    let status = std::fs::read_to_string(root.join(pid).join("status")).ok()?;
+    parse_status(&status).map(|(uid, _)| uid)
+}
+
+/// Sends the signal to the process if it still belongs to the user, as its PID
+/// may have been reused since it was chosen.
+fn send(root: &Path, pid: u32, signal: Signal) -> Result<(), String> {
+    let owner = owner(root, &pid.to_string()).ok_or("the process has exited")?;
+    if Some(owner) != self::owner(root, "self") {
+        return Err("the process belongs to another user".into());
+    }
+    // SAFETY: kill has no preconditions.
+    if unsafe { libc::kill(pid as libc::pid_t, signal.number()) } != 0 {
+        return Err(std::io::Error::last_os_error().to_string());
+    }
+    Ok(())
+}
+
+/// Sends the signal to the process, if it's still the user's.
+pub fn signal(pid: u32, signal: Signal, quit: bool) {
+    if let Err(e) = send(Path::new("/proc"), pid, signal) {
+        eprintln!("failed to send {} to {}: {}", signal.name(), pid, e);
+    }
+    if quit {
+        std::process::exit(0);
+    }
+}
+
+fn format_memory(kib: u64) -> String {
+    match kib {
+        0..=1023 => format!("{} KiB", kib),
+        1024..=1048575 => format!("{:.1} MiB", kib as f64 / 1024.),
+        _ => format!("{:.1} GiB", kib as f64 / 1048576.),
+    }
+}
+
+/// Finds running processes with `kill …` and sends them signals. Choosing a
+/// process lists the signals, and choosing a signal asks for confirmation
+/// before it's sent.
+pub struct ProcessProvider {
+    root: PathBuf,
+    passwd: PathBuf,
+    /// Our real uid. Only processes with the same owner are signalled.
+    uid: Option<u32>,
+    /// Clock ticks a second.
+    ticks: f64,
+}
+
+impl ProcessProvider {
+    /// Reads processes from the proc filesystem at `root`, usually `/proc`,
+    /// naming their owners from the `passwd` file.
+    pub fn new(root: PathBuf, passwd: PathBuf) -> Self {
+        let uid = owner(&root, "self");
+        Self {
+            root,
+            passwd,
+            uid,
+            ticks: clock_ticks(),
+        }
+    }
+
+    fn processes(&self) -> Vec<Process> {
+        let users = std::fs::read_to_string(&self.passwd)
+            .map(|p| parse_passwd(&p))
+            .unwrap_or_default();
+        read_processes(&self.root, &users, self.ticks)
+    }
+
+    fn owns(&self, process: &Process) -> bool {
+        self.uid == Some(process.uid)
+    }
+
+    /// Returns an item explaining why the process can't be signalled, if it can't.
+    fn refusal(&self, process: Option<&Process>, pid: u32) -> Option<ResultItem> {
+        let title = match process {
+            None => format!("No process with PID {}", pid),
+            Some(p) if !self.owns(p) => {
+                format!("{} ({}) belongs to {}", p.name, p.pid, p.user)
+            }
+            Some(_) => return None,
+        };
+        Some(ResultItem {
+            title,
+            subtitle: Some("Only your own processes can be signalled".into()),
+            glyph: "🔒".into(),
+            score: 0,
+            action: Action::SetInput(format!("{} ", PREFIX)),
+            alt_action: None,
+        })
+    }
+
+    fn search(&self, matcher: &SkimMatcherV2, query: &str) -> Vec<ResultItem> {
+        let processes = self.processes();
+        let mut scored: Vec<(i64, &Process)> = if query.is_empty() {
+            // Without a query, the busiest processes are listed first.
+            let mut busiest: Vec<&Process> = processes.iter().collect();
+            busiest.sort_by(|a, b| b.cpu.total_cmp(&a.cpu));
+            busiest
+                .into_iter()
+                .enumerate()
+                .map(|(i, p)| (-(i as i64), p))
+                .collect()
+        } else {
+            processes
+                .iter()
+                .filter_map(|p| {
+                    let score = [
+                        matcher.fuzzy_match(&p.name, query),
+                        matcher.fuzzy_match(&p.cmdline.join(" "), query),
+                    ]
+                    .into_iter()
+                    .flatten()
+                    .max()?;
+                    Some((score, p))
+                })
+                .collect()
+        };
+        scored.sort_by_key(|s| std::cmp::Reverse(s.0));
+        scored.truncate(MAX_RESULTS);
+
+        scored
+            .into_iter()
+            .map(|(score, p)| {
+                let mut subtitle = format!(
+                    "PID {} · {} · {:.1}% CPU · {}",
+                    p.pid,
+                    p.user,
+                    p.cpu,
+                    format_memory(p.rss)
+                );
+                if !p.cmdline.is_empty() {
+                    subtitle += &format!(" · {}", p.cmdline.join(" "));
+                }
+                ResultItem {
+                    title: p.name.clone(),
+                    subtitle: Some(subtitle),
+                    glyph: if self.owns(p) { "⚙" } else { "🔒" }.into(),
+                    score,
+                    action: Action::SetInput(format!("{} {} ", PREFIX, p.pid)),
+                    alt_action: None,
+                }
+            })
+            .collect()
+    }
+
+    /// Lists the signals which can be sent to the process.
+    fn signals(&self, pid: u32) -> Vec<ResultItem> {
+        let processes = self.processes();
+        let process = processes.iter().find(|p| p.pid == pid);
+        if let Some(refusal) = self.refusal(process, pid) {
+            return vec![refusal];
+        }
+        let p = process.unwrap();
+
+        Signal::ALL
+            .into_iter()
+            .enumerate()
+            .map(|(i, signal)| ResultItem {
+                title: format!("{} {} ({})", signal.name(), p.name, p.pid),
+                subtitle: Some(signal.description().into()),
+                glyph: "⚡".into(),
+                score: -(i as i64),
+                action: Action::SetInput(format!("{} {} {}", PREFIX, p.pid, signal.name())),
+                alt_action: None,
+            })
+            .collect()
+    }
+
+    /// Asks for confirmation before sending the signal.
+    fn confirm(&self, pid: u32, signal: Signal) -> Vec<ResultItem> {
+        let processes = self.processes();
+        let process = processes.iter().find(|p| p.pid == pid);
+        if let Some(refusal) = self.refusal(process, pid) {
+            return vec![refusal];
+        }
+        let p = process.unwrap();
+
+        vec![ResultItem {
+            title: format!("Send {} to {} ({})?", signal.name(), p.name, p.pid),
+            subtitle: Some("Press enter to confirm".into()),
+            glyph: "⚠".into(),
+            score: 0,
+            action: Action::Signal(p.pid, signal),
+            alt_action: None,
+        }]
+    }
+}
+
+impl Provider for ProcessProvider {
+    fn name(&self) -> &'static str {
+        "processes"
+    }
+
+    fn exclusive(&self, input: &str) -> bool {
+        let input = input.trim_start();
+        input == PREFIX || input.starts_with(&format!("{} ", PREFIX))
+    }
+
+    fn query(&mut self, matcher: &SkimMatcherV2, input: &str, _cancel: &Cancel) -> Vec<ResultItem> {
+        if !self.exclusive(input) {
+            return vec![];
+        }
+        let query = input.trim()[PREFIX.len()..].trim();
+
+        let words: Vec<&str> = query.split_whitespace().collect();
+        match words[..] {
+            [pid, signal] => match (pid.parse(), Signal::parse(signal)) {
+                (Ok(pid), Some(signal)) => self.confirm(pid, signal),
+                _ => self.search(matcher, query),
+            },
+            [pid] => match pid.parse() {
+                Ok(pid) => self.signals(pid),
+                Err(_) => self.search(matcher, query),
+            },
+            _ => self.search(matcher, query),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn testdata() -> ProcessProvider {
+        let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("testdata/processes");
+        ProcessProvider {
+            // The CPU times in the test data are in 100ths of a second.
+            ticks: 100.,
+            ..ProcessProvider::new(dir.join("proc"), dir.join("passwd"))
+        }
+    }
+
+    #[test]
+    fn stat() {
+        assert_eq!(
+            parse_stat("42 (Web Content) S 1 42 42 0 -1 4194560 100 0 0 0 250 50 0 0 20 0 30 0 5000 1000 300 18446744073709551615"),
+            Some(Stat {
+                name: "Web Content".into(),
+                cpu_ticks: 300,
+                start_ticks: 5000,
+            })
+        );
+        // Names may contain parentheses.
+        assert_eq!(
+            parse_stat("7 (a) (b)) R 1 7 7 0 -1 0 0 0 0 0 1 2 0 0 20 0 1 0 9 0 0")
+                .unwrap()
+                .name,
+            "a) (b)"
+        );
+        assert_eq!(parse_stat("7 (short) R 1"), None);
+    }
+
+    #[test]
+    fn status() {
+        assert_eq!(
+            parse_status("Name:\tbash\nUid:\t1000\t1000\t1000\t1000\nVmRSS:\t    5120 kB\n"),
+            Some((1000, 5120))
+        );
+        assert_eq!(
+            parse_status("Name:\tkthreadd\nUid:\t0\t0\t0\t0\n"),
+            Some((0, 0))
+        );
+        assert_eq!(parse_status("Name:\tbash\n"), None);
+    }
+
+    #[test]
+    fn processes() {
+        let p = testdata();
+        assert_eq!(p.uid, Some(1000));
+
+        let processes = p.processes();
+        assert_eq!(
+            processes.iter().map(|p| p.pid).collect::<Vec<_>>(),
+            vec![1, 2, 1234, 4321]
+        );
+        assert_eq!(
+            processes[2],
+            Process {
+                pid: 1234,
+                name: "firefox".into(),
+                cmdline: vec!["/usr/lib/firefox/firefox".into(), "--new-window".into()],
+                uid: 1000,
+                user: "alice".into(),
+                cpu: 25.,
+                rss: 307200,
+            }
+        );
+        assert!(processes[1].cmdline.is_empty());
+        assert_eq!(processes[1].user, "root");
+        assert_eq!(processes[3].user, "1001");
+    }
+
+    #[test]
+    fn query() {
+        let mut p = testdata();
+        let matcher = SkimMatcherV2::default();
+
+        assert!(p.query(&matcher, "firefox", &Cancel::default()).is_empty());
+        assert!(p.exclusive("kill fire"));
+        assert!(!p.exclusive("killer"));
+
+        let results = p.query(&matcher, "kill fire", &Cancel::default());
+        assert_eq!(results[0].title, "firefox");
+        assert_eq!(
+            results[0].subtitle.as_deref(),
+            Some(
+                "PID 1234 · alice · 25.0% CPU · 300.0 MiB · /usr/lib/firefox/firefox --new-window"
+            )
+        );
+        assert_eq!(results[0].action, Action::SetInput("kill 1234 ".into()));
+
+        // Matches the command line too.
+        assert_eq!(
+            p.query(&matcher, "kill new-window", &Cancel::default())[0].title,
+            "firefox"
+        );
+        // Busiest first without a query.
+        assert_eq!(
+            p.query(&matcher, "kill", &Cancel::default())[0].title,
+            "firefox"
+        );
+
+        let results = p.query(&matcher, "kill 1234 ", &Cancel::default());
+        assert_eq!(
+            results.iter().map(|r| r.title.as_str()).collect::<Vec<_>>(),
+            vec![
+                "TERM firefox (1234)",
+                "KILL firefox (1234)",
+                "STOP firefox (1234)",
+                "CONT firefox (1234)"
+            ]
+        );
+        assert_eq!(results[1].action, Action::SetInput("kill 1234 KILL".into()));
+
+        let results = p.query(&matcher, "kill 1234 sigkill", &Cancel::default());
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0].action, Action::Signal(1234, Signal::Kill));
+    }
+
+    #[test]
+    fn refuses_others() {
+        let mut p = testdata();
+        let matcher = SkimMatcherV2::default();
+
+        for input in ["kill 1", "kill 1 TERM", "kill 4321 KILL", "kill 99 TERM"] {
+            let results = p.query(&matcher, input, &Cancel::default());
+            assert_eq!(results.len(), 1, "{}", input);
+            assert_eq!(results[0].glyph, "🔒", "{}", input);
+            assert!(
+                !matches!(results[0].action, Action::Signal(..)),
+                "{}",
+                input
+            );
+        }
+        assert_eq!(
+            p.query(&matcher, "kill 1", &Cancel::default())[0].title,
+            "systemd (1) belongs to root"
+        );
+
+        // Ownership is checked again when the signal is sent.
+        let root = &p.root;
+        assert_eq!(
+            send(root, 1, Signal::Term),
+            Err("the process belongs to another user".into())
+        );
+        assert_eq!(
+            send(root, 99, Signal::Term),
+            Err("the process has exited".into())
+        );
+    }
+}
@@ -0,0 +1,154 @@
+use fuzzy_matcher::skim::SkimMatcherV2;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+/// A source of results other than the installed applications, such as files.
+/// Queries run on a thread of their own, so they may take a while.
+pub trait Provider: Send {
+    /// Short name used to select the provider from the command line.
+    fn name(&self) -> &'static str;
+
+    /// Returns the results matching the input, in no particular order. A slow
+    /// provider may give up early once the query is cancelled, as whatever it
+    /// returns then is dropped.
+    fn query(&mut self, matcher: &SkimMatcherV2, input: &str, cancel: &Cancel) -> Vec<ResultItem>;
+
+    /// Returns whether the input is meant only for this provider, such as a
+    /// prefix or keyword, hiding results from everything else.
+    fn exclusive(&self, _input: &str) -> bool {
+        false
+    }
+
+    /// Returns results to show when nothing else matches the input.
+    fn fallback(&mut self, _input: &str) -> Vec<ResultItem> {
+        vec![]
+    }
+
+    /// Blocks until any background loading has finished, so queries return
+    /// complete results.
+    fn wait_ready(&self) {}
+
+    /// How long a query may take before the launcher stops waiting for it,
+    /// going without this provider's results for that input.
+    fn budget(&self) -> Duration {
+        Duration::from_millis(500)
+    }
+}
+
+/// Set once the input a query was for has changed, so it's no longer wanted.
+#[derive(Debug, Clone, Default)]
+pub struct Cancel(Arc<AtomicBool>);
+
+impl Cancel {
+    pub fn cancel(&self) {
+        self.0.store(true, Ordering::Relaxed);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.0.load(Ordering::Relaxed)
+    }
+}
+
+/// What happens when a result is chosen.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Action {
+    /// Open a file or URL with its default application.
+    Open(String),
+    /// Open a file or URL with the default application for the MIME type.
+    #[cfg(not(target_arch = "wasm32"))]
+    OpenAs(String, String),
+    /// Show a file in the file manager, selected if possible.
+    Reveal(PathBuf),
+    /// Print the text to stdout, for scripts using the launcher as a chooser.
+    Print(String),
+    /// Open a URL with the opener.
+    OpenUrl(String, Opener),
+    /// Run the program, given first, with the arguments following it.
+    Spawn(Vec<String>),
+    /// Copy the text to the clipboard.
+    Copy(String),
+    /// Replace the input with the text, keeping the launcher open.
+    SetInput(String),
+    /// Send the signal to the process with the PID, if it's still the user's.
+    #[cfg(not(target_arch = "wasm32"))]
+    Signal(u32, crate::processes::Signal),
+    /// Run the action once the seconds have passed, unless it's cancelled
+    /// first, showing the text & the time left meanwhile.
+    Countdown(String, u64, Box<Action>),
+}
+
+impl Action {
+    pub fn run(&self, quit: bool) {
+        match self {
+            Action::Open(target) => crate::sys_apps::open(target, quit),
+            #[cfg(not(target_arch = "wasm32"))]
+            Action::OpenAs(target, mime) => crate::sys_apps::open_as(target, Some(mime), quit),
+            Action::Reveal(path) => crate::sys_apps::reveal(path, quit),
+            Action::Print(text) => crate::dmenu::print_and_exit(text),
+            Action::OpenUrl(url, opener) => opener.open(url, quit),
+            Action::Spawn(command) => crate::sys_apps::spawn(command, quit),
+            Action::Copy(text) => crate::sys_apps::copy(text, quit),
+            // Only the launcher can change its input, so it handles this itself.
+            Action::SetInput(_) => {}
+            #[cfg(not(target_arch = "wasm32"))]
+            Action::Signal(pid, signal) => crate::processes::signal(*pid, *signal, quit),
+            // Only the launcher can show the countdown, so elsewhere there's no waiting.
+            Action::Countdown(_, _, action) => action.run(quit),
+        }
+    }
+}
+
+/// Opens URLs. The default opens them with the desktop entry of the default
+/// browser; tests substitute their own to see what would be opened.
+#[derive(Clone)]
+pub struct Opener(Arc<OpenFn>);
+
+type OpenFn = dyn Fn(&str, bool) + Send + Sync;
+
+impl Opener {
+    pub fn new(f: impl Fn(&str, bool) + Send + Sync + 'static) -> Self {
+        Opener(Arc::new(f))
+    }
+
+    pub fn open(&self, url: &str, quit: bool) {
+        (self.0)(url, quit)
+    }
+}
+
+impl Default for Opener {
+    fn default() -> Self {
+        Opener::new(crate::sys_apps::open)
+    }
+}
+
+impl std::fmt::Debug for Opener {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("Opener")
+    }
+}
+
+impl PartialEq for Opener {
+    fn eq(&self, other: &Self) -> bool {
+        // Compare only the data pointers, as vtables may be duplicated.
+        Arc::as_ptr(&self.0) as *const () == Arc::as_ptr(&other.0) as *const ()
+    }
+}
+
+impl Eq for Opener {}
+
+/// A result from a provider, shown in the list below the matching applications.
+#[derive(Debug, Clone)]
+pub struct ResultItem {
+    pub title: String,
+    pub subtitle: Option<String>,
+    /// Shown in place of an icon, usually an emoji.
+    pub glyph: String,
+    pub score: i64,
+
+    /// Run on enter or click.
+    pub action: Action,
+    /// Run on alt+enter, if present.
+    pub alt_action: Option<Action>,
+}
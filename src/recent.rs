@@ -0,0 +1,498 @@
+use fuzzy_matcher::skim::SkimMatcherV2;
+use fuzzy_matcher::FuzzyMatcher;
+use quick_xml::events::{BytesStart, Event};
+use quick_xml::Reader;
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, SystemTime};
+
+use crate::provider::{Action, Cancel, Provider, ResultItem};
+
+/// How often the recently used file is checked for changes.
+const REFRESH_INTERVAL: Duration = Duration::from_secs(3);
+
+/// At most this many documents are listed.
+const MAX_RESULTS: usize = 10;
+
+/// Queries shorter than this don't search documents, as they'd match almost everything.
+const MIN_QUERY_LEN: usize = 2;
+
+/// Typing this lists the recent documents, most recently used first.
+const PREFIX: &str = "recent";
+
+/// An application which opened a document, from its bookmark metadata.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RecentApp {
+    pub name: String,
+    /// Command line, with `%u` or `%f` standing for the document.
+    pub exec: String,
+    /// When the application last opened the document, in seconds since the epoch.
+    pub modified: u64,
+    pub count: u32,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RecentDocument {
+    pub uri: String,
+    pub title: String,
+    pub mime_type: Option<String>,
+    pub applications: Vec<RecentApp>,
+    /// When the document was last used, in seconds since the epoch.
+    pub visited: u64,
+}
+
+impl RecentDocument {
+    /// Returns the path of a local document.
+    pub fn path(&self) -> Option<PathBuf> {
+        self.uri
+            .strip_prefix("file://")
+            .map(|p| PathBuf::from(crate::percent::decode(p)))
+    }
+
+    /// Returns the application which opened the document most recently.
+    pub fn last_application(&self) -> Option<&RecentApp> {
+        self.applications.iter().max_by_key(|a| a.modified)
+    }
+
+    /// Returns the command which opens the document with the application.
+    pub fn command(&self, app: &RecentApp) -> Option<Vec<String>> {
+        let mut words = shell_words::split(&app.exec).ok()?;
+        // GLib writes the command line quoted as a single word.
+        if let [word] = words.as_slice() {
+            if word.contains(' ') {
+                words = shell_words::split(word).ok()?;
+            }
+        }
+
+        let path = self.path().map(|p| p.to_string_lossy().to_string());
+        let command: Vec<String> = words
+            .into_iter()
+            .filter_map(|w| match w.as_str() {
+                "%u" | "%U" => Some(self.uri.clone()),
+                "%f" | "%F" => Some(path.clone().unwrap_or_else(|| self.uri.clone())),
+                w if w.starts_with('%') => None,
+                w => Some(w.to_string()),
+            })
+            .collect();
+        Some(command).filter(|c| !c.is_empty())
+    }
+
+    fn glyph(&self) -> &'static str {
+        match self.mime_type.as_deref().and_then(|m| m.split_once('/')) {
+            Some(("image", _)) => "🖼",
+            Some(("audio", _)) => "🎵",
+            Some(("video", _)) => "🎞",
+            Some(("text", _)) => "📝",
+            Some(("inode", "directory")) => "📁",
+            _ => "📄",
+        }
+    }
+}
+
+/// Returns where GTK & KDE applications record the documents they open.
+pub fn default_path() -> Option<PathBuf> {
+    let dir = match std::env::var_os("XDG_DATA_HOME") {
+        Some(dir) if !dir.is_empty() => PathBuf::from(dir),
+        _ => PathBuf::from(std::env::var_os("HOME")?).join(".local/share"),
+    };
+    Some(dir.join("recently-used.xbel"))
+}
+
+/// Parses an XBEL timestamp like `2023-10-01T12:30:00.123456Z` into seconds
+/// since the epoch.
+fn parse_timestamp(s: &str) -> Option<u64> {
+    let time = chrono::DateTime::parse_from_rfc3339(s).ok()?;
+    u64::try_from(time.timestamp()).ok()
+}
+
+/// Returns the element's attributes by name, unescaped, skipping malformed ones.
+fn attributes(element: &BytesStart<'_>) -> HashMap<String, String> {
+    element
+        .attributes()
+        .flatten()
+        .filter_map(|a| {
+            let key = std::str::from_utf8(a.key.as_ref()).ok()?.to_string();
+            Some((key, a.unescape_value().ok()?.into_owned()))
+        })
+        .collect()
+}
+
+/// Reads the documents from an XBEL file, most recently used first. A file
+/// which goes wrong part way, such as while it's rewritten, gives the documents
+/// before that.
+pub fn parse_xbel(data: &str) -> Vec<RecentDocument> {
+    let time = |attrs: &HashMap<String, String>, key: &str| {
+        attrs.get(key).and_then(|t| parse_timestamp(t)).unwrap_or(0)
+    };
+
+    let mut reader = Reader::from_str(data);
+    reader.trim_text(true).expand_empty_elements(true);
+
+    let mut documents = vec![];
+    let mut current: Option<RecentDocument> = None;
+    let mut in_title = false;
+    loop {
+        match reader.read_event() {
+            Ok(Event::Start(e)) => match e.name().as_ref() {
+                b"bookmark" => {
+                    let attrs = attributes(&e);
+                    current = attrs.get("href").map(|href| RecentDocument {
+                        uri: href.clone(),
+                        title: String::new(),
+                        mime_type: None,
+                        applications: vec![],
+                        visited: time(&attrs, "visited").max(time(&attrs, "modified")),
+                    });
+                }
+                b"title" => in_title = true,
+                _ => {
+                    let Some(doc) = current.as_mut() else {
+                        continue;
+                    };
+                    let attrs = attributes(&e);
+                    // Metadata elements are namespaced, like `mime:mime-type`.
+                    match e.local_name().as_ref() {
+                        b"mime-type" => doc.mime_type = attrs.get("type").cloned(),
+                        b"application" => doc.applications.push(RecentApp {
+                            name: attrs.get("name").cloned().unwrap_or_default(),
+                            exec: attrs.get("exec").cloned().unwrap_or_default(),
+                            modified: time(&attrs, "modified"),
+                            count: attrs.get("count").and_then(|c| c.parse().ok()).unwrap_or(1),
+                        }),
+                        _ => {}
+                    }
+                }
+            },
+            Ok(Event::End(e)) => match e.name().as_ref() {
+                b"bookmark" => documents.extend(current.take()),
+                b"title" => in_title = false,
+                _ => {}
+            },
+            Ok(Event::Text(text)) if in_title => {
+                if let (Some(doc), Ok(text)) = (current.as_mut(), text.unescape()) {
+                    doc.title = text.into_owned();
+                }
+            }
+            Ok(Event::CData(text)) if in_title => {
+                if let Some(doc) = current.as_mut() {
+                    doc.title = String::from_utf8_lossy(&text).to_string();
+                }
+            }
+            Ok(Event::Eof) | Err(_) => break,
+            Ok(_) => {}
+        }
+    }
+
+    for doc in documents.iter_mut() {
+        // Opening a document updates the application's time, but not always the visit.
+        if let Some(app) = doc.last_application() {
+            doc.visited = doc.visited.max(app.modified);
+        }
+        if doc.title.is_empty() {
+            doc.title = match doc.path() {
+                Some(path) => path
+                    .file_name()
+                    .map(|n| n.to_string_lossy().to_string())
+                    .unwrap_or_default(),
+                None => doc.uri.clone(),
+            };
+        }
+    }
+    documents.sort_by_key(|d| std::cmp::Reverse(d.visited));
+    documents
+}
+
+/// Reads the recently used file when it changes.
+struct Index {
+    path: PathBuf,
+    stamp: Option<(SystemTime, u64)>,
+}
+
+impl Index {
+    fn new(path: PathBuf) -> Self {
+        Self { path, stamp: None }
+    }
+
+    /// Returns the documents if the file has changed since it was last read,
+    /// leaving out local documents which no longer exist.
+    fn refresh(&mut self) -> Option<Vec<RecentDocument>> {
+        let stamp = std::fs::metadata(&self.path)
+            .ok()
+            .and_then(|m| Some((m.modified().ok()?, m.len())));
+        if stamp == self.stamp {
+            return None;
+        }
+        self.stamp = stamp;
+
+        let data = std::fs::read_to_string(&self.path).unwrap_or_default();
+        let mut documents = parse_xbel(&data);
+        documents.retain(|d| d.path().filter(|p| !p.exists()).is_none());
+        Some(documents)
+    }
+}
+
+/// Lists the documents recorded in `recently-used.xbel`, opening them with the
+/// application which last did.
+pub struct RecentProvider {
+    documents: Arc<Mutex<Arc<Vec<RecentDocument>>>>,
+    loaded: Arc<AtomicBool>,
+}
+
+impl RecentProvider {
+    /// Reads the recently used file in the background, rereading it when it changes.
+    pub fn new(path: PathBuf) -> Self {
+        let documents = Arc::new(Mutex::new(Arc::new(vec![])));
+        let documents2 = documents.clone();
+        let loaded = Arc::new(AtomicBool::new(false));
+        let loaded2 = loaded.clone();
+
+        std::thread::spawn(move || {
+            let mut index = Index::new(path);
+            loop {
+                if let Some(docs) = index.refresh() {
+                    *documents2.lock().unwrap() = Arc::new(docs);
+                }
+                loaded2.store(true, Ordering::SeqCst);
+                std::thread::sleep(REFRESH_INTERVAL);
+            }
+        });
+
+        Self { documents, loaded }
+    }
+}
+
+/// Scores a document against the query, preferring matches on the title.
+fn score(matcher: &SkimMatcherV2, d: &RecentDocument, query: &str) -> Option<i64> {
+    [
+        matcher.fuzzy_match(&d.title, query).map(|s| s * 2),
+        matcher.fuzzy_match(&d.uri, query),
+    ]
+    .into_iter()
+    .flatten()
+    .max()
+}
+
+fn result(d: &RecentDocument, score: i64) -> ResultItem {
+    let path = d.path();
+    let location = match &path {
+        Some(p) => p.to_string_lossy().to_string(),
+        None => d.uri.clone(),
+    };
+    let app = d.last_application();
+
+    ResultItem {
+        title: d.title.clone(),
+        subtitle: Some(match app {
+            Some(app) if !app.name.is_empty() => format!("{} · {}", app.name, location),
+            _ => location,
+        }),
+        glyph: d.glyph().into(),
+        score,
+        action: match (app.and_then(|app| d.command(app)), &d.mime_type) {
+            (Some(command), _) => Action::Spawn(command),
+            // The type's already known, so isn't looked up again from the file.
+            (None, Some(mime)) => Action::OpenAs(d.uri.clone(), mime.clone()),
+            (None, None) => Action::Open(d.uri.clone()),
+        },
+        alt_action: path.map(Action::Reveal),
+    }
+}
+
+impl Provider for RecentProvider {
+    fn name(&self) -> &'static str {
+        "recent"
+    }
+
+    fn wait_ready(&self) {
+        while !self.loaded.load(Ordering::SeqCst) {
+            std::thread::sleep(Duration::from_millis(10));
+        }
+    }
+
+    fn exclusive(&self, input: &str) -> bool {
+        let input = input.trim_start();
+        input == PREFIX || input.starts_with(&format!("{} ", PREFIX))
+    }
+
+    fn query(&mut self, matcher: &SkimMatcherV2, input: &str, _cancel: &Cancel) -> Vec<ResultItem> {
+        let documents = self.documents.lock().unwrap().clone();
+
+        // With the prefix, matching documents are listed most recently used first.
+        if self.exclusive(input) {
+            let query = input.trim()[PREFIX.len()..].trim();
+            return documents
+                .iter()
+                .filter(|d| query.is_empty() || score(matcher, d, query).is_some())
+                .take(MAX_RESULTS)
+                .enumerate()
+                .map(|(i, d)| result(d, -(i as i64)))
+                .collect();
+        }
+
+        let query = input.trim();
+        if query.len() < MIN_QUERY_LEN {
+            return vec![];
+        }
+
+        let mut scored: Vec<(i64, &RecentDocument)> = documents
+            .iter()
+            .filter_map(|d| score(matcher, d, query).map(|s| (s, d)))
+            .collect();
+        // Documents are already sorted by visit, so this keeps recent ones first on ties.
+        scored.sort_by_key(|s| std::cmp::Reverse(s.0));
+        scored.truncate(MAX_RESULTS);
+
+        scored.into_iter().map(|(s, d)| result(d, s)).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::Path;
+
+    fn testdata() -> PathBuf {
+        Path::new(env!("CARGO_MANIFEST_DIR")).join("testdata/recent/recently-used.xbel")
+    }
+
+    #[test]
+    fn timestamps() {
+        assert_eq!(parse_timestamp("1970-01-01T00:00:00Z"), Some(0));
+        assert_eq!(
+            parse_timestamp("2023-10-01T12:30:15.123456Z"),
+            Some(1696163415)
+        );
+        assert_eq!(parse_timestamp("2024-02-29T00:00:00Z"), Some(1709164800));
+        assert_eq!(parse_timestamp("yesterday"), None);
+    }
+
+    #[test]
+    fn xml() {
+        let docs = parse_xbel(
+            "<?xml version=\"1.0\"?><!-- c --><xbel xmlns:mime=\"m\">\
+             <bookmark href='file:///tmp/a%20%26%20b.txt' visited=\"2023-01-01T00:00:00Z\">\
+             <title><![CDATA[<A> & B]]></title><info><metadata>\
+             <mime:mime-type type=\"text/plain\"/></metadata></info></bookmark>\
+             <bookmark href=\"file:///tmp/c.txt\"><title>C &amp; &#x44;</title></bookmark>\
+             <bookmark href=\"file:///tmp/broken.txt\"></xbel>",
+        );
+        let titles: Vec<&str> = docs.iter().map(|d| d.title.as_str()).collect();
+        assert_eq!(titles, vec!["<A> & B", "C & D"]);
+        assert_eq!(docs[0].path(), Some(PathBuf::from("/tmp/a & b.txt")));
+        assert_eq!(docs[0].mime_type.as_deref(), Some("text/plain"));
+        assert_eq!(docs[0].visited, 1672531200);
+    }
+
+    #[test]
+    fn xbel() {
+        let docs = parse_xbel(&std::fs::read_to_string(testdata()).unwrap());
+        let titles: Vec<&str> = docs.iter().map(|d| d.title.as_str()).collect();
+        assert_eq!(
+            titles,
+            vec![
+                "Quarterly Report.pdf",
+                "Trip photos",
+                "notes.md",
+                "sftp://example.com/srv/log.txt"
+            ]
+        );
+
+        let report = &docs[0];
+        assert_eq!(report.mime_type.as_deref(), Some("application/pdf"));
+        assert_eq!(
+            report.path(),
+            Some(PathBuf::from("/home/user/Documents/Quarterly Report.pdf"))
+        );
+        assert_eq!(report.applications.len(), 2);
+        let app = report.last_application().unwrap();
+        assert_eq!(app.name, "Okular");
+        assert_eq!(app.count, 2);
+        assert_eq!(
+            report.command(app),
+            Some(vec![
+                "okular".to_string(),
+                "/home/user/Documents/Quarterly Report.pdf".to_string()
+            ])
+        );
+
+        let notes = &docs[2];
+        assert_eq!(
+            notes.command(notes.last_application().unwrap()),
+            Some(vec![
+                "gnome-text-editor".to_string(),
+                "file:///home/user/notes.md".to_string()
+            ])
+        );
+        assert_eq!(docs[3].path(), None);
+    }
+
+    #[test]
+    fn query() {
+        let dir =
+            std::env::temp_dir().join(format!("liquid-launcher-recent-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        for name in ["alpha.txt", "beta.txt"] {
+            std::fs::write(dir.join(name), "").unwrap();
+        }
+        let bookmark = |name: &str, visited: &str| {
+            format!(
+                "<bookmark href=\"file://{}/{}\" visited=\"{}\"><info><metadata>\
+                 <mime:mime-type type=\"text/plain\"/></metadata></info></bookmark>",
+                dir.display(),
+                name,
+                visited
+            )
+        };
+        let xbel = dir.join("recently-used.xbel");
+        std::fs::write(
+            &xbel,
+            format!(
+                "<xbel>{}{}{}</xbel>",
+                bookmark("alpha.txt", "2023-01-01T00:00:00Z"),
+                bookmark("beta.txt", "2023-06-01T00:00:00Z"),
+                bookmark("deleted.txt", "2023-07-01T00:00:00Z"),
+            ),
+        )
+        .unwrap();
+
+        let mut index = Index::new(xbel.clone());
+        assert_eq!(index.refresh().unwrap().len(), 2);
+        assert_eq!(index.refresh(), None);
+
+        let mut p = RecentProvider::new(xbel);
+        p.wait_ready();
+        let matcher = SkimMatcherV2::default();
+
+        assert!(p.exclusive("recent"));
+        let results = p.query(&matcher, "recent", &Cancel::default());
+        let titles: Vec<&str> = results.iter().map(|r| r.title.as_str()).collect();
+        assert_eq!(titles, vec!["beta.txt", "alpha.txt"]);
+        assert_eq!(results[0].glyph, "📝");
+        assert_eq!(
+            results[0].action,
+            Action::OpenAs(
+                format!("file://{}/beta.txt", dir.display()),
+                "text/plain".into()
+            )
+        );
+        assert_eq!(
+            results[0].alt_action,
+            Some(Action::Reveal(dir.join("beta.txt")))
+        );
+
+        assert_eq!(
+            p.query(&matcher, "recent beta", &Cancel::default()).len(),
+            1
+        );
+        assert_eq!(
+            p.query(&matcher, "alpha", &Cancel::default())[0].title,
+            "alpha.txt"
+        );
+        assert!(p.query(&matcher, "a", &Cancel::default()).is_empty());
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
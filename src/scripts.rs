@@ -0,0 +1,376 @@
+use std::cell::RefCell;
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+use std::time::{Duration, Instant, SystemTime};
+
+use regex::{Captures, Regex};
+use rhai::{Array, CallFnOptions, Dynamic, Engine, Map, Scope, AST};
+
+use crate::ext::{copy_row, copy_table, ImmediateExtra};
+
+/// Scripts running for more operations than this are stopped.
+const MAX_OPERATIONS: u64 = 100_000;
+
+/// How often the scripts directory is checked for changes.
+const RELOAD_INTERVAL: Duration = Duration::from_secs(1);
+
+/// What a script registered while it was loaded.
+#[derive(Default)]
+struct Registration {
+    title: Option<String>,
+    triggers: Vec<String>,
+}
+
+struct Script {
+    title: String,
+    triggers: Vec<Regex>,
+    ast: AST,
+}
+
+/// A row computed by a script.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Row {
+    pub label: String,
+    pub value: String,
+    /// Copied by the copy button.
+    pub copy: String,
+}
+
+/// The rows a script computed for an input, or what went wrong running it.
+struct Output {
+    title: String,
+    rows: Result<Vec<Row>, String>,
+}
+
+/// Path, modification time and length of a script file, to notice when it changes.
+type FileState = (PathBuf, Option<SystemTime>, u64);
+
+/// Shows the rows computed by the [Rhai](https://rhai.rs) scripts whose trigger
+/// matches the input, loaded from a directory & reloaded when they change.
+///
+/// A script registers the patterns it handles with `trigger`, and optionally
+/// the heading of its rows with `title`. Its `rows` function is then called
+/// with the input & the captures of the matching pattern, and returns maps of a
+/// `label`, a `value`, and optionally what to `copy` in place of the value:
+///
+/// ```rhai
+/// title("Unix time");
+/// trigger(`^@(\d+)$`);
+///
+/// fn rows(input, captures) {
+///     let secs = parse_int(captures[1]);
+///     [#{ label: "Days", value: secs / 86400 }]
+/// }
+/// ```
+pub struct ScriptExtra {
+    dir: PathBuf,
+    engine: Engine,
+    registration: Rc<RefCell<Registration>>,
+    scripts: Vec<Script>,
+    loaded: Vec<FileState>,
+    checked: Option<Instant>,
+    reload_interval: Duration,
+    /// Output for the last input, so the scripts run once per input rather than
+    /// every frame.
+    cached: Option<(String, Vec<Output>)>,
+}
+
+impl ScriptExtra {
+    pub fn new(dir: PathBuf) -> Self {
+        let registration = Rc::new(RefCell::new(Registration::default()));
+
+        let mut engine = Engine::new();
+        engine
+            .set_max_operations(MAX_OPERATIONS)
+            .set_max_call_levels(32)
+            .set_max_expr_depths(64, 32)
+            .set_max_string_size(64 * 1024)
+            .set_max_array_size(10_000)
+            .set_max_map_size(1_000);
+        engine.disable_symbol("eval");
+        // stdout may be read by whatever started the launcher, so keep it clean.
+        engine.on_print(|text| eprintln!("{}", text));
+        engine.on_debug(|text, source, pos| {
+            eprintln!("{} @ {}: {}", source.unwrap_or("script"), pos, text)
+        });
+
+        let r = registration.clone();
+        engine.register_fn("trigger", move |pattern: &str| {
+            r.borrow_mut().triggers.push(pattern.to_string())
+        });
+        let r = registration.clone();
+        engine.register_fn("title", move |title: &str| {
+            r.borrow_mut().title = Some(title.to_string())
+        });
+
+        Self {
+            dir,
+            engine,
+            registration,
+            scripts: vec![],
+            loaded: vec![],
+            checked: None,
+            reload_interval: RELOAD_INTERVAL,
+            cached: None,
+        }
+    }
+
+    fn scan(&self) -> Vec<FileState> {
+        let mut files: Vec<FileState> = std::fs::read_dir(&self.dir)
+            .into_iter()
+            .flatten()
+            .flatten()
+            .map(|entry| entry.path())
+            .filter(|path| path.extension().map_or(false, |ext| ext == "rhai"))
+            .map(|path| {
+                let meta = std::fs::metadata(&path).ok();
+                let modified = meta.as_ref().and_then(|m| m.modified().ok());
+                (path, modified, meta.map_or(0, |m| m.len()))
+            })
+            .collect();
+        files.sort();
+        files
+    }
+
+    /// Reloads the scripts if any have been added, changed or removed since they
+    /// were last loaded.
+    fn refresh(&mut self) {
+        if let Some(checked) = self.checked {
+            if checked.elapsed() < self.reload_interval {
+                return;
+            }
+        }
+        self.checked = Some(Instant::now());
+
+        let files = self.scan();
+        if files == self.loaded {
+            return;
+        }
+        self.scripts = files
+            .iter()
+            .filter_map(|(path, ..)| match self.load(path) {
+                Ok(script) => Some(script),
+                Err(e) => {
+                    eprintln!("failed to load script {}: {}", path.display(), e);
+                    None
+                }
+            })
+            .collect();
+        self.loaded = files;
+        self.cached = None;
+    }
+
+    fn load(&self, path: &Path) -> Result<Script, String> {
+        let source = std::fs::read_to_string(path).map_err(|e| e.to_string())?;
+        let ast = self.engine.compile(source).map_err(|e| e.to_string())?;
+
+        self.registration.take();
+        self.engine.run_ast(&ast).map_err(|e| e.to_string())?;
+        let registration = self.registration.take();
+
+        if registration.triggers.is_empty() {
+            return Err("no trigger was registered".into());
+        }
+        if !ast
+            .iter_functions()
+            .any(|f| f.name == "rows" && f.params.len() == 2)
+        {
+            return Err("rows(input, captures) isn't defined".into());
+        }
+        let triggers = registration
+            .triggers
+            .iter()
+            .map(|pattern| Regex::new(pattern).map_err(|e| e.to_string()))
+            .collect::<Result<_, _>>()?;
+
+        let title = registration.title.unwrap_or_else(|| {
+            path.file_stem()
+                .unwrap_or_default()
+                .to_string_lossy()
+                .to_string()
+        });
+        Ok(Script {
+            title,
+            triggers,
+            ast,
+        })
+    }
+
+    fn run(
+        &self,
+        script: &Script,
+        input: &str,
+        captures: Captures<'_>,
+    ) -> Result<Vec<Row>, String> {
+        let captures: Array = captures
+            .iter()
+            .map(|c| c.map_or("", |m| m.as_str()).to_string().into())
+            .collect();
+        // The top level was run when loading, and only registers the triggers.
+        let options = CallFnOptions::new().eval_ast(false);
+        let result: Dynamic = self
+            .engine
+            .call_fn_with_options(
+                options,
+                &mut Scope::new(),
+                &script.ast,
+                "rows",
+                (input.to_string(), captures),
+            )
+            .map_err(|e| e.to_string())?;
+        rows(result)
+    }
+
+    fn outputs(&mut self, input: &str) -> &[Output] {
+        self.refresh();
+        if !matches!(&self.cached, Some((cached, _)) if cached == input) {
+            let outputs = self
+                .scripts
+                .iter()
+                .filter_map(|script| {
+                    let captures = script.triggers.iter().find_map(|t| t.captures(input))?;
+                    Some(Output {
+                        title: script.title.clone(),
+                        rows: self.run(script, input, captures),
+                    })
+                })
+                .collect();
+            self.cached = Some((input.to_string(), outputs));
+        }
+        &self.cached.as_ref().unwrap().1
+    }
+}
+
+/// Reads the rows out of what `rows` returned.
+fn rows(result: Dynamic) -> Result<Vec<Row>, String> {
+    let items = result
+        .try_cast::<Array>()
+        .ok_or("rows must return an array")?;
+    items
+        .into_iter()
+        .map(|item| {
+            let mut map = item.try_cast::<Map>().ok_or("each row must be a map")?;
+            let mut field = |name: &str| map.remove(name).map(|v| v.to_string());
+            let label = field("label").unwrap_or_default();
+            let value = field("value").unwrap_or_default();
+            let copy = field("copy").unwrap_or_else(|| value.clone());
+            Ok(Row { label, value, copy })
+        })
+        .collect()
+}
+
+impl ImmediateExtra for ScriptExtra {
+    fn ui(&mut self, input: &String, _ctx: &egui::Context, ui: &mut egui::Ui) -> bool {
+        let outputs = self.outputs(input);
+        for (i, output) in outputs.iter().enumerate() {
+            egui::CollapsingHeader::new(&output.title)
+                .id_source(("script", i))
+                .default_open(true)
+                .show(ui, |ui| match &output.rows {
+                    Err(e) => {
+                        ui.colored_label(ui.visuals().error_fg_color, format!("✖ {}", e));
+                    }
+                    Ok(rows) => {
+                        ui.push_id(("script-rows", i), |ui| {
+                            let text_height = egui::TextStyle::Body.resolve(ui.style()).size;
+                            copy_table(ui, |body| {
+                                for r in rows {
+                                    let copy = Some(r.copy.clone());
+                                    copy_row(body, text_height, &r.label, copy, |ui| {
+                                        ui.label(&r.value);
+                                    });
+                                }
+                            });
+                        });
+                    }
+                });
+        }
+        !outputs.is_empty()
+    }
+
+    fn text(&mut self, input: &String) -> Vec<(String, String)> {
+        self.outputs(input)
+            .iter()
+            .flat_map(|output| match &output.rows {
+                Ok(rows) => rows
+                    .iter()
+                    .map(|r| (r.label.clone(), r.value.clone()))
+                    .collect(),
+                Err(e) => vec![(output.title.clone(), format!("Error: {}", e))],
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn testdata() -> ScriptExtra {
+        ScriptExtra::new(Path::new(env!("CARGO_MANIFEST_DIR")).join("testdata/scripts"))
+    }
+
+    #[test]
+    fn loads() {
+        let mut extra = testdata();
+        assert_eq!(
+            extra.text(&"@1700000000".to_string()),
+            vec![
+                ("Days".to_string(), "19675".to_string()),
+                ("Hex".to_string(), "0x6553f100".to_string()),
+            ]
+        );
+        // The broken scripts are skipped, without keeping the others from loading.
+        let titles: Vec<&str> = extra.scripts.iter().map(|s| s.title.as_str()).collect();
+        assert_eq!(titles, vec!["runaway", "Unix time"]);
+        assert!(extra.text(&"1700000000".to_string()).is_empty());
+
+        let rows = extra.outputs("@1700000000")[0].rows.clone().unwrap();
+        assert_eq!(rows[1].copy, "6553f100");
+        assert_eq!(rows[0].copy, "19675");
+    }
+
+    #[test]
+    fn operation_limit() {
+        let mut extra = testdata();
+        let text = extra.text(&"loop".to_string());
+        assert_eq!(text.len(), 1);
+        assert_eq!(text[0].0, "runaway");
+        assert!(text[0].1.contains("Too many operations"), "{:?}", text);
+    }
+
+    #[test]
+    fn reloads() {
+        let dir =
+            std::env::temp_dir().join(format!("liquid-launcher-scripts-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        let script = dir.join("greet.rhai");
+        let write = |greeting: &str| {
+            let source = format!(
+                r#"trigger(`^hi (\w+)$`); fn rows(input, captures) {{ [#{{ label: "{}", value: captures[1] }}] }}"#,
+                greeting
+            );
+            std::fs::write(&script, source).unwrap();
+        };
+
+        let mut extra = ScriptExtra::new(dir.clone());
+        extra.reload_interval = Duration::ZERO;
+        let input = "hi bob".to_string();
+        assert!(extra.text(&input).is_empty());
+
+        write("Hello");
+        assert_eq!(extra.text(&input), vec![("Hello".into(), "bob".into())]);
+
+        write("Good morning");
+        assert_eq!(
+            extra.text(&input),
+            vec![("Good morning".into(), "bob".into())]
+        );
+
+        std::fs::remove_file(&script).unwrap();
+        assert!(extra.text(&input).is_empty());
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
@@ -0,0 +1,261 @@
+use fuzzy_matcher::skim::SkimMatcherV2;
+use fuzzy_matcher::FuzzyMatcher;
+
+use crate::config::SessionConfig;
+use crate::provider::{Action, Cancel, Provider, ResultItem};
+
+/// Queries shorter than this aren't matched, as they'd match almost everything.
+const MIN_QUERY_LEN: usize = 2;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SessionAction {
+    Lock,
+    LogOut,
+    Suspend,
+    Hibernate,
+    Reboot,
+    PowerOff,
+}
+
+impl SessionAction {
+    pub const ALL: [SessionAction; 6] = [
+        SessionAction::Lock,
+        SessionAction::LogOut,
+        SessionAction::Suspend,
+        SessionAction::Hibernate,
+        SessionAction::Reboot,
+        SessionAction::PowerOff,
+    ];
+
+    fn title(self) -> &'static str {
+        match self {
+            SessionAction::Lock => "Lock",
+            SessionAction::LogOut => "Log out",
+            SessionAction::Suspend => "Suspend",
+            SessionAction::Hibernate => "Hibernate",
+            SessionAction::Reboot => "Reboot",
+            SessionAction::PowerOff => "Power off",
+        }
+    }
+
+    /// Other words the action is found by.
+    fn keywords(self) -> &'static str {
+        match self {
+            SessionAction::Lock => "screen",
+            SessionAction::LogOut => "logout sign out exit",
+            SessionAction::Suspend => "sleep",
+            SessionAction::Hibernate => "",
+            SessionAction::Reboot => "restart",
+            SessionAction::PowerOff => "poweroff shutdown shut down",
+        }
+    }
+
+    /// Shown while counting down to the action.
+    fn progressive(self) -> &'static str {
+        match self {
+            SessionAction::Lock => "Locking",
+            SessionAction::LogOut => "Logging out",
+            SessionAction::Suspend => "Suspending",
+            SessionAction::Hibernate => "Hibernating",
+            SessionAction::Reboot => "Rebooting",
+            SessionAction::PowerOff => "Powering off",
+        }
+    }
+
+    fn glyph(self) -> &'static str {
+        match self {
+            SessionAction::Lock => "🔒",
+            SessionAction::LogOut => "🚪",
+            SessionAction::Suspend => "💤",
+            SessionAction::Hibernate => "🛌",
+            SessionAction::Reboot => "🔄",
+            SessionAction::PowerOff => "🔌",
+        }
+    }
+
+    /// Whether the action closes the applications, losing unsaved work, so
+    /// needs confirming.
+    fn destructive(self) -> bool {
+        matches!(
+            self,
+            SessionAction::LogOut | SessionAction::Reboot | SessionAction::PowerOff
+        )
+    }
+
+    /// The input which asks to confirm the action, like `reboot?`.
+    fn confirmation(self) -> String {
+        format!("{}?", self.title().to_lowercase())
+    }
+}
+
+/// Lists Lock, Log out, Suspend, Hibernate, Reboot & Power off. Those which
+/// lose unsaved work ask for confirmation, then count down before running.
+pub struct SessionProvider {
+    commands: Vec<(SessionAction, Vec<String>)>,
+    /// Seconds to wait before running destructive actions.
+    countdown: u64,
+}
+
+impl SessionProvider {
+    pub fn new(config: &SessionConfig) -> Self {
+        let commands = SessionAction::ALL
+            .into_iter()
+            .map(|action| {
+                // The config checks the commands split.
+                let command = shell_words::split(config.command(action)).unwrap_or_default();
+                (action, command)
+            })
+            .collect();
+        Self {
+            commands,
+            countdown: config.countdown,
+        }
+    }
+
+    fn result(&self, action: SessionAction, command: &[String], score: i64) -> ResultItem {
+        ResultItem {
+            title: action.title().into(),
+            subtitle: Some(shell_words::join(command)),
+            glyph: action.glyph().into(),
+            score,
+            action: match action.destructive() {
+                true => Action::SetInput(action.confirmation()),
+                false => Action::Spawn(command.to_vec()),
+            },
+            alt_action: None,
+        }
+    }
+
+    /// Asks to confirm the action, which runs after the countdown.
+    fn confirm(&self, action: SessionAction, command: &[String]) -> ResultItem {
+        let run = Action::Spawn(command.to_vec());
+        let (subtitle, run) = match self.countdown {
+            0 => ("Press enter to confirm".to_string(), run),
+            seconds => (
+                format!(
+                    "Press enter to confirm, then escape within {} seconds to cancel",
+                    seconds
+                ),
+                Action::Countdown(action.progressive().into(), seconds, Box::new(run)),
+            ),
+        };
+        ResultItem {
+            title: format!("{} now?", action.title()),
+            subtitle: Some(subtitle),
+            glyph: "⚠".into(),
+            score: 0,
+            action: run,
+            alt_action: None,
+        }
+    }
+
+    /// Returns the destructive action the input asks to confirm.
+    fn confirming(&self, input: &str) -> Option<&(SessionAction, Vec<String>)> {
+        let input = input.trim().to_lowercase();
+        self.commands
+            .iter()
+            .find(|(action, _)| action.destructive() && action.confirmation() == input)
+    }
+}
+
+impl Provider for SessionProvider {
+    fn name(&self) -> &'static str {
+        "session"
+    }
+
+    fn exclusive(&self, input: &str) -> bool {
+        self.confirming(input).is_some()
+    }
+
+    fn query(&mut self, matcher: &SkimMatcherV2, input: &str, _cancel: &Cancel) -> Vec<ResultItem> {
+        if let Some((action, command)) = self.confirming(input) {
+            return vec![self.confirm(*action, command)];
+        }
+
+        let query = input.trim();
+        if query.len() < MIN_QUERY_LEN {
+            return vec![];
+        }
+        self.commands
+            .iter()
+            .filter_map(|(action, command)| {
+                let words = format!("{} {}", action.title(), action.keywords());
+                let score = matcher.fuzzy_match(&words, query)?;
+                Some(self.result(*action, command, score))
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn command(words: &[&str]) -> Action {
+        Action::Spawn(words.iter().map(|w| w.to_string()).collect())
+    }
+
+    #[test]
+    fn runs_at_once() {
+        let mut p = SessionProvider::new(&SessionConfig::default());
+        let matcher = SkimMatcherV2::default();
+
+        let results = p.query(&matcher, "lock", &Cancel::default());
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0].title, "Lock");
+        assert_eq!(results[0].action, command(&["loginctl", "lock-session"]));
+
+        assert_eq!(
+            p.query(&matcher, "sleep", &Cancel::default())[0].title,
+            "Suspend"
+        );
+        assert!(p.query(&matcher, "s", &Cancel::default()).is_empty());
+    }
+
+    #[test]
+    fn confirms() {
+        let mut p = SessionProvider::new(&SessionConfig::default());
+        let matcher = SkimMatcherV2::default();
+
+        let results = p.query(&matcher, "shutdown", &Cancel::default());
+        assert_eq!(results[0].title, "Power off");
+        assert_eq!(results[0].action, Action::SetInput("power off?".into()));
+        assert!(!p.exclusive("shutdown"));
+
+        assert!(p.exclusive("Power off?"));
+        let results = p.query(&matcher, "power off?", &Cancel::default());
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0].title, "Power off now?");
+        assert_eq!(
+            results[0].action,
+            Action::Countdown(
+                "Powering off".into(),
+                10,
+                Box::new(command(&["systemctl", "poweroff"]))
+            )
+        );
+
+        // Only destructive actions are confirmed.
+        assert!(!p.exclusive("lock?"));
+    }
+
+    #[test]
+    fn configured() {
+        let config = SessionConfig {
+            countdown: 0,
+            reboot: "sudo reboot --force".into(),
+            ..SessionConfig::default()
+        };
+        let mut p = SessionProvider::new(&config);
+        let matcher = SkimMatcherV2::default();
+
+        let results = p.query(&matcher, "restart", &Cancel::default());
+        assert_eq!(results[0].subtitle.as_deref(), Some("sudo reboot --force"));
+
+        // Without a countdown, confirming runs the command straight away.
+        assert_eq!(
+            p.query(&matcher, "reboot?", &Cancel::default())[0].action,
+            command(&["sudo", "reboot", "--force"])
+        );
+    }
+}
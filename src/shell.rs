@@ -0,0 +1,460 @@
+use std::io::Read;
+use std::process::{Child, Command, ExitStatus, Stdio};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use crate::ext::ImmediateExtra;
+
+/// Prefix which switches the input into command mode.
+const COMMAND_PREFIX: char = '>';
+
+/// Commands running for longer than this are killed.
+const TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Output beyond this many bytes is discarded, so a chatty command can't eat all our memory.
+const MAX_OUTPUT_BYTES: usize = 256 * 1024;
+
+/// How the command finished (if it has).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RunStatus {
+    Running,
+    Exited(ExitStatus),
+    Killed,
+    TimedOut,
+    SpawnFailed(String),
+}
+
+/// Shared state of a command, written by the worker threads and read by the UI.
+#[derive(Debug)]
+struct RunState {
+    output: String,
+    /// The start of a character split between reads, kept until the rest arrives.
+    partial: Vec<u8>,
+    /// Bytes of output kept, including `partial`.
+    kept: usize,
+    truncated: bool,
+    status: RunStatus,
+}
+
+impl RunState {
+    fn append(&mut self, data: &[u8]) {
+        let remaining = MAX_OUTPUT_BYTES - self.kept;
+        if data.len() > remaining {
+            self.truncated = true;
+        }
+        let data = &data[..data.len().min(remaining)];
+        self.kept += data.len();
+        self.partial.extend_from_slice(data);
+
+        // Only complete characters are decoded, so one split between reads
+        // isn't replaced.
+        let mut rest = &self.partial[..];
+        loop {
+            let (valid, error) = match std::str::from_utf8(rest) {
+                Ok(text) => (text, None),
+                Err(e) => (
+                    std::str::from_utf8(&rest[..e.valid_up_to()]).unwrap(),
+                    Some(e),
+                ),
+            };
+            self.output.push_str(valid);
+            rest = &rest[valid.len()..];
+            match error.and_then(|e| e.error_len()) {
+                Some(len) => {
+                    self.output.push(char::REPLACEMENT_CHARACTER);
+                    rest = &rest[len..];
+                }
+                // Everything's decoded, or what's left is the start of a character.
+                None => break,
+            }
+        }
+        self.partial = rest.to_vec();
+    }
+
+    fn finish(&mut self, status: RunStatus) {
+        self.output
+            .push_str(&String::from_utf8_lossy(&self.partial));
+        self.partial.clear();
+        self.status = status;
+    }
+}
+
+/// A command spawned through the users shell.
+#[derive(Debug)]
+struct Run {
+    cmd: String,
+    state: Arc<Mutex<RunState>>,
+    kill: Arc<AtomicBool>,
+}
+
+impl Run {
+    fn spawn(cmd: String, timeout: Duration, ctx: egui::Context) -> Self {
+        let state = Arc::new(Mutex::new(RunState {
+            output: String::new(),
+            partial: vec![],
+            kept: 0,
+            truncated: false,
+            status: RunStatus::Running,
+        }));
+        let kill = Arc::new(AtomicBool::new(false));
+
+        let shell = std::env::var("SHELL").unwrap_or("/bin/sh".into());
+        let mut command = Command::new(shell);
+        command
+            .arg("-c")
+            .arg(&cmd)
+            .stdin(Stdio::null())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped());
+        // In a process group of its own, so whatever it starts can be killed with it.
+        #[cfg(unix)]
+        std::os::unix::process::CommandExt::process_group(&mut command, 0);
+        let child = command.spawn();
+
+        match child {
+            Err(e) => {
+                state.lock().unwrap().status = RunStatus::SpawnFailed(e.to_string());
+            }
+            Ok(mut child) => {
+                // stdout & stderr are streamed into the same buffer, in whatever
+                // order the command writes them.
+                let open_streams = Arc::new(AtomicUsize::new(0));
+                if let Some(out) = child.stdout.take() {
+                    Run::stream(out, state.clone(), open_streams.clone(), ctx.clone());
+                }
+                if let Some(err) = child.stderr.take() {
+                    Run::stream(err, state.clone(), open_streams.clone(), ctx.clone());
+                }
+                Run::wait(
+                    child,
+                    timeout,
+                    state.clone(),
+                    kill.clone(),
+                    open_streams,
+                    ctx,
+                );
+            }
+        }
+
+        Self { cmd, state, kill }
+    }
+
+    fn stream(
+        mut src: impl Read + Send + 'static,
+        state: Arc<Mutex<RunState>>,
+        open_streams: Arc<AtomicUsize>,
+        ctx: egui::Context,
+    ) {
+        open_streams.fetch_add(1, Ordering::SeqCst);
+        std::thread::spawn(move || {
+            let mut buf = [0u8; 4096];
+            loop {
+                match src.read(&mut buf) {
+                    Ok(0) | Err(_) => break,
+                    Ok(n) => {
+                        state.lock().unwrap().append(&buf[..n]);
+                        ctx.request_repaint();
+                    }
+                }
+            }
+            open_streams.fetch_sub(1, Ordering::SeqCst);
+        });
+    }
+
+    fn wait(
+        mut child: Child,
+        timeout: Duration,
+        state: Arc<Mutex<RunState>>,
+        kill: Arc<AtomicBool>,
+        open_streams: Arc<AtomicUsize>,
+        ctx: egui::Context,
+    ) {
+        std::thread::spawn(move || {
+            let started = Instant::now();
+            let status = loop {
+                match child.try_wait() {
+                    Ok(Some(status)) => break RunStatus::Exited(status),
+                    Ok(None) => {}
+                    Err(e) => break RunStatus::SpawnFailed(e.to_string()),
+                }
+
+                if kill.load(Ordering::SeqCst) {
+                    Run::kill(&mut child);
+                    break RunStatus::Killed;
+                }
+                if started.elapsed() > timeout {
+                    Run::kill(&mut child);
+                    break RunStatus::TimedOut;
+                }
+                std::thread::sleep(Duration::from_millis(20));
+            };
+
+            // Give the readers a moment to drain the pipes. We don't wait forever, as
+            // a backgrounded grandchild can keep them open indefinitely.
+            let drain_start = Instant::now();
+            while open_streams.load(Ordering::SeqCst) > 0
+                && drain_start.elapsed() < Duration::from_millis(250)
+            {
+                std::thread::sleep(Duration::from_millis(5));
+            }
+
+            state.lock().unwrap().finish(status);
+            ctx.request_repaint();
+        });
+    }
+
+    /// Kills the shell along with the commands it started, which are in its
+    /// process group.
+    fn kill(child: &mut Child) {
+        #[cfg(unix)]
+        unsafe {
+            libc::kill(-(child.id() as libc::pid_t), libc::SIGKILL);
+        }
+        #[cfg(not(unix))]
+        let _ = child.kill();
+        let _ = child.wait();
+    }
+
+    fn is_running(&self) -> bool {
+        self.state.lock().unwrap().status == RunStatus::Running
+    }
+}
+
+/// Runs `> <command>` through `$SHELL -c`, showing its output inline.
+#[derive(Debug, Default)]
+pub struct ShellExtra {
+    run: Option<Run>,
+}
+
+impl ShellExtra {
+    /// Returns the command in the input, if the input is in command mode.
+    fn command(input: &str) -> Option<&str> {
+        input
+            .trim_start()
+            .strip_prefix(COMMAND_PREFIX)
+            .map(|c| c.trim())
+    }
+
+    fn status_ui(status: &RunStatus, ui: &mut egui::Ui) {
+        match status {
+            RunStatus::Running => {
+                ui.spinner();
+            }
+            RunStatus::Exited(s) if s.success() => {
+                ui.colored_label(egui::Color32::GREEN, "✔ exit 0");
+            }
+            RunStatus::Exited(s) => {
+                let text = match s.code() {
+                    Some(code) => format!("✖ exit {}", code),
+                    None => "✖ signalled".to_string(),
+                };
+                ui.colored_label(ui.visuals().error_fg_color, text);
+            }
+            RunStatus::Killed => {
+                ui.colored_label(ui.visuals().warn_fg_color, "✖ killed");
+            }
+            RunStatus::TimedOut => {
+                ui.colored_label(ui.visuals().warn_fg_color, "✖ timed out");
+            }
+            RunStatus::SpawnFailed(e) => {
+                ui.colored_label(ui.visuals().error_fg_color, format!("✖ {}", e));
+            }
+        }
+    }
+}
+
+impl ImmediateExtra for ShellExtra {
+    fn ui(&mut self, input: &String, ctx: &egui::Context, ui: &mut egui::Ui) -> bool {
+        let cmd = match ShellExtra::command(input) {
+            Some(cmd) => cmd,
+            None => return false,
+        };
+
+        egui::CollapsingHeader::new("Command")
+            .default_open(true)
+            .show(ui, |ui| match &self.run {
+                None => {
+                    ui.weak(if cmd.is_empty() {
+                        "Type a command to run"
+                    } else {
+                        "Press enter to run"
+                    });
+                }
+                Some(run) => {
+                    let state = run.state.lock().unwrap();
+                    ui.horizontal(|ui| {
+                        ShellExtra::status_ui(&state.status, ui);
+                        ui.monospace(&run.cmd);
+
+                        ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
+                            if ui.small_button("📋").on_hover_text("Copy output").clicked() {
+                                ctx.output_mut(|o| o.copied_text = state.output.clone());
+                            }
+                            if state.status == RunStatus::Running
+                                && ui.small_button("⏹").on_hover_text("Kill").clicked()
+                            {
+                                run.kill.store(true, Ordering::SeqCst);
+                            }
+                        });
+                    });
+
+                    egui::ScrollArea::vertical()
+                        .id_source("shell_output")
+                        .auto_shrink([false, true])
+                        .max_height(ui.available_height().max(80.))
+                        .stick_to_bottom(true)
+                        .show(ui, |ui| {
+                            ui.add(
+                                egui::Label::new(egui::RichText::new(&state.output).monospace())
+                                    .wrap(false),
+                            );
+                            if state.truncated {
+                                ui.weak("(output truncated)");
+                            }
+                        });
+                }
+            });
+
+        true
+    }
+
+    fn submit(&mut self, input: &String, ctx: &egui::Context) -> bool {
+        let cmd = match ShellExtra::command(input) {
+            Some(cmd) => cmd,
+            None => return false,
+        };
+        if cmd.is_empty() {
+            return true;
+        }
+
+        if let Some(run) = &self.run {
+            if run.is_running() {
+                run.kill.store(true, Ordering::SeqCst);
+            }
+        }
+        self.run = Some(Run::spawn(cmd.to_string(), TIMEOUT, ctx.clone()));
+        true
+    }
+
+    fn exclusive(&self, input: &String) -> bool {
+        ShellExtra::command(input).is_some()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn command() {
+        assert_eq!(ShellExtra::command("> df -h"), Some("df -h"));
+        assert_eq!(ShellExtra::command(">date -u\n"), Some("date -u"));
+        assert_eq!(ShellExtra::command(">"), Some(""));
+        assert_eq!(ShellExtra::command("firefox"), None);
+    }
+
+    #[test]
+    fn output_capped() {
+        let mut st = RunState {
+            output: String::new(),
+            partial: vec![],
+            kept: 0,
+            truncated: false,
+            status: RunStatus::Running,
+        };
+        st.append(&vec![b'a'; MAX_OUTPUT_BYTES - 1]);
+        assert!(!st.truncated);
+        st.append(b"bc");
+        assert!(st.truncated);
+        assert_eq!(st.output.len(), MAX_OUTPUT_BYTES);
+    }
+
+    #[test]
+    fn output_decoded() {
+        let mut st = RunState {
+            output: String::new(),
+            partial: vec![],
+            kept: 0,
+            truncated: false,
+            status: RunStatus::Running,
+        };
+        let text = "caf\u{e9} \u{1f680}".as_bytes();
+        for b in text {
+            st.append(&[*b]);
+        }
+        assert_eq!(st.output, "caf\u{e9} \u{1f680}");
+
+        st.append(b"\xffok \xf0\x9f");
+        assert_eq!(st.output, "caf\u{e9} \u{1f680}\u{fffd}ok ");
+        st.finish(RunStatus::Killed);
+        assert_eq!(st.output, "caf\u{e9} \u{1f680}\u{fffd}ok \u{fffd}");
+    }
+
+    #[test]
+    fn runs_command() {
+        let run = Run::spawn(
+            "echo hi; echo there >&2".into(),
+            TIMEOUT,
+            egui::Context::default(),
+        );
+        let start = Instant::now();
+        while run.is_running() && start.elapsed() < Duration::from_secs(5) {
+            std::thread::sleep(Duration::from_millis(10));
+        }
+
+        let st = run.state.lock().unwrap();
+        assert!(matches!(st.status, RunStatus::Exited(s) if s.success()));
+        assert!(st.output.contains("hi\n"));
+        assert!(st.output.contains("there\n"));
+    }
+
+    #[test]
+    fn timeout() {
+        let run = Run::spawn(
+            "sleep 5".into(),
+            Duration::from_millis(50),
+            egui::Context::default(),
+        );
+        let start = Instant::now();
+        while run.is_running() && start.elapsed() < Duration::from_secs(5) {
+            std::thread::sleep(Duration::from_millis(10));
+        }
+        assert_eq!(run.state.lock().unwrap().status, RunStatus::TimedOut);
+    }
+
+    #[test]
+    fn kills_group() {
+        let run = Run::spawn(
+            "sleep 30 & echo $!; wait".into(),
+            TIMEOUT,
+            egui::Context::default(),
+        );
+        let start = Instant::now();
+        let pid = loop {
+            let output = run.state.lock().unwrap().output.clone();
+            if let Some(pid) = output
+                .strip_suffix('\n')
+                .and_then(|p| p.parse::<u32>().ok())
+            {
+                break pid;
+            }
+            assert!(start.elapsed() < Duration::from_secs(5));
+            std::thread::sleep(Duration::from_millis(10));
+        };
+        run.kill.store(true, Ordering::SeqCst);
+
+        // The command the shell started is killed too, though it may not have
+        // been reaped yet.
+        let alive = || match std::fs::read_to_string(format!("/proc/{}/stat", pid)) {
+            Ok(stat) => !stat.rsplit(") ").next().unwrap().starts_with('Z'),
+            Err(_) => false,
+        };
+        let start = Instant::now();
+        while alive() || run.is_running() {
+            assert!(start.elapsed() < Duration::from_secs(5));
+            std::thread::sleep(Duration::from_millis(10));
+        }
+        assert_eq!(run.state.lock().unwrap().status, RunStatus::Killed);
+    }
+}
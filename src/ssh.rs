@@ -0,0 +1,369 @@
+use fuzzy_matcher::skim::SkimMatcherV2;
+use fuzzy_matcher::FuzzyMatcher;
+use std::path::{Path, PathBuf};
+
+use crate::provider::{Action, Cancel, Provider, ResultItem};
+
+/// Includes nested deeper than this are ignored, in case they form a loop.
+const MAX_INCLUDE_DEPTH: usize = 8;
+
+/// At most this many hosts are listed.
+const MAX_RESULTS: usize = 10;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SshHost {
+    /// What's passed to ssh: an alias from the config, or a known host.
+    pub name: String,
+    /// The real host name, if the config gives one.
+    pub hostname: Option<String>,
+    /// Set for known hosts on a non-standard port.
+    pub port: Option<u16>,
+}
+
+impl SshHost {
+    fn new(name: &str) -> Self {
+        Self {
+            name: name.to_string(),
+            hostname: None,
+            port: None,
+        }
+    }
+
+    /// Returns the arguments which connect to the host.
+    pub fn ssh_args(&self) -> Vec<String> {
+        let mut args = vec!["ssh".to_string()];
+        if let Some(port) = self.port {
+            args.extend(["-p".to_string(), port.to_string()]);
+        }
+        // A host named like `-oProxyCommand=…` mustn't be taken for an option.
+        args.extend(["--".to_string(), self.name.clone()]);
+        args
+    }
+}
+
+/// Splits a config line into its keyword, lowercased, and arguments. The
+/// keyword may be followed by `=` rather than whitespace.
+fn split_line(line: &str) -> Option<(String, &str)> {
+    let line = line.trim();
+    if line.is_empty() || line.starts_with('#') {
+        return None;
+    }
+
+    let end = line
+        .find(|c: char| c.is_whitespace() || c == '=')
+        .unwrap_or(line.len());
+    let (keyword, rest) = line.split_at(end);
+    let rest = rest.trim_start();
+    let rest = rest.strip_prefix('=').unwrap_or(rest).trim();
+    Some((keyword.to_lowercase(), rest))
+}
+
+/// Matches a file name against a pattern of `*` and `?` wildcards.
+fn wildcard_match(pattern: &str, name: &str) -> bool {
+    match (pattern.chars().next(), name.chars().next()) {
+        (None, None) => true,
+        (Some('*'), _) => {
+            wildcard_match(&pattern[1..], name)
+                || name
+                    .chars()
+                    .next()
+                    .map_or(false, |c| wildcard_match(pattern, &name[c.len_utf8()..]))
+        }
+        (Some('?'), Some(c)) => wildcard_match(&pattern[1..], &name[c.len_utf8()..]),
+        (Some(p), Some(c)) if p == c => {
+            wildcard_match(&pattern[p.len_utf8()..], &name[c.len_utf8()..])
+        }
+        _ => false,
+    }
+}
+
+/// Returns the files an Include argument refers to. Relative paths are relative
+/// to ~/.ssh, and wildcards are allowed in the file name.
+fn include_paths(pattern: &str, home: &Path) -> Vec<PathBuf> {
+    let path = match pattern.strip_prefix("~/") {
+        Some(rest) => home.join(rest),
+        None => home.join(".ssh").join(pattern),
+    };
+
+    let name = path
+        .file_name()
+        .map(|n| n.to_string_lossy().to_string())
+        .unwrap_or_default();
+    if !name.contains(['*', '?']) {
+        return vec![path];
+    }
+
+    let mut paths: Vec<PathBuf> = match path.parent().map(std::fs::read_dir) {
+        Some(Ok(entries)) => entries
+            .flatten()
+            .filter(|e| wildcard_match(&name, &e.file_name().to_string_lossy()))
+            .map(|e| e.path())
+            .collect(),
+        _ => vec![],
+    };
+    paths.sort();
+    paths
+}
+
+/// Reads the hosts from an ssh config file and the files it includes.
+pub fn parse_config(path: &Path, home: &Path, hosts: &mut Vec<SshHost>) {
+    parse_config_at_depth(path, home, hosts, 0)
+}
+
+fn parse_config_at_depth(path: &Path, home: &Path, hosts: &mut Vec<SshHost>, depth: usize) {
+    let Ok(data) = std::fs::read_to_string(path) else {
+        return;
+    };
+
+    // Indices of the hosts of the current Host block.
+    let mut block: Vec<usize> = vec![];
+    for (keyword, args) in data.lines().filter_map(split_line) {
+        match keyword.as_str() {
+            "host" => {
+                block.clear();
+                for name in args.split_whitespace() {
+                    // Patterns & negations don't name a host we can connect to.
+                    if name.contains(['*', '?', '!']) {
+                        continue;
+                    }
+                    match hosts.iter().position(|h| h.name == name) {
+                        Some(i) => block.push(i),
+                        None => {
+                            block.push(hosts.len());
+                            hosts.push(SshHost::new(name));
+                        }
+                    }
+                }
+            }
+            "match" => block.clear(),
+            "hostname" => {
+                for i in block.iter() {
+                    // Like ssh, the first value given is used.
+                    hosts[*i].hostname.get_or_insert_with(|| args.to_string());
+                }
+            }
+            "include" if depth < MAX_INCLUDE_DEPTH => {
+                for pattern in args.split_whitespace() {
+                    for include in include_paths(pattern, home) {
+                        parse_config_at_depth(&include, home, hosts, depth + 1);
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+/// Reads the hosts from a known_hosts file, skipping hashed entries.
+pub fn parse_known_hosts(path: &Path, hosts: &mut Vec<SshHost>) {
+    let Ok(data) = std::fs::read_to_string(path) else {
+        return;
+    };
+
+    for line in data.lines().map(str::trim) {
+        // Markers apply to certificate authorities & revoked keys, not hosts.
+        if line.is_empty() || line.starts_with('#') || line.starts_with('@') {
+            continue;
+        }
+        let Some(names) = line.split_whitespace().next() else {
+            continue;
+        };
+
+        for name in names.split(',') {
+            if name.starts_with('|') || name.contains(['*', '?', '!']) {
+                continue;
+            }
+
+            // Hosts on other ports are written [host]:port.
+            let host = match name
+                .strip_prefix('[')
+                .and_then(|n| n.split_once("]:"))
+                .and_then(|(h, p)| Some((h, p.parse::<u16>().ok()?)))
+            {
+                Some((h, port)) => SshHost {
+                    port: Some(port),
+                    ..SshHost::new(h)
+                },
+                None => SshHost::new(name),
+            };
+
+            let known = hosts
+                .iter()
+                .any(|h| h.name == host.name || h.hostname.as_deref() == Some(&host.name));
+            if !known {
+                hosts.push(host);
+            }
+        }
+    }
+}
+
+/// Lists the hosts from ~/.ssh/config & ~/.ssh/known_hosts, connecting to them
+/// in a terminal.
+pub struct SshProvider {
+    hosts: Vec<SshHost>,
+    /// The command which runs its arguments in a terminal window.
+    terminal: Vec<String>,
+}
+
+impl SshProvider {
+    pub fn new(home: &Path, terminal: Vec<String>) -> Self {
+        let mut hosts = vec![];
+        parse_config(&home.join(".ssh/config"), home, &mut hosts);
+        parse_known_hosts(&home.join(".ssh/known_hosts"), &mut hosts);
+        Self { hosts, terminal }
+    }
+}
+
+impl Provider for SshProvider {
+    fn name(&self) -> &'static str {
+        "ssh"
+    }
+
+    fn query(&mut self, matcher: &SkimMatcherV2, input: &str, _cancel: &Cancel) -> Vec<ResultItem> {
+        // Allow typing the command, like "ssh web".
+        let query = input.trim();
+        let query = query.strip_prefix("ssh ").unwrap_or(query).trim();
+        if query.is_empty() {
+            return vec![];
+        }
+
+        let mut scored: Vec<(i64, &SshHost)> = self
+            .hosts
+            .iter()
+            .filter_map(|h| {
+                let score = [
+                    matcher.fuzzy_match(&h.name, query),
+                    h.hostname
+                        .as_ref()
+                        .and_then(|n| matcher.fuzzy_match(n, query)),
+                ]
+                .into_iter()
+                .flatten()
+                .max()?;
+                Some((score, h))
+            })
+            .collect();
+        scored.sort_by_key(|s| std::cmp::Reverse(s.0));
+        scored.truncate(MAX_RESULTS);
+
+        scored
+            .into_iter()
+            .map(|(score, h)| {
+                let mut command = self.terminal.clone();
+                command.extend(h.ssh_args());
+                ResultItem {
+                    title: format!("ssh {}", h.name),
+                    subtitle: h.hostname.clone(),
+                    glyph: "🖧".into(),
+                    score,
+                    action: Action::Spawn(command),
+                    alt_action: None,
+                }
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn home() -> PathBuf {
+        Path::new(env!("CARGO_MANIFEST_DIR")).join("testdata/ssh/home")
+    }
+
+    fn host(name: &str, hostname: Option<&str>, port: Option<u16>) -> SshHost {
+        SshHost {
+            name: name.into(),
+            hostname: hostname.map(|h| h.into()),
+            port,
+        }
+    }
+
+    #[test]
+    fn lines() {
+        assert_eq!(
+            split_line("  Host web db "),
+            Some(("host".into(), "web db"))
+        );
+        assert_eq!(
+            split_line("HostName=10.0.0.1"),
+            Some(("hostname".into(), "10.0.0.1"))
+        );
+        assert_eq!(split_line("Port = 22"), Some(("port".into(), "22")));
+        assert_eq!(split_line("# Host commented"), None);
+        assert_eq!(split_line("   "), None);
+    }
+
+    #[test]
+    fn wildcards() {
+        assert!(wildcard_match("*", "work"));
+        assert!(wildcard_match("*.conf", "work.conf"));
+        assert!(wildcard_match("w?rk", "work"));
+        assert!(!wildcard_match("*.conf", "work.conf~"));
+        assert!(!wildcard_match("w?rk", "wrk"));
+    }
+
+    #[test]
+    fn config() {
+        let home = home();
+        let mut hosts = vec![];
+        parse_config(&home.join(".ssh/config"), &home, &mut hosts);
+        assert_eq!(
+            hosts,
+            vec![
+                host("web", Some("web1.example.com"), None),
+                host("web-alias", Some("web1.example.com"), None),
+                host("db", Some("10.0.0.5"), None),
+                host("build", Some("build.internal"), None),
+                host("pi", None, None),
+                host("bastion", Some("bastion.example.com"), None),
+            ]
+        );
+    }
+
+    #[test]
+    fn known_hosts() {
+        let mut hosts = vec![host("db", Some("10.0.0.5"), None)];
+        parse_known_hosts(&home().join(".ssh/known_hosts"), &mut hosts);
+        assert_eq!(
+            hosts,
+            vec![
+                host("db", Some("10.0.0.5"), None),
+                host("github.com", None, None),
+                host("140.82.121.4", None, None),
+                host("git.example.com", None, Some(2222)),
+            ]
+        );
+    }
+
+    #[test]
+    fn query() {
+        let mut p = SshProvider::new(&home(), vec!["foot".into(), "-e".into()]);
+        let matcher = SkimMatcherV2::default();
+
+        let results = p.query(&matcher, "ssh bast", &Cancel::default());
+        assert_eq!(results[0].title, "ssh bastion");
+        assert_eq!(
+            results[0].action,
+            Action::Spawn(vec![
+                "foot".into(),
+                "-e".into(),
+                "ssh".into(),
+                "--".into(),
+                "bastion".into()
+            ])
+        );
+
+        let results = p.query(&matcher, "git.example", &Cancel::default());
+        assert_eq!(
+            results[0].action,
+            Action::Spawn(
+                ["foot", "-e", "ssh", "-p", "2222", "--", "git.example.com"]
+                    .map(String::from)
+                    .to_vec()
+            )
+        );
+        assert!(p.query(&matcher, "", &Cancel::default()).is_empty());
+    }
+}
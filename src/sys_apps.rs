@@ -1,6 +1,6 @@
 use freedesktop_entry_parser::{parse_entry, Entry};
 use std::env;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 
 /// In addition to entries in $XDG_DATA_DIRS
 const APPLICATION_PATHS: [&str; 4] = [
@@ -10,11 +10,13 @@ const APPLICATION_PATHS: [&str; 4] = [
     "/var/lib/flatpak/exports/share/applications",
 ];
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Default)]
 pub struct App {
     pub name: String,
     cmd: String,
     pub icon_name: Option<String>,
+    /// Desktop file ID, such as `firefox.desktop`.
+    pub id: String,
 }
 
 impl TryFrom<Entry> for App {
@@ -33,6 +35,7 @@ impl TryFrom<Entry> for App {
                 name: name.into(),
                 cmd: cmd.into(),
                 icon_name,
+                id: String::new(),
             }),
             _ => Err(()),
         }
@@ -40,6 +43,16 @@ impl TryFrom<Entry> for App {
 }
 
 impl App {
+    /// Creates an application which runs the command, as if from a desktop entry.
+    #[cfg(test)]
+    pub fn new(name: &str, cmd: &str) -> Self {
+        Self {
+            name: name.into(),
+            cmd: cmd.into(),
+            ..Self::default()
+        }
+    }
+
     // This whole method is terrible, im sorry (not sorry)
     pub fn find_icon(&self, size: u16) -> Option<PathBuf> {
         match &self.icon_name {
@@ -54,7 +67,7 @@ impl App {
                     hicolor_png.pop(); // remove 'applications/'
                     hicolor_png.push("icons");
                     hicolor_png.push("hicolor");
-                    hicolor_png.push(size.to_string() + "x" + &size.to_string());
+                    hicolor_png.push(size.to_string() + "x" + size.to_string().as_str());
                     hicolor_png.push("apps");
                     hicolor_png.push(ic.to_owned() + ".png");
                     if hicolor_png.exists() {
@@ -73,7 +86,7 @@ impl App {
                     hicolor_jpg.pop(); // remove 'applications/'
                     hicolor_jpg.push("icons");
                     hicolor_jpg.push("hicolor");
-                    hicolor_jpg.push(size.to_string() + "x" + &size.to_string());
+                    hicolor_jpg.push(size.to_string() + "x" + size.to_string().as_str());
                     hicolor_jpg.push("apps");
                     hicolor_jpg.push(ic.to_owned() + ".jpg");
                     if hicolor_jpg.exists() {
@@ -95,7 +108,7 @@ impl App {
                     gnome_apps_png.pop(); // remove 'applications/'
                     gnome_apps_png.push("icons");
                     gnome_apps_png.push("gnome");
-                    gnome_apps_png.push(size.to_string() + "x" + &size.to_string());
+                    gnome_apps_png.push(size.to_string() + "x" + size.to_string().as_str());
                     gnome_apps_png.push("apps");
                     gnome_apps_png.push(ic.to_owned() + ".png");
                     if gnome_apps_png.exists() {
@@ -105,7 +118,7 @@ impl App {
                     gnome_devices_png.pop(); // remove 'applications/'
                     gnome_devices_png.push("icons");
                     gnome_devices_png.push("gnome");
-                    gnome_devices_png.push(size.to_string() + "x" + &size.to_string());
+                    gnome_devices_png.push(size.to_string() + "x" + size.to_string().as_str());
                     gnome_devices_png.push("devices");
                     gnome_devices_png.push(ic.to_owned() + ".png");
                     if gnome_devices_png.exists() {
@@ -116,7 +129,7 @@ impl App {
                     adwaita_actions_png.pop(); // remove 'applications/'
                     adwaita_actions_png.push("icons");
                     adwaita_actions_png.push("Adwaita");
-                    adwaita_actions_png.push(size.to_string() + "x" + &size.to_string());
+                    adwaita_actions_png.push(size.to_string() + "x" + size.to_string().as_str());
                     adwaita_actions_png.push("actions");
                     adwaita_actions_png.push(ic.to_owned() + "-symbolic.symbolic.png");
                     if adwaita_actions_png.exists() {
@@ -140,22 +153,48 @@ impl App {
     }
 
     pub fn run(&self, quit: bool) {
-        use std::process::{exit, Command};
-
-        let split_command = shell_words::split(&self.cmd).unwrap();
-        let args: Vec<String> = split_command[1..]
-            .iter()
-            .filter(|a| !a.starts_with("%"))
-            .map(|a| a.clone())
-            .collect();
+        if let Err(e) = self.run_with(&[]) {
+            eprintln!("failed to run {:?}: {}", self.name, e);
+        }
+        if quit {
+            std::process::exit(0);
+        }
+    }
 
-        Command::new(split_command[0].trim_matches('\"'))
-            .args(args)
+    /// Runs the application with the given files or URLs.
+    pub fn run_with(&self, targets: &[String]) -> std::io::Result<()> {
+        let command = self.command(targets)?;
+        std::process::Command::new(&command[0])
+            .args(&command[1..])
             .spawn()
-            .unwrap();
-        if quit {
-            exit(0);
+            .map(|_| ())
+    }
+
+    /// Returns the program & arguments which run the application with the given
+    /// files or URLs, substituted into the field codes of its Exec line. Files
+    /// given as `file://` URIs are passed as paths for `%f` & `%F`.
+    pub fn command(&self, targets: &[String]) -> std::io::Result<Vec<String>> {
+        use std::io::{Error, ErrorKind};
+
+        let split_command = shell_words::split(&self.cmd)
+            .map_err(|e| Error::new(ErrorKind::InvalidData, format!("bad Exec line: {}", e)))?;
+        let (program, args) = split_command
+            .split_first()
+            .ok_or_else(|| Error::new(ErrorKind::InvalidData, "empty Exec line"))?;
+        let paths: Vec<String> = targets.iter().map(|t| local_path(t)).collect();
+        let mut command: Vec<String> = Vec::with_capacity(split_command.len() + targets.len());
+        command.push(program.trim_matches('\"').to_string());
+        for a in args.iter() {
+            match a.as_str() {
+                "%f" => command.extend(paths.first().cloned()),
+                "%u" => command.extend(targets.first().cloned()),
+                "%F" => command.extend(paths.iter().cloned()),
+                "%U" => command.extend(targets.iter().cloned()),
+                a if a.starts_with("%") => {}
+                a => command.push(a.to_string()),
+            }
         }
+        Ok(command)
     }
 }
 
@@ -165,16 +204,21 @@ pub struct AppList {
 }
 
 impl AppList {
-    #[cfg(not(target_os = "linux"))]
     pub fn new() -> std::io::Result<Self> {
+        AppList::with_extra_dirs(&[])
+    }
+
+    #[cfg(not(target_os = "linux"))]
+    pub fn with_extra_dirs(_extra_dirs: &[PathBuf]) -> std::io::Result<Self> {
         Ok(AppList {
             apps: vec![],
             ..Self::default()
         })
     }
 
+    /// Loads the applications from the standard directories, and then the given ones.
     #[cfg(target_os = "linux")]
-    pub fn new() -> std::io::Result<Self> {
+    pub fn with_extra_dirs(extra_dirs: &[PathBuf]) -> std::io::Result<Self> {
         let mut entries = Vec::new();
         let xdg = AppList::xdg_app_dirs();
         if xdg.len() == 0 {
@@ -185,6 +229,9 @@ impl AppList {
         for path in xdg.into_iter() {
             AppList::read_desktop_entries(&path, &mut entries)
         }
+        for path in extra_dirs.iter() {
+            AppList::read_desktop_entries(path, &mut entries)
+        }
 
         // panic!("{:?}", entries);
         Ok(AppList {
@@ -193,6 +240,24 @@ impl AppList {
         })
     }
 
+    /// Returns the application registered as the default handler for the MIME type.
+    pub fn default_for_mime(mime: &str) -> Option<App> {
+        let id = xdg_mime(&["query", "default", mime])?;
+
+        let mut dirs = AppList::xdg_app_dirs();
+        dirs.extend(APPLICATION_PATHS.iter().map(PathBuf::from));
+        for dir in dirs {
+            let path = dir.join(&id);
+            if let Ok(e) = parse_entry(&path) {
+                if let Ok(mut app) = App::try_from(e) {
+                    app.id = id;
+                    return Some(app);
+                }
+            }
+        }
+        None
+    }
+
     fn read_desktop_entries(path: &PathBuf, entries: &mut Vec<App>) {
         match std::fs::read_dir(path) {
             Ok(files) => {
@@ -204,6 +269,7 @@ impl AppList {
                             if !file.file_name().to_string_lossy().ends_with(".desktop") {
                                 return None;
                             }
+                            let id = file.file_name().to_string_lossy().to_string();
                             match parse_entry(file.path()) {
                                 Ok(e) => {
                                     if let Some(nodisplay) =
@@ -212,27 +278,27 @@ impl AppList {
                                         if nodisplay == "true" {
                                             None
                                         } else {
-                                            Some(e)
+                                            Some((id, e))
                                         }
                                     } else {
-                                        Some(e)
+                                        Some((id, e))
                                     }
                                 }
                                 Err(e) => {
-                                    println!("parse err: {:?}", e);
+                                    eprintln!("parse err: {:?}", e);
                                     None
                                 }
                             }
                         }
 
                         Err(e) => {
-                            println!("err: {:?}", e);
+                            eprintln!("err: {:?}", e);
                             None
                         }
                     })
                     .filter(|e| e.is_some())
                     .map(|e| e.unwrap())
-                    .map(|e| App::try_from(e).ok())
+                    .map(|(id, e)| App::try_from(e).ok().map(|app| App { id, ..app }))
                     .filter(|e| e.is_some())
                     .map(|e| e.unwrap())
                     .collect();
@@ -259,6 +325,160 @@ impl AppList {
     }
 }
 
+/// Runs xdg-mime with the given arguments, returning its trimmed output.
+fn xdg_mime(args: &[&str]) -> Option<String> {
+    let out = std::process::Command::new("xdg-mime")
+        .args(args)
+        .output()
+        .ok()?;
+    let out = String::from_utf8_lossy(&out.stdout).trim().to_string();
+    if out.is_empty() {
+        None
+    } else {
+        Some(out)
+    }
+}
+
+/// Returns the path of a `file://` URI, or the target as it is otherwise.
+fn local_path(target: &str) -> String {
+    match target.strip_prefix("file://") {
+        Some(path) => crate::percent::decode(path),
+        None => target.to_string(),
+    }
+}
+
+/// Returns the MIME type used to pick the default application for the file or URL.
+fn mime_type(target: &str) -> Option<String> {
+    match target.split_once("://") {
+        Some((scheme, _)) if scheme != "file" => Some(format!("x-scheme-handler/{}", scheme)),
+        _ => xdg_mime(&["query", "filetype", &local_path(target)]),
+    }
+}
+
+/// Opens a file or URL with the default application for its MIME type, falling
+/// back to xdg-open.
+pub fn open(target: &str, quit: bool) {
+    open_as(target, mime_type(target).as_deref(), quit)
+}
+
+/// Opens a file or URL with the default application for the MIME type given,
+/// falling back to xdg-open without one, or if the application fails to start.
+pub fn open_as(target: &str, mime: Option<&str>, quit: bool) {
+    let opened = match mime.and_then(AppList::default_for_mime) {
+        Some(app) => match app.run_with(&[target.to_string()]) {
+            Ok(()) => true,
+            Err(e) => {
+                eprintln!("failed to open {:?} with {:?}: {}", target, app.name, e);
+                false
+            }
+        },
+        None => false,
+    };
+    if opened {
+        if quit {
+            std::process::exit(0);
+        }
+        return;
+    }
+
+    if let Err(e) = std::process::Command::new("xdg-open").arg(target).spawn() {
+        eprintln!("failed to open {:?}: {:?}", target, e);
+    }
+    if quit {
+        std::process::exit(0);
+    }
+}
+
+/// Runs the program, given first, with the arguments following it.
+pub fn spawn(command: &[String], quit: bool) {
+    if let Some((program, args)) = command.split_first() {
+        if let Err(e) = std::process::Command::new(program).args(args).spawn() {
+            eprintln!("failed to run {:?}: {:?}", command, e);
+        }
+    }
+    if quit {
+        std::process::exit(0);
+    }
+}
+
+/// Copies the text to the clipboard with `wl-copy` or `xclip`, which keep it
+/// available after the launcher exits.
+pub fn copy(text: &str, quit: bool) {
+    use std::io::Write;
+    use std::process::{Command, Stdio};
+
+    let command = if std::env::var_os("WAYLAND_DISPLAY").is_some() {
+        Command::new("wl-copy").stdin(Stdio::piped()).spawn()
+    } else {
+        Command::new("xclip")
+            .args(["-selection", "clipboard"])
+            .stdin(Stdio::piped())
+            .spawn()
+    };
+    let result = command.and_then(|mut child| {
+        child.stdin.take().unwrap().write_all(text.as_bytes())?;
+        child.wait().map(|_| ())
+    });
+    if let Err(e) = result {
+        eprintln!("failed to copy {:?}: {:?}", text, e);
+    }
+    if quit {
+        std::process::exit(0);
+    }
+}
+
+/// Returns the command which runs its arguments in a new terminal window: the
+/// configured one, otherwise `$TERMINAL -e`, falling back to
+/// `x-terminal-emulator -e`.
+pub fn terminal_command(configured: Option<&str>) -> Vec<String> {
+    if let Some(command) = configured.and_then(|c| shell_words::split(c).ok()) {
+        if !command.is_empty() {
+            return command;
+        }
+    }
+
+    let terminal = std::env::var("TERMINAL")
+        .ok()
+        .filter(|t| !t.is_empty())
+        .unwrap_or_else(|| "x-terminal-emulator".to_string());
+    vec![terminal, "-e".to_string()]
+}
+
+/// Returns the `file://` URI of the absolute path, with each segment
+/// percent-encoded.
+fn file_uri(path: &Path) -> String {
+    let path = path.to_string_lossy();
+    let segments: Vec<String> = path.split('/').map(crate::percent::encode).collect();
+    format!("file://{}", segments.join("/"))
+}
+
+/// Shows the file in the file manager, selected if the file manager supports
+/// it, otherwise opening the directory containing it.
+pub fn reveal(path: &Path, quit: bool) {
+    let shown = std::process::Command::new("dbus-send")
+        .args([
+            "--session",
+            "--print-reply",
+            "--dest=org.freedesktop.FileManager1",
+            "/org/freedesktop/FileManager1",
+            "org.freedesktop.FileManager1.ShowItems",
+        ])
+        .arg(format!("array:string:{}", file_uri(path)))
+        .arg("string:")
+        .status()
+        .map(|s| s.success())
+        .unwrap_or(false);
+
+    if !shown {
+        if let Some(parent) = path.parent() {
+            return open(&parent.to_string_lossy(), quit);
+        }
+    }
+    if quit {
+        std::process::exit(0);
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -268,6 +488,43 @@ mod tests {
         let _ = AppList::new();
     }
 
+    #[test]
+    fn command() {
+        let app = App::new("Editor", "\"/opt/my editor/bin\" --new %F %i");
+        let targets = ["a.txt".to_string(), "b.txt".to_string()];
+        assert_eq!(
+            app.command(&targets).unwrap(),
+            vec!["/opt/my editor/bin", "--new", "a.txt", "b.txt"]
+        );
+        assert_eq!(
+            App::new("Browser", "browser %u").command(&[]).unwrap(),
+            vec!["browser"]
+        );
+
+        // Files are given as paths, & URLs as they are.
+        let uri = ["file:///tmp/My%20Notes%23.md".to_string()];
+        assert_eq!(
+            App::new("Editor", "edit %f").command(&uri).unwrap(),
+            vec!["edit", "/tmp/My Notes#.md"]
+        );
+        assert_eq!(
+            App::new("Viewer", "view %U").command(&uri).unwrap(),
+            vec!["view", "file:///tmp/My%20Notes%23.md"]
+        );
+
+        // A malformed Exec line is an error, rather than a panic.
+        assert!(App::new("Broken", "broken \"%f").command(&uri).is_err());
+        assert!(App::new("Empty", "").command(&uri).is_err());
+    }
+
+    #[test]
+    fn file_uris() {
+        assert_eq!(
+            file_uri(Path::new("/tmp/a b,c/50%#1.txt")),
+            "file:///tmp/a%20b%2Cc/50%25%231.txt"
+        );
+    }
+
     #[test]
     fn xdg_app_dirs() {
         #[cfg(os = "linux")]
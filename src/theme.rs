@@ -0,0 +1,283 @@
+use egui::{Color32, FontFamily, Rounding, Stroke};
+
+use crate::config::{FontConfig, ThemeConfig};
+
+/// The built-in themes, which a config can further adjust.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, serde::Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum ThemeName {
+    /// Light or dark, following the system preference.
+    #[default]
+    System,
+    Light,
+    Dark,
+    HighContrast,
+}
+
+/// A colour written as `#rrggbb` or `#rrggbbaa`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Deserialize)]
+#[serde(try_from = "String")]
+pub struct HexColor(pub Color32);
+
+impl TryFrom<String> for HexColor {
+    type Error = String;
+
+    fn try_from(s: String) -> Result<Self, Self::Error> {
+        let hex = s
+            .strip_prefix('#')
+            .filter(|h| matches!(h.len(), 6 | 8) && h.bytes().all(|b| b.is_ascii_hexdigit()))
+            .ok_or_else(|| format!("invalid colour {:?}, expected #rrggbb or #rrggbbaa", s))?;
+
+        let mut c = [255u8; 4];
+        for (i, byte) in c.iter_mut().take(hex.len() / 2).enumerate() {
+            *byte = u8::from_str_radix(&hex[i * 2..i * 2 + 2], 16)
+                .map_err(|_| format!("invalid colour {:?}", s))?;
+        }
+        Ok(HexColor(Color32::from_rgba_unmultiplied(
+            c[0], c[1], c[2], c[3],
+        )))
+    }
+}
+
+/// Colours & sizes used to style the launcher.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Theme {
+    pub dark: bool,
+    pub background: Color32,
+    /// Replaces the text colour of every widget when set.
+    pub text: Option<Color32>,
+    pub accent: Color32,
+    /// Background of the selected result & selected text.
+    pub selection: Color32,
+    pub rounding: f32,
+    /// Horizontal space between widgets, half of which is used vertically.
+    pub spacing: f32,
+}
+
+impl Theme {
+    pub fn light() -> Self {
+        Self {
+            dark: false,
+            background: Color32::from_gray(248),
+            text: None,
+            accent: Color32::from_rgb(0, 109, 143),
+            selection: Color32::from_rgb(144, 209, 255),
+            rounding: 4.,
+            spacing: 8.,
+        }
+    }
+
+    pub fn dark() -> Self {
+        Self {
+            dark: true,
+            background: Color32::from_gray(27),
+            text: None,
+            accent: Color32::from_rgb(90, 170, 255),
+            selection: Color32::from_rgb(0, 92, 128),
+            rounding: 4.,
+            spacing: 8.,
+        }
+    }
+
+    pub fn high_contrast() -> Self {
+        Self {
+            dark: true,
+            background: Color32::BLACK,
+            text: Some(Color32::WHITE),
+            accent: Color32::YELLOW,
+            selection: Color32::from_rgb(0, 60, 170),
+            rounding: 0.,
+            spacing: 8.,
+        }
+    }
+
+    /// Returns the theme described by the config. `system` is the preference
+    /// reported by the OS, if any, which dark is assumed without.
+    pub fn from_config(config: &ThemeConfig, system: Option<eframe::Theme>) -> Self {
+        let mut theme = match (config.name, system) {
+            (ThemeName::System, Some(eframe::Theme::Light)) | (ThemeName::Light, _) => {
+                Theme::light()
+            }
+            (ThemeName::System, _) | (ThemeName::Dark, _) => Theme::dark(),
+            (ThemeName::HighContrast, _) => Theme::high_contrast(),
+        };
+
+        if let Some(HexColor(c)) = config.background {
+            theme.background = c;
+        }
+        if let Some(HexColor(c)) = config.text {
+            theme.text = Some(c);
+        }
+        if let Some(HexColor(c)) = config.accent {
+            theme.accent = c;
+        }
+        if let Some(HexColor(c)) = config.selection {
+            theme.selection = c;
+        }
+        if let Some(r) = config.rounding {
+            theme.rounding = r;
+        }
+        if let Some(s) = config.spacing {
+            theme.spacing = s;
+        }
+        theme
+    }
+
+    pub fn visuals(&self) -> egui::Visuals {
+        let mut v = if self.dark {
+            egui::Visuals::dark()
+        } else {
+            egui::Visuals::light()
+        };
+
+        v.override_text_color = self.text;
+        v.panel_fill = self.background;
+        v.window_fill = self.background;
+        v.hyperlink_color = self.accent;
+        v.text_cursor.color = self.accent;
+        v.selection.bg_fill = self.selection;
+        if let Some(text) = self.text {
+            v.selection.stroke.color = text;
+        }
+
+        let rounding = Rounding::same(self.rounding);
+        v.window_rounding = rounding;
+        v.menu_rounding = rounding;
+        for w in [
+            &mut v.widgets.noninteractive,
+            &mut v.widgets.inactive,
+            &mut v.widgets.hovered,
+            &mut v.widgets.active,
+            &mut v.widgets.open,
+        ] {
+            w.rounding = rounding;
+        }
+        v.widgets.hovered.bg_stroke = Stroke::new(1., self.accent);
+        v.widgets.active.bg_stroke = Stroke::new(1., self.accent);
+        v
+    }
+
+    /// Sets the visuals, spacing & text sizes of the context.
+    pub fn apply(&self, ctx: &egui::Context, fonts: &FontConfig) {
+        let mut style = (*ctx.style()).clone();
+        style.visuals = self.visuals();
+        style.spacing.item_spacing = egui::vec2(self.spacing, self.spacing / 2.);
+        style.spacing.button_padding = egui::vec2(self.spacing / 2., self.spacing / 4.);
+        style.text_styles = text_styles(fonts);
+        ctx.set_style(style);
+    }
+}
+
+fn text_styles(fonts: &FontConfig) -> std::collections::BTreeMap<egui::TextStyle, egui::FontId> {
+    use egui::FontFamily::{Monospace, Proportional};
+    use egui::{FontId, TextStyle};
+
+    [
+        (TextStyle::Heading, FontId::new(fonts.heading, Proportional)),
+        (TextStyle::Body, FontId::new(fonts.body, Proportional)),
+        (
+            TextStyle::Monospace,
+            FontId::new(fonts.monospace, Monospace),
+        ),
+        (TextStyle::Button, FontId::new(fonts.button, Proportional)),
+        (TextStyle::Small, FontId::new(fonts.small, Proportional)),
+    ]
+    .into()
+}
+
+/// Returns egui's fonts, preceded by the TTF/OTF files from the config. Files
+/// earlier in the lists take priority, with egui's fonts as the fallback.
+pub fn font_definitions(fonts: &FontConfig) -> Result<egui::FontDefinitions, String> {
+    let mut defs = egui::FontDefinitions::default();
+
+    for (family, files) in [
+        (FontFamily::Proportional, &fonts.files),
+        (FontFamily::Monospace, &fonts.monospace_files),
+    ] {
+        for (i, path) in files.iter().enumerate() {
+            let path = crate::config::expand_home(path);
+            let data = std::fs::read(&path)
+                .map_err(|e| format!("failed to load font {}: {}", path.display(), e))?;
+
+            let name = path.display().to_string();
+            defs.font_data
+                .insert(name.clone(), egui::FontData::from_owned(data));
+            defs.families
+                .entry(family.clone())
+                .or_default()
+                .insert(i, name);
+        }
+    }
+    Ok(defs)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn hex_color() {
+        assert_eq!(
+            HexColor::try_from("#ff8000".to_string()),
+            Ok(HexColor(Color32::from_rgb(255, 128, 0)))
+        );
+        assert_eq!(
+            HexColor::try_from("#00000080".to_string()),
+            Ok(HexColor(Color32::from_rgba_unmultiplied(0, 0, 0, 128)))
+        );
+        assert!(HexColor::try_from("ff8000".to_string()).is_err());
+        assert!(HexColor::try_from("#ff80".to_string()).is_err());
+        assert!(HexColor::try_from("#gg8000".to_string()).is_err());
+        assert!(HexColor::try_from("#+f0000".to_string()).is_err());
+    }
+
+    #[test]
+    fn from_config() {
+        let config = ThemeConfig::default();
+        assert_eq!(Theme::from_config(&config, None), Theme::dark());
+        assert_eq!(
+            Theme::from_config(&config, Some(eframe::Theme::Light)),
+            Theme::light()
+        );
+
+        let config = ThemeConfig {
+            name: ThemeName::HighContrast,
+            accent: Some(HexColor(Color32::RED)),
+            rounding: Some(2.),
+            ..ThemeConfig::default()
+        };
+        let theme = Theme::from_config(&config, Some(eframe::Theme::Light));
+        assert!(theme.dark);
+        assert_eq!(theme.accent, Color32::RED);
+        assert_eq!(theme.rounding, 2.);
+        assert_eq!(theme.background, Theme::high_contrast().background);
+    }
+
+    #[test]
+    fn apply() {
+        let ctx = egui::Context::default();
+        Theme::light().apply(&ctx, &FontConfig::default());
+
+        let style = ctx.style();
+        assert!(!style.visuals.dark_mode);
+        assert_eq!(style.visuals.panel_fill, Theme::light().background);
+        assert_eq!(style.visuals.selection.bg_fill, Theme::light().selection);
+        assert_eq!(
+            style.text_styles[&egui::TextStyle::Body].size,
+            FontConfig::default().body
+        );
+    }
+
+    #[test]
+    fn missing_font() {
+        assert!(font_definitions(&FontConfig::default()).is_ok());
+
+        let fonts = FontConfig {
+            files: vec!["/nonexistent/font.ttf".into()],
+            ..FontConfig::default()
+        };
+        assert!(font_definitions(&fonts)
+            .unwrap_err()
+            .contains("/nonexistent/font.ttf"));
+    }
+}
@@ -0,0 +1,306 @@
+use fuzzy_matcher::skim::SkimMatcherV2;
+use fuzzy_matcher::FuzzyMatcher;
+
+use crate::config::SkinTone;
+use crate::provider::{Action, Cancel, Provider, ResultItem};
+
+/// Generated by build.rs from the Unicode character names & CLDR emoji
+/// annotations in `data/`.
+static TABLE: &str = include_str!(concat!(env!("OUT_DIR"), "/unicode.txt"));
+
+/// Ranks searches above anything else, as they're asked for by prefix.
+const EXPLICIT_SCORE: i64 = 1000;
+
+/// At most this many characters are listed.
+const MAX_RESULTS: usize = 20;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Char {
+    text: &'static str,
+    /// Lowercase Unicode name, or the CLDR short name of emoji.
+    name: &'static str,
+    /// CLDR keywords separated by `|`, empty for non-emoji.
+    keywords: &'static str,
+    emoji: bool,
+    /// Whether the emoji takes a skin tone modifier.
+    tones: bool,
+}
+
+fn parse_table(table: &'static str) -> Vec<Char> {
+    table
+        .lines()
+        .filter_map(|line| {
+            let mut fields = line.split('\t');
+            let (text, name, keywords, flags) = (
+                fields.next()?,
+                fields.next()?,
+                fields.next()?,
+                fields.next()?,
+            );
+            Some(Char {
+                text,
+                name,
+                keywords,
+                emoji: flags.contains('e'),
+                tones: flags.contains('t'),
+            })
+        })
+        .collect()
+}
+
+/// What the input asks to search for.
+#[derive(Debug, PartialEq)]
+enum Search<'a> {
+    /// `emoji …`: emoji names & keywords.
+    Emoji(Vec<&'a str>, Option<SkinTone>),
+    /// `char …` or `unicode …`: the names of all characters.
+    Name(Vec<&'a str>, Option<SkinTone>),
+    /// `u+2192`: a single code point.
+    CodePoint(char),
+}
+
+impl<'a> Search<'a> {
+    fn parse(input: &'a str) -> Option<Self> {
+        let input = input.trim();
+        let (prefix, rest) = input.split_once(' ').unwrap_or((input, ""));
+
+        if let Some(hex) = prefix
+            .strip_prefix("u+")
+            .or_else(|| prefix.strip_prefix("U+"))
+        {
+            if rest.is_empty() && (1..=6).contains(&hex.len()) {
+                return u32::from_str_radix(hex, 16)
+                    .ok()
+                    .and_then(char::from_u32)
+                    .map(Search::CodePoint);
+            }
+            return None;
+        }
+
+        // Words like `tone3` pick a skin tone rather than being searched for.
+        let mut tone = None;
+        let mut words = vec![];
+        for word in rest.split_whitespace() {
+            match word
+                .strip_prefix("tone")
+                .and_then(|n| n.parse::<usize>().ok())
+                .and_then(|n| SkinTone::TONES.get(n.wrapping_sub(1)))
+            {
+                Some(t) => tone = Some(*t),
+                None => words.push(word),
+            }
+        }
+        if words.is_empty() {
+            return None;
+        }
+
+        match prefix {
+            "emoji" => Some(Search::Emoji(words, tone)),
+            "char" | "unicode" => Some(Search::Name(words, tone)),
+            _ => None,
+        }
+    }
+}
+
+/// Returns the text as `\u{…}` escapes.
+pub fn escape(text: &str) -> String {
+    text.chars()
+        .map(|c| format!("\\u{{{:X}}}", c as u32))
+        .collect()
+}
+
+/// Returns the code points of the text, like `U+1F44D U+1F3FD`.
+fn code_points(text: &str) -> String {
+    text.chars()
+        .map(|c| format!("U+{:04X}", c as u32))
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// Finds characters by name, emoji by their CLDR keywords, and code points,
+/// copying the chosen one.
+pub struct UnicodeProvider {
+    chars: Vec<Char>,
+    skin_tone: SkinTone,
+}
+
+impl UnicodeProvider {
+    pub fn new(skin_tone: SkinTone) -> Self {
+        Self {
+            chars: parse_table(TABLE),
+            skin_tone,
+        }
+    }
+
+    /// Scores how well every word matches the name or keywords of the character.
+    /// Matching the name counts for more, so characters named like the search
+    /// come before those which only mention it.
+    fn score(matcher: &SkimMatcherV2, c: &Char, words: &[&str]) -> Option<i64> {
+        let mut total = 0;
+        for word in words {
+            let name = matcher.fuzzy_match(c.name, word).map(|s| s * 4);
+            let keywords = c.keywords.split('|');
+            let keyword = keywords.filter_map(|k| matcher.fuzzy_match(k, word)).max();
+            total += name.max(keyword.map(|s| s * 3))?;
+        }
+        Some(total)
+    }
+
+    /// Returns the character's text, with the skin tone if it takes one.
+    fn toned(&self, c: &Char, tone: Option<SkinTone>) -> String {
+        let mut text = c.text.to_string();
+        let tone = tone.unwrap_or(self.skin_tone);
+        if let (true, Some(modifier)) = (c.tones, tone.modifier()) {
+            text.push(modifier);
+        }
+        text
+    }
+}
+
+fn result(text: String, name: &str, score: i64) -> ResultItem {
+    ResultItem {
+        title: name.to_string(),
+        subtitle: Some(code_points(&text)),
+        glyph: text.clone(),
+        score,
+        action: Action::Copy(text.clone()),
+        alt_action: Some(Action::Copy(escape(&text))),
+    }
+}
+
+impl Provider for UnicodeProvider {
+    fn name(&self) -> &'static str {
+        "unicode"
+    }
+
+    fn query(&mut self, matcher: &SkimMatcherV2, input: &str, _cancel: &Cancel) -> Vec<ResultItem> {
+        let (words, tone, emoji_only) = match Search::parse(input) {
+            Some(Search::CodePoint(c)) => {
+                let text = c.to_string();
+                let name = self.chars.iter().find(|ch| ch.text == text);
+                let name = name.map_or("unnamed character", |ch| ch.name);
+                return vec![result(text, name, EXPLICIT_SCORE)];
+            }
+            Some(Search::Emoji(words, tone)) => (words, tone, true),
+            Some(Search::Name(words, tone)) => (words, tone, false),
+            None => return vec![],
+        };
+
+        let mut scored: Vec<(i64, &Char)> = self
+            .chars
+            .iter()
+            .filter(|c| c.emoji || !emoji_only)
+            .filter_map(|c| Some((Self::score(matcher, c, &words)?, c)))
+            .collect();
+        // Shorter names match more closely when the scores are equal.
+        scored.sort_by_key(|(score, c)| (std::cmp::Reverse(*score), c.name.len()));
+        scored.truncate(MAX_RESULTS);
+
+        scored
+            .into_iter()
+            .enumerate()
+            .map(|(i, (_, c))| result(self.toned(c, tone), c.name, EXPLICIT_SCORE - i as i64))
+            .collect()
+    }
+
+    fn exclusive(&self, input: &str) -> bool {
+        Search::parse(input).is_some()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn table() {
+        let chars = parse_table(TABLE);
+        let find = |text: &str| *chars.iter().find(|c| c.text == text).unwrap();
+
+        assert_eq!(find("→").name, "rightwards arrow");
+        let thumbs = find("👍");
+        assert_eq!(thumbs.name, "thumbs up");
+        assert!(thumbs.keywords.split('|').any(|k| k == "thumbs up sign"));
+        assert!(thumbs.emoji && thumbs.tones);
+        let rocket = find("🚀");
+        assert!(rocket.emoji && !rocket.tones);
+        // Controls & CJK ranges don't have names to search.
+        assert!(!chars.iter().any(|c| c.text == "\n" || c.text == "一"));
+    }
+
+    #[test]
+    fn parse() {
+        assert_eq!(Search::parse("u+2192"), Some(Search::CodePoint('→')));
+        assert_eq!(Search::parse("U+1f44d"), Some(Search::CodePoint('👍')));
+        assert_eq!(
+            Search::parse("emoji thumbs tone2"),
+            Some(Search::Emoji(vec!["thumbs"], Some(SkinTone::MediumLight)))
+        );
+        assert_eq!(
+            Search::parse("char right arrow"),
+            Some(Search::Name(vec!["right", "arrow"], None))
+        );
+        for input in [
+            "u+d800",
+            "u+",
+            "u+1234567",
+            "emoji",
+            "emoji tone1",
+            "firefox",
+            "",
+        ] {
+            assert_eq!(Search::parse(input), None, "{:?}", input);
+        }
+    }
+
+    #[test]
+    fn escaped() {
+        assert_eq!(escape("→"), "\\u{2192}");
+        assert_eq!(escape("👍🏽"), "\\u{1F44D}\\u{1F3FD}");
+        assert_eq!(code_points("👍🏽"), "U+1F44D U+1F3FD");
+    }
+
+    #[test]
+    fn query() {
+        let mut p = UnicodeProvider::new(SkinTone::None);
+        let matcher = SkimMatcherV2::default();
+
+        let results = p.query(&matcher, "emoji thumbs up", &Cancel::default());
+        assert_eq!(results[0].title, "thumbs up");
+        assert_eq!(results[0].action, Action::Copy("👍".into()));
+        assert!(results.iter().all(|r| r.glyph.chars().count() <= 2));
+
+        let results = p.query(&matcher, "emoji thumbs up tone5", &Cancel::default());
+        assert_eq!(results[0].action, Action::Copy("👍🏿".into()));
+        assert_eq!(
+            results[0].alt_action,
+            Some(Action::Copy("\\u{1F44D}\\u{1F3FF}".into()))
+        );
+
+        let results = p.query(&matcher, "char rightwards arrow", &Cancel::default());
+        assert_eq!(results[0].title, "rightwards arrow");
+        // Only emoji are searched after `emoji`.
+        let results = p.query(&matcher, "emoji rightwards arrow", &Cancel::default());
+        assert!(!results.is_empty() && !results.iter().any(|r| r.glyph == "→"));
+
+        let results = p.query(&matcher, "u+2192", &Cancel::default());
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0].glyph, "→");
+
+        let results = p.query(&matcher, "u+4e00", &Cancel::default());
+        assert_eq!(results[0].action, Action::Copy("一".into()));
+
+        assert!(p.query(&matcher, "thumbs", &Cancel::default()).is_empty());
+    }
+
+    #[test]
+    fn default_tone() {
+        let mut p = UnicodeProvider::new(SkinTone::Medium);
+        let matcher = SkimMatcherV2::default();
+        let results = p.query(&matcher, "emoji clapping", &Cancel::default());
+        assert_eq!(results[0].action, Action::Copy("👏🏽".into()));
+        // The tone only applies to emoji which take one.
+        let results = p.query(&matcher, "emoji rocket", &Cancel::default());
+        assert_eq!(results[0].action, Action::Copy("🚀".into()));
+    }
+}
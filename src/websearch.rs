@@ -0,0 +1,172 @@
+use fuzzy_matcher::skim::SkimMatcherV2;
+
+use crate::config::SearchEngine;
+use crate::provider::{Action, Cancel, Opener, Provider, ResultItem};
+
+/// Ranks keyword & `?` searches above anything else the provider could return.
+const EXPLICIT_SCORE: i64 = 1000;
+
+/// Opens web searches, through a `?` prefix, an engine's keyword like `g rust`,
+/// or as a fallback when nothing else matches.
+pub struct WebSearchProvider {
+    engines: Vec<SearchEngine>,
+    opener: Opener,
+}
+
+impl WebSearchProvider {
+    pub fn new(engines: Vec<SearchEngine>) -> Self {
+        Self {
+            engines,
+            opener: Opener::default(),
+        }
+    }
+
+    /// Opens searches with the given opener instead of the default browser.
+    #[cfg(test)]
+    pub fn with_opener(mut self, opener: Opener) -> Self {
+        self.opener = opener;
+        self
+    }
+
+    /// Returns the engine & query the input explicitly asks to search for.
+    fn explicit<'a>(&self, input: &'a str) -> Option<(&SearchEngine, &'a str)> {
+        if let Some(query) = input.strip_prefix('?') {
+            let query = query.trim();
+            return Some((self.engines.first()?, query)).filter(|_| !query.is_empty());
+        }
+
+        let (keyword, query) = input.split_once(' ')?;
+        let query = query.trim();
+        let engine = self.engines.iter().find(|e| e.keyword == keyword)?;
+        Some((engine, query)).filter(|_| !query.is_empty())
+    }
+
+    fn result(&self, engine: &SearchEngine, query: &str, score: i64) -> ResultItem {
+        let url = engine
+            .url
+            .replace("{query}", &crate::percent::encode(query));
+        ResultItem {
+            title: format!("Search {} for “{}”", engine.name, query),
+            subtitle: Some(url.clone()),
+            glyph: engine.icon.clone(),
+            score,
+            action: Action::OpenUrl(url, self.opener.clone()),
+            alt_action: None,
+        }
+    }
+}
+
+impl Provider for WebSearchProvider {
+    fn name(&self) -> &'static str {
+        "web"
+    }
+
+    fn query(
+        &mut self,
+        _matcher: &SkimMatcherV2,
+        input: &str,
+        _cancel: &Cancel,
+    ) -> Vec<ResultItem> {
+        match self.explicit(input) {
+            Some((engine, query)) => vec![self.result(engine, query, EXPLICIT_SCORE)],
+            None => vec![],
+        }
+    }
+
+    fn exclusive(&self, input: &str) -> bool {
+        self.explicit(input).is_some()
+    }
+
+    fn fallback(&mut self, input: &str) -> Vec<ResultItem> {
+        let query = input.trim();
+        if query.is_empty() {
+            return vec![];
+        }
+
+        self.engines
+            .iter()
+            .enumerate()
+            .map(|(i, engine)| self.result(engine, query, -(i as i64)))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::WebSearchConfig;
+    use std::sync::{Arc, Mutex};
+
+    fn provider() -> (WebSearchProvider, Arc<Mutex<Vec<String>>>) {
+        let opened = Arc::new(Mutex::new(vec![]));
+        let opened2 = opened.clone();
+        let p = WebSearchProvider::new(WebSearchConfig::default().engines).with_opener(
+            Opener::new(move |url, _quit| opened2.lock().unwrap().push(url.to_string())),
+        );
+        (p, opened)
+    }
+
+    #[test]
+    fn keyword() {
+        let (mut p, opened) = provider();
+        let matcher = SkimMatcherV2::default();
+
+        assert!(p.exclusive("g rust enums"));
+        let results = p.query(&matcher, "g rust enums", &Cancel::default());
+        assert_eq!(results.len(), 1);
+        results[0].action.run(false);
+
+        assert!(p.exclusive("? what is 1+1"));
+        p.query(&matcher, "? what is 1+1", &Cancel::default())[0]
+            .action
+            .run(false);
+
+        assert_eq!(
+            *opened.lock().unwrap(),
+            vec![
+                "https://www.google.com/search?q=rust%20enums".to_string(),
+                "https://duckduckgo.com/?q=what%20is%201%2B1".to_string(),
+            ]
+        );
+    }
+
+    #[test]
+    fn not_explicit() {
+        let (mut p, _) = provider();
+        let matcher = SkimMatcherV2::default();
+        // A keyword needs a query after it, & other words aren't keywords.
+        let inputs = [
+            "firefox",
+            "g",
+            "g ",
+            "?",
+            "x rust",
+            "",
+            "gimp editor",
+            "!g rust",
+        ];
+        for input in inputs {
+            assert!(!p.exclusive(input), "{:?}", input);
+            assert!(
+                p.query(&matcher, input, &Cancel::default()).is_empty(),
+                "{:?}",
+                input
+            );
+        }
+    }
+
+    #[test]
+    fn fallback() {
+        let (mut p, opened) = provider();
+        assert!(p.fallback("  ").is_empty());
+
+        let results = p.fallback("nothing matches");
+        assert_eq!(results.len(), 3);
+        assert!(results[0].score > results[1].score);
+        results[2].action.run(false);
+        assert_eq!(
+            *opened.lock().unwrap(),
+            vec!["https://en.wikipedia.org/w/index.php?search=nothing%20matches".to_string()]
+        );
+    }
+}
@@ -0,0 +1,68 @@
+use egui::{Pos2, Vec2};
+
+/// A change to the native window, collected while drawing a frame and applied
+/// to the `eframe::Frame` once it's done.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum WindowCommand {
+    SetVisible(bool),
+    Focus,
+    Close,
+    SetSize(Vec2),
+    SetPos(Pos2),
+}
+
+impl WindowCommand {
+    pub fn apply(self, frame: &mut eframe::Frame) {
+        match self {
+            WindowCommand::SetVisible(visible) => frame.set_visible(visible),
+            WindowCommand::Focus => frame.focus(),
+            WindowCommand::Close => frame.close(),
+            WindowCommand::SetSize(size) => frame.set_window_size(size),
+            WindowCommand::SetPos(pos) => frame.set_window_pos(pos),
+        }
+    }
+}
+
+/// Returns where to put a window of the given size so it's centred on the
+/// monitor, then moved by the offset.
+pub fn centred_pos(monitor_size: Vec2, window_size: Vec2, offset: Vec2) -> Pos2 {
+    let pos = (monitor_size - window_size) / 2. + offset;
+    pos.max(Vec2::ZERO).to_pos2()
+}
+
+/// Returns the window height needed to show the given number of result rows
+/// beneath `above`, within the limits.
+pub fn fitted_height(above: f32, rows: usize, row_height: f32, min: f32, max: f32) -> f32 {
+    (above + rows as f32 * row_height).clamp(min, max.max(min))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn centred() {
+        let monitor = Vec2::new(1920., 1080.);
+        let window = Vec2::new(300., 260.);
+        assert_eq!(
+            centred_pos(monitor, window, Vec2::ZERO),
+            Pos2::new(810., 410.)
+        );
+        assert_eq!(
+            centred_pos(monitor, window, Vec2::new(0., -200.)),
+            Pos2::new(810., 210.)
+        );
+        // Never pushed off the top-left of the monitor.
+        assert_eq!(
+            centred_pos(monitor, window, Vec2::new(-2000., -2000.)),
+            Pos2::ZERO
+        );
+    }
+
+    #[test]
+    fn fitted() {
+        assert_eq!(fitted_height(60., 0, 36., 100., 600.), 100.);
+        assert_eq!(fitted_height(60., 5, 36., 100., 600.), 240.);
+        assert_eq!(fitted_height(60., 500, 36., 100., 600.), 600.);
+    }
+}
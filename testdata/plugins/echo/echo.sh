@@ -0,0 +1,25 @@
+#!/bin/sh
+# Example plugin, answering each query with a result which says it back.
+# Some queries misbehave, for testing: "crash", "hang" and "garbage". "hang"
+# isn't answered until the query "release" arrives, after it's been given up on.
+answer() {
+    id=$(printf '%s\n' "$1" | sed 's/.*"id":\([0-9]*\).*/\1/')
+    query=$(printf '%s\n' "$1" | sed 's/.*"query":"\(.*\)"}$/\1/')
+    printf '{"id":%s,"results":[{"title":"You said %s","subtitle":"echo","icon":"🔊","score":5,' "$id" "$query"
+    printf '"action":{"copy":"%s"},"alt_action":{"spawn":["echo","%s"]}}]}\n' "$query" "$query"
+}
+
+while IFS= read -r line; do
+    case "$line" in
+        *'"query":"crash"}') exit 1 ;;
+        *'"query":"hang"}')
+            while IFS= read -r next; do
+                case "$next" in *'"query":"release"}') break ;; esac
+            done
+            answer "$line"
+            line=$next
+            ;;
+        *'"query":"garbage"}') echo "this isn't JSON" ;;
+    esac
+    answer "$line"
+done